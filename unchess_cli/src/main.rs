@@ -4,6 +4,7 @@ use rustyline::{DefaultEditor, error::ReadlineError};
 
 use unchess_lib::{
     board::piece_list::PieceListBoard,
+    enums::DrawReason,
     error::ChessError,
     notation,
     simple_types::SimpleSquare,
@@ -163,6 +164,16 @@ impl Repl {
             unchess_lib::enums::BoardState::Check => println!("{}", "Check!".magenta().bold()),
             unchess_lib::enums::BoardState::Stalemate => println!("{}", "Stalemate!".bold()),
             unchess_lib::enums::BoardState::Checkmate => println!("{}", "Checkmate!".red().bold()),
+            unchess_lib::enums::BoardState::Draw(reason) => {
+                let reason = match reason {
+                    DrawReason::ThreefoldRepetition => "threefold repetition",
+                    DrawReason::FivefoldRepetition => "fivefold repetition",
+                    DrawReason::FiftyMoves => "the fifty move rule",
+                    DrawReason::SeventyFiveMoves => "the seventy-five move rule",
+                    DrawReason::InsufficientMaterial => "insufficient material",
+                };
+                println!("{}", format!("Draw by {reason}!").bold());
+            }
         }
         Ok(())
     }
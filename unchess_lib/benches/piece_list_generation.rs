@@ -49,6 +49,19 @@ fn generate_pchecked_moves(moves: &Vec<SimpleMove>) {
     }
 }
 
+fn branch_variations(board: &PieceListBoard) -> Vec<PieceListBoard> {
+    board
+        .all_legal_moves()
+        .unwrap()
+        .into_iter()
+        .map(|chess_move| {
+            let mut child = black_box(board).clone();
+            child.move_piece(chess_move).unwrap();
+            child
+        })
+        .collect()
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let mut moves: Vec<SimpleMove> = vec![];
     let pgn = pgn_to_moves(BYRNE_FISCHER_1956).unwrap();
@@ -63,7 +76,14 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("Unchecked moving", |b| b.iter(|| play_unchecked_moves(&moves)));
     c.bench_function("Legal move generation", |b| b.iter(|| generate_checked_moves(&moves)));
     c.bench_function("Pseudo-legal move generation", |b| {
-        b.iter(|| generate_pchecked_moves(&moves))
+        b.iter(|| generate_pchecked_moves(&moves));
+    });
+    let mut late_board = PieceListBoard::starting_board();
+    for chess_move in &moves[..moves.len() - 1] {
+        late_board.move_piece(*chess_move).unwrap();
+    }
+    c.bench_function("Variation tree branching", |b| {
+        b.iter(|| branch_variations(&late_board));
     });
 }
 
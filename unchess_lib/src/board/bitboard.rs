@@ -1,5 +1,1104 @@
 //! Bitboard representation of chess board
 //!
 //! Uses u64s with a bit to represent each square on the chess board, bit 0 representing square a1
-//! and bit 64 representing square h8. This is the most performant implementation of a chess board
+//! and bit 63 representing square h8. This is the most performant implementation of a chess board
 //! for almost all uses.
+
+use std::array;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _, ser::Error as _};
+
+#[cfg(feature = "serde")]
+use crate::board::SerializedBoard;
+use crate::board::history::{self, PositionHistory};
+use crate::diagram::{self, DiagramOptions};
+use crate::enums::{AmbiguousMove, BoardState, CastlingSide, DrawReason, MoveAction, PieceColour, PieceKind, Variant};
+use crate::error::ChessError;
+use crate::parser::{self, fen::Fen};
+use crate::simple_types::{SimpleMove, SimplePiece, SimpleSquare};
+use crate::square_set::knight_attacks_table;
+use crate::traits::{
+    ChessBoard, ChessMove as _, ChessPiece as _, ChessSquare as _, LegalMoveGenerator, PLegalMoveGenerator,
+};
+use crate::zobrist;
+
+/// Order of piece kinds within each colour's bitboards
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+/// Pieces a pawn can promote to, in the order their moves are generated
+const PROMOTIONS: [PieceKind; 4] = [PieceKind::Knight, PieceKind::Queen, PieceKind::Bishop, PieceKind::Rook];
+
+/// Light squares of the board, a1 being dark
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
+/// Ray directions as `(file, rank)` steps, the four that increase the square index first
+const DIRECTIONS: [(i8, i8); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+
+/// Indices into [`DIRECTIONS`] a rook slides along
+const ROOK_DIRECTIONS: [usize; 4] = [0, 2, 4, 6];
+
+/// Indices into [`DIRECTIONS`] a bishop slides along
+const BISHOP_DIRECTIONS: [usize; 4] = [1, 3, 5, 7];
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+/// Bitboards of the squares in each direction from each square up to the edge of the board
+const fn rays() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < DIRECTIONS.len() {
+        let mut index = 0;
+        while index < 64 {
+            let (mut file, mut rank) = ((index % 8) as i8, (index / 8) as i8);
+            loop {
+                file += DIRECTIONS[direction].0;
+                rank += DIRECTIONS[direction].1;
+                if file < 0 || file >= 8 || rank < 0 || rank >= 8 {
+                    break;
+                }
+                table[direction][index] |= 1 << (rank * 8 + file);
+            }
+            index += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+/// Bitboards of the squares a king attacks from each square
+const fn king_attacks() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        let mut direction = 0;
+        while direction < DIRECTIONS.len() {
+            let file = (index % 8) as i8 + DIRECTIONS[direction].0;
+            let rank = (index / 8) as i8 + DIRECTIONS[direction].1;
+            if 0 <= file && file < 8 && 0 <= rank && rank < 8 {
+                table[index] |= 1 << (rank * 8 + file);
+            }
+            direction += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+static RAYS: [[u64; 64]; 8] = rays();
+static KING_ATTACKS: [u64; 64] = king_attacks();
+
+/// Squares attacked by a piece on `index` sliding in `directions`, up to and including the first
+/// piece in `occupied` along each ray
+fn slider_attacks(index: usize, occupied: u64, directions: [usize; 4]) -> u64 {
+    let mut attacks = 0;
+    for direction in directions {
+        let ray = RAYS[direction][index];
+        let blockers = ray & occupied;
+        if blockers == 0 {
+            attacks |= ray;
+            continue;
+        }
+        // The nearest blocker is the lowest bit on rays increasing the index, the highest otherwise
+        let blocker = if direction < 4 {
+            blockers.trailing_zeros()
+        } else {
+            63 - blockers.leading_zeros()
+        };
+        attacks |= ray & !RAYS[direction][blocker as usize];
+    }
+    attacks
+}
+
+/// Squares attacked by `colour` pawns on `pawns`
+fn pawn_attacks(pawns: u64, colour: PieceColour) -> u64 {
+    match colour {
+        PieceColour::White => ((pawns & !FILE_A) << 7) | ((pawns & !FILE_H) << 9),
+        PieceColour::Black => ((pawns & !FILE_A) >> 9) | ((pawns & !FILE_H) >> 7),
+    }
+}
+
+/// Bitboard representation of chess board
+///
+/// One bitboard per colour and piece kind, laid out as in
+/// [`crate::board::piece_list::PieceListBoard::to_bitboards`], with sliding attacks found from
+/// precomputed rays. The position history is shared between boards branching from the same
+/// position, so cloning stays cheap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitBoard {
+    pieces: [u64; 12],
+    turn: PieceColour,
+    en_passant: Option<SimpleSquare>,
+    castling_rights: [bool; 4],
+    /// File of the rook each castling right castles with
+    castling_files: [u8; 4],
+    variant: Variant,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    history: PositionHistory,
+}
+
+impl ChessBoard for BitBoard {
+    type Square = SimpleSquare;
+    type Piece = SimplePiece;
+    type Move = SimpleMove;
+
+    fn from_fen(fen: &str) -> Result<Self, ChessError> {
+        let board = match parser::fen::fen(fen) {
+            Ok((_, parsed)) => Self::from(parsed),
+            Err(_) => return Err(ChessError::InvalidFEN(fen.to_string())),
+        };
+        if board.phantom_en_passant().is_some() {
+            return Err(ChessError::InvalidFEN(fen.to_string()));
+        }
+        Ok(board)
+    }
+
+    fn from_fen_lenient(fen: &str) -> Result<Self, ChessError> {
+        let mut board = match parser::fen::board_lenient(fen) {
+            Ok((_, parsed)) => Self::from(parsed),
+            Err(_) => return Err(ChessError::InvalidFEN(fen.to_string())),
+        };
+        if board.phantom_en_passant().is_some() {
+            board.en_passant = None;
+        }
+        Ok(board)
+    }
+
+    fn to_fen(&self) -> Result<String, ChessError> {
+        Ok(self.as_fen_str())
+    }
+
+    fn get_piece(&self, square: SimpleSquare) -> Result<SimplePiece, ChessError> {
+        match self.piece_at(square.to_index()) {
+            Some((colour, kind)) => Ok(SimplePiece::new(kind, colour)),
+            None => Err(ChessError::PieceNotFound(square)),
+        }
+    }
+
+    fn all_pieces(&self) -> impl IntoIterator<Item = SimplePiece> {
+        [PieceColour::White, PieceColour::Black]
+            .into_iter()
+            .flat_map(|colour| KINDS.map(|kind| (colour, kind)))
+            .flat_map(|(colour, kind)| {
+                let count = self.pieces[Self::index(colour, kind)].count_ones() as usize;
+                vec![SimplePiece::new(kind, colour); count]
+            })
+    }
+
+    fn move_piece(&mut self, chess_move: SimpleMove) -> Result<(), ChessError> {
+        let hash = self.zobrist_hash();
+        self.make_move(chess_move)?;
+        self.history.push(hash);
+        Ok(())
+    }
+}
+
+impl PLegalMoveGenerator for BitBoard {
+    fn all_plegal_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        self.king_index(PieceColour::White)?;
+        self.king_index(PieceColour::Black)?;
+        let mut moves = Vec::with_capacity(64);
+        self.all_moves(&mut moves);
+        Ok(moves)
+    }
+
+    fn piece_plegal_moves(&self, square: SimpleSquare) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let piece = self.get_piece(square)?;
+        let mut moves = vec![];
+        if piece.colour() == self.turn {
+            self.piece_moves(square.to_index(), piece.kind(), &mut moves);
+        }
+        Ok(moves)
+    }
+
+    fn is_move_plegal(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        Ok(self
+            .piece_plegal_moves(chess_move.src())?
+            .into_iter()
+            .any(|plegal_move| plegal_move == chess_move))
+    }
+
+    fn move_piece_plegal(&mut self, chess_move: SimpleMove) -> Result<(), ChessError> {
+        if self.is_move_plegal(chess_move)? {
+            self.move_piece(chess_move)
+        } else {
+            Err(ChessError::IllegalMove(chess_move))
+        }
+    }
+}
+
+impl LegalMoveGenerator for BitBoard {
+    fn all_legal_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut moves = vec![];
+        for chess_move in self.all_plegal_moves()? {
+            if self.keeps_king_safe(chess_move)? {
+                moves.push(chess_move);
+            }
+        }
+        Ok(moves)
+    }
+
+    fn piece_legal_moves(&self, square: SimpleSquare) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut moves = vec![];
+        for chess_move in self.piece_plegal_moves(square)? {
+            if self.keeps_king_safe(chess_move)? {
+                moves.push(chess_move);
+            }
+        }
+        Ok(moves)
+    }
+
+    fn legal_moves_for(&self, colour: PieceColour) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut board = self.clone();
+        if colour != self.turn {
+            board.turn = colour;
+            board.en_passant = None;
+        }
+        Ok(board.all_legal_moves()?.into_iter().collect::<Vec<SimpleMove>>())
+    }
+
+    fn is_move_legal(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        Ok(self.is_move_plegal(chess_move)? && self.keeps_king_safe(chess_move)?)
+    }
+
+    fn gives_check(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        let mut board = self.without_history();
+        board.make_move(chess_move)?;
+        board.king_in_check(board.turn)
+    }
+
+    fn checking_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut moves = vec![];
+        for chess_move in self.all_legal_moves()? {
+            if self.gives_check(chess_move)? {
+                moves.push(chess_move);
+            }
+        }
+        Ok(moves)
+    }
+
+    fn move_piece_legal(&mut self, chess_move: SimpleMove) -> Result<(), ChessError> {
+        if self.is_move_legal(chess_move)? {
+            self.move_piece(chess_move)
+        } else {
+            Err(ChessError::IllegalMove(chess_move))
+        }
+    }
+
+    fn state(&self) -> Result<BoardState, ChessError> {
+        self.king_index(PieceColour::White)?;
+        self.king_index(PieceColour::Black)?;
+        let mut moves = Vec::with_capacity(64);
+        self.all_moves(&mut moves);
+        let mut has_legal_move = false;
+        for chess_move in moves {
+            if self.keeps_king_safe(chess_move)? {
+                has_legal_move = true;
+                break;
+            }
+        }
+        match (has_legal_move, self.king_in_check(self.turn)?, self.draw_reason()) {
+            (false, true, _) => Ok(BoardState::Checkmate),
+            (false, false, _) => Ok(BoardState::Stalemate),
+            (true, _, Some(reason)) => Ok(BoardState::Draw(reason)),
+            (true, true, None) => Ok(BoardState::Check),
+            (true, false, None) => Ok(BoardState::Normal),
+        }
+    }
+
+    fn draw_reason(&self) -> Option<DrawReason> {
+        history::draw_reason(
+            self.history.repetitions(self.zobrist_hash(), self.halfmove_clock),
+            self.halfmove_clock,
+            self.insufficient_material(),
+        )
+    }
+
+    fn disambiguate_move(&self, chess_move: AmbiguousMove) -> Result<SimpleMove, ChessError> {
+        match chess_move {
+            AmbiguousMove::Normal { .. } => self.disambiguate_normal(chess_move),
+            AmbiguousMove::Castle { side, .. } => {
+                let right = Self::CASTLING_RIGHTS
+                    .iter()
+                    .position(|&right| right == (self.turn, side))
+                    .unwrap_or_default();
+                let (king_from, king_to, rook_from, _) = self.castling_squares(right);
+                match self.variant {
+                    Variant::Standard => Ok(SimpleMove::new(king_from, king_to, None)),
+                    Variant::Chess960 => Ok(SimpleMove::new(king_from, rook_from, None)),
+                }
+            }
+            AmbiguousMove::Null => Err(ChessError::ImpossibleMove(chess_move)),
+        }
+    }
+}
+
+/// Serialized as FEN and variant, e.g. `{"fen":"8/8/8/8/8/8/8/K6k w - - 0 1","variant":"Standard"}`,
+/// so the position history is lost
+#[cfg(feature = "serde")]
+impl Serialize for BitBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedBoard {
+            fen: self.to_fen().map_err(S::Error::custom)?,
+            variant: self.variant,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BitBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedBoard::deserialize(deserializer)?;
+        let mut board = Self::from_fen(&serialized.fen).map_err(D::Error::custom)?;
+        board.set_variant(serialized.variant);
+        Ok(board)
+    }
+}
+
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&diagram::to_diagram(self, &DiagramOptions::default()))
+    }
+}
+
+impl From<Fen> for BitBoard {
+    fn from(value: Fen) -> Self {
+        let mut pieces = [0; 12];
+        for (row, rank) in value.layout.iter().enumerate() {
+            for (file, piece) in rank.iter().enumerate() {
+                if let Some(piece) = piece {
+                    let square = SimpleSquare::new(file as u8, 7 - row as u8);
+                    pieces[Self::index(piece.colour(), piece.kind())] |= 1 << square.to_index();
+                }
+            }
+        }
+        let variant = if value.needs_chess960() {
+            Variant::Chess960
+        } else {
+            Variant::Standard
+        };
+        Self {
+            pieces,
+            turn: value.turn,
+            en_passant: value.en_passant,
+            castling_rights: value.castling_rights,
+            castling_files: value.castling_files,
+            variant,
+            halfmove_clock: value.halfmove_clock,
+            fullmove_number: value.fullmove_number,
+            history: PositionHistory::default(),
+        }
+    }
+}
+
+impl From<&BitBoard> for Fen {
+    fn from(value: &BitBoard) -> Self {
+        let mut layout: Box<[[Option<SimplePiece>; 8]; 8]> = Box::new([[None; 8]; 8]);
+        for (row, rank) in layout.iter_mut().enumerate() {
+            for (file, piece) in rank.iter_mut().enumerate() {
+                *piece = value.get_piece(SimpleSquare::new(file as u8, 7 - row as u8)).ok();
+            }
+        }
+        Self {
+            layout,
+            turn: value.turn,
+            castling_rights: value.castling_rights,
+            castling_files: value.castling_files,
+            en_passant: value.en_passant,
+            halfmove_clock: value.halfmove_clock,
+            fullmove_number: value.fullmove_number,
+        }
+    }
+}
+
+impl BitBoard {
+    /// Colour and side of each castling right, in the order of the FEN castling field
+    const CASTLING_RIGHTS: [(PieceColour, CastlingSide); 4] = [
+        (PieceColour::White, CastlingSide::KingSide),
+        (PieceColour::White, CastlingSide::QueenSide),
+        (PieceColour::Black, CastlingSide::KingSide),
+        (PieceColour::Black, CastlingSide::QueenSide),
+    ];
+
+    /// Print self as fen string
+    pub fn as_fen_str(&self) -> String {
+        Fen::from(self).to_str()
+    }
+
+    /// Rules the board is played under
+    ///
+    /// Boards loaded from a FEN are Chess960 if their castling rights need it, as for
+    /// [`crate::board::piece_list::PieceListBoard::variant`].
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Play under the rules of `variant`
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Chess960 starting position numbered `number` in Scharnagl's numbering, from 0 to 959
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidChess960Position`] if `number` is over 959
+    pub fn chess960_starting_board(number: u16) -> Result<Self, ChessError> {
+        let mut board = Self::from(Fen::chess960(number)?);
+        board.variant = Variant::Chess960;
+        Ok(board)
+    }
+
+    /// Zobrist hash of the position
+    ///
+    /// Equal to [`crate::board::piece_list::PieceListBoard::zobrist_hash`] for the same position.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (bitboard, mut pieces) in self.pieces.into_iter().enumerate() {
+            let colour = if bitboard < 6 {
+                PieceColour::White
+            } else {
+                PieceColour::Black
+            };
+            let piece = SimplePiece::new(KINDS[bitboard % 6], colour);
+            while pieces != 0 {
+                let square = SimpleSquare::from_index(pieces.trailing_zeros() as u8);
+                pieces &= pieces - 1;
+                hash ^= zobrist::piece_key(&piece, &square);
+            }
+        }
+        if self.turn == PieceColour::White {
+            hash ^= zobrist::turn_key();
+        }
+        for (right, (colour, side)) in Self::CASTLING_RIGHTS.into_iter().enumerate() {
+            if self.castling_rights[right] {
+                hash ^= zobrist::castling_key(colour, side);
+            }
+        }
+        if let Some(en_passant) = self.en_passant {
+            hash ^= zobrist::en_passant_key(en_passant.file());
+        }
+        hash
+    }
+
+    /// Return true if neither side could checkmate by any series of legal moves
+    ///
+    /// True with only kings and at most one knight or bishop left, or if every other piece is a
+    /// bishop on the same coloured squares.
+    pub fn insufficient_material(&self) -> bool {
+        let bishops = self.pieces[Self::index(PieceColour::White, PieceKind::Bishop)]
+            | self.pieces[Self::index(PieceColour::Black, PieceKind::Bishop)];
+        let kings = self.pieces[Self::index(PieceColour::White, PieceKind::King)]
+            | self.pieces[Self::index(PieceColour::Black, PieceKind::King)];
+        let knights = self.pieces[Self::index(PieceColour::White, PieceKind::Knight)]
+            | self.pieces[Self::index(PieceColour::Black, PieceKind::Knight)];
+        let others = self.occupied() & !kings;
+        if others.count_ones() <= 1 {
+            return others & !(bishops | knights) == 0;
+        }
+        others == bishops && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /// En passant square if no pawn of the side not to move could have just double pushed past it,
+    /// or no pawn of the side to move stands beside the pushed pawn to take it
+    fn phantom_en_passant(&self) -> Option<SimpleSquare> {
+        let square = self.en_passant?;
+        let (origin_rank, en_passant_rank, pushed_rank) = match self.turn {
+            PieceColour::White => (6, 5, 4),
+            PieceColour::Black => (1, 2, 3),
+        };
+        let pushed = SimpleSquare::new(square.file(), pushed_rank);
+        let origin = SimpleSquare::new(square.file(), origin_rank);
+        let takers =
+            pawn_attacks(1 << square.to_index(), !self.turn) & self.pieces[Self::index(self.turn, PieceKind::Pawn)];
+        let possible = square.rank() == en_passant_rank
+            && self.piece_at(pushed.to_index()) == Some((!self.turn, PieceKind::Pawn))
+            && self.piece_at(square.to_index()).is_none()
+            && self.piece_at(origin.to_index()).is_none()
+            && takers != 0;
+        (!possible).then_some(square)
+    }
+
+    /// Copy of the board without its position history, for looking ahead without draw detection
+    fn without_history(&self) -> Self {
+        Self {
+            history: PositionHistory::default(),
+            ..*self
+        }
+    }
+
+    /// Play `chess_move` without recording the position it was played from
+    fn make_move(&mut self, chess_move: SimpleMove) -> Result<(), ChessError> {
+        let (src, dest) = (chess_move.src(), chess_move.dest());
+        let Some((colour, kind)) = self.piece_at(src.to_index()) else {
+            return Err(ChessError::PieceNotFound(src));
+        };
+        // Found before anything moves, since in Chess960 they depend on where the king stands
+        let rights_squares: [[SimpleSquare; 2]; 4] = array::from_fn(|right| {
+            let (king_from, _, rook_from, _) = self.castling_squares(right);
+            [king_from, rook_from]
+        });
+        let captured = if self.variant == Variant::Chess960
+            && kind == PieceKind::King
+            && self.pieces[Self::index(colour, PieceKind::Rook)] & (1 << dest.to_index()) != 0
+        {
+            self.castle_chess960(colour, src, dest);
+            false
+        } else {
+            self.move_pieces(colour, kind, chess_move)?
+        };
+
+        self.halfmove_clock = if captured || kind == PieceKind::Pawn {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        // Only kept if a pawn could take, as on PieceListBoard
+        self.en_passant = if kind == PieceKind::Pawn && src.rank().abs_diff(dest.rank()) == 2 {
+            let skipped = SimpleSquare::new(src.file(), u8::midpoint(src.rank(), dest.rank()));
+            let takers =
+                pawn_attacks(1 << skipped.to_index(), colour) & self.pieces[Self::index(!colour, PieceKind::Pawn)];
+            (takers != 0).then_some(skipped)
+        } else {
+            None
+        };
+        // Moving a king or rook from its starting square, or taking a rook on it, loses the right
+        for (right, squares) in rights_squares.iter().enumerate() {
+            if squares.iter().any(|&square| square == src || square == dest) {
+                self.castling_rights[right] = false;
+            }
+        }
+        self.turn = !self.turn;
+        if self.turn == PieceColour::White {
+            self.fullmove_number += 1;
+        }
+        Ok(())
+    }
+
+    /// Move the king on `src` and the rook on `dest` it takes to their castled squares
+    fn castle_chess960(&mut self, colour: PieceColour, src: SimpleSquare, dest: SimpleSquare) {
+        let side = if dest.file() > src.file() {
+            CastlingSide::KingSide
+        } else {
+            CastlingSide::QueenSide
+        };
+        let (_, king_to, _, rook_to) = side.castle_squares_from(colour, src.file(), dest.file());
+        // Either piece may land on the other's square, so both are lifted before either is placed
+        let (king, rook) = (
+            Self::index(colour, PieceKind::King),
+            Self::index(colour, PieceKind::Rook),
+        );
+        self.pieces[king] &= !(1 << src.to_index());
+        self.pieces[rook] &= !(1 << dest.to_index());
+        self.pieces[king] |= 1 << king_to.to_index();
+        self.pieces[rook] |= 1 << rook_to.to_index();
+    }
+
+    /// Move pieces for any move but Chess960 castling, returning whether a piece was captured
+    fn move_pieces(
+        &mut self,
+        colour: PieceColour,
+        kind: PieceKind,
+        chess_move: SimpleMove,
+    ) -> Result<bool, ChessError> {
+        let (src, dest) = (chess_move.src(), chess_move.dest());
+        let dest_bit = 1 << dest.to_index();
+        let en_passant_pawn = match self.en_passant {
+            Some(en_passant) if kind == PieceKind::Pawn && en_passant == dest && src.file() != dest.file() => {
+                let taken = SimpleSquare::new(dest.file(), src.rank());
+                if self.pieces[Self::index(!colour, PieceKind::Pawn)] & (1 << taken.to_index()) == 0 {
+                    return Err(ChessError::InvalidBoard(format!(
+                        "En passant square present at {en_passant} but no pawn to take at {taken}"
+                    )));
+                }
+                Some(taken)
+            }
+            _ => None,
+        };
+
+        let captured = self.occupied() & dest_bit != 0;
+        for bitboard in &mut self.pieces {
+            *bitboard &= !dest_bit;
+        }
+        self.pieces[Self::index(colour, kind)] &= !(1 << src.to_index());
+        self.pieces[Self::index(colour, chess_move.promote_to().unwrap_or(kind))] |= dest_bit;
+        if let Some(taken) = en_passant_pawn {
+            self.pieces[Self::index(!colour, PieceKind::Pawn)] &= !(1 << taken.to_index());
+        }
+        if kind == PieceKind::King && src.file().abs_diff(dest.file()) == 2 {
+            let side = if dest.file() > src.file() {
+                CastlingSide::KingSide
+            } else {
+                CastlingSide::QueenSide
+            };
+            let (_, _, rook_from, rook_to) = side.castle_squares(colour);
+            let rooks = &mut self.pieces[Self::index(colour, PieceKind::Rook)];
+            if *rooks & (1 << rook_from.to_index()) != 0 {
+                *rooks ^= (1 << rook_from.to_index()) | (1 << rook_to.to_index());
+            }
+        }
+        Ok(captured)
+    }
+
+    /// Squares involved in castling with the castling right at `right`, as for
+    /// [`CastlingSide::castle_squares_from`]
+    ///
+    /// In Chess960 the king castles from wherever it stands on its back rank.
+    fn castling_squares(&self, right: usize) -> (SimpleSquare, SimpleSquare, SimpleSquare, SimpleSquare) {
+        let (colour, side) = Self::CASTLING_RIGHTS[right];
+        let (standard_king, _, _, _) = side.castle_squares(colour);
+        let king_file = match self.variant {
+            Variant::Standard => standard_king.file(),
+            Variant::Chess960 => {
+                let kings = self.pieces[Self::index(colour, PieceKind::King)] & (0xFF << (8 * standard_king.rank()));
+                if kings == 0 {
+                    standard_king.file()
+                } else {
+                    (kings.trailing_zeros() % 8) as u8
+                }
+            }
+        };
+        side.castle_squares_from(colour, king_file, self.castling_files[right])
+    }
+
+    fn index(colour: PieceColour, kind: PieceKind) -> usize {
+        let offset = match colour {
+            PieceColour::White => 0,
+            PieceColour::Black => 6,
+        };
+        offset + KINDS.iter().position(|&other| other == kind).unwrap_or_default()
+    }
+
+    fn colour_occupied(&self, colour: PieceColour) -> u64 {
+        let offset = Self::index(colour, PieceKind::Pawn);
+        self.pieces[offset..offset + 6]
+            .iter()
+            .fold(0, |occupied, bitboard| occupied | bitboard)
+    }
+
+    fn occupied(&self) -> u64 {
+        self.pieces.iter().fold(0, |occupied, bitboard| occupied | bitboard)
+    }
+
+    fn piece_at(&self, index: u8) -> Option<(PieceColour, PieceKind)> {
+        let bitboard = self.pieces.iter().position(|bitboard| bitboard & (1 << index) != 0)?;
+        let colour = if bitboard < 6 {
+            PieceColour::White
+        } else {
+            PieceColour::Black
+        };
+        Some((colour, KINDS[bitboard % 6]))
+    }
+
+    fn king_index(&self, colour: PieceColour) -> Result<usize, ChessError> {
+        let kings = self.pieces[Self::index(colour, PieceKind::King)];
+        if kings.is_power_of_two() {
+            Ok(kings.trailing_zeros() as usize)
+        } else {
+            Err(ChessError::InvalidBoard(format!(
+                "Number of kings of colour {colour:?} on the board not equal to one"
+            )))
+        }
+    }
+
+    /// Check if the square at `index` is attacked by `colour`'s opponent
+    fn under_attack(&self, index: usize, colour: PieceColour) -> bool {
+        let theirs = |kind| self.pieces[Self::index(!colour, kind)];
+        let occupied = self.occupied();
+        let diagonal = theirs(PieceKind::Bishop) | theirs(PieceKind::Queen);
+        let straight = theirs(PieceKind::Rook) | theirs(PieceKind::Queen);
+        knight_attacks_table()[index] & theirs(PieceKind::Knight) != 0
+            || KING_ATTACKS[index] & theirs(PieceKind::King) != 0
+            || pawn_attacks(1 << index, colour) & theirs(PieceKind::Pawn) != 0
+            || slider_attacks(index, occupied, BISHOP_DIRECTIONS) & diagonal != 0
+            || slider_attacks(index, occupied, ROOK_DIRECTIONS) & straight != 0
+    }
+
+    /// Check if any square of `squares` is attacked by `colour`'s opponent
+    fn any_attacked(&self, mut squares: u64, colour: PieceColour) -> bool {
+        while squares != 0 {
+            if self.under_attack(squares.trailing_zeros() as usize, colour) {
+                return true;
+            }
+            squares &= squares - 1;
+        }
+        false
+    }
+
+    fn king_in_check(&self, colour: PieceColour) -> Result<bool, ChessError> {
+        Ok(self.under_attack(self.king_index(colour)?, colour))
+    }
+
+    /// Check if playing `chess_move` leaves the mover's king out of check
+    fn keeps_king_safe(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        let mut board = self.without_history();
+        board.make_move(chess_move)?;
+        Ok(!board.king_in_check(self.turn)?)
+    }
+
+    /// Add the pseudo-legal moves of the side to move to `moves`, even if the game is drawn
+    fn all_moves(&self, moves: &mut Vec<SimpleMove>) {
+        for kind in KINDS {
+            let mut pieces = self.pieces[Self::index(self.turn, kind)];
+            while pieces != 0 {
+                let index = pieces.trailing_zeros() as u8;
+                pieces &= pieces - 1;
+                self.piece_moves(index, kind, moves);
+            }
+        }
+    }
+
+    /// Add the pseudo-legal moves of the side to move's `kind` on `index` to `moves`
+    fn piece_moves(&self, index: u8, kind: PieceKind, moves: &mut Vec<SimpleMove>) {
+        let square = usize::from(index);
+        let occupied = self.occupied();
+        let own = self.colour_occupied(self.turn);
+        let targets = match kind {
+            PieceKind::Pawn => return self.pawn_moves(index, moves),
+            PieceKind::Knight => knight_attacks_table()[square],
+            PieceKind::Bishop => slider_attacks(square, occupied, BISHOP_DIRECTIONS),
+            PieceKind::Rook => slider_attacks(square, occupied, ROOK_DIRECTIONS),
+            PieceKind::Queen => {
+                slider_attacks(square, occupied, BISHOP_DIRECTIONS) | slider_attacks(square, occupied, ROOK_DIRECTIONS)
+            }
+            PieceKind::King => {
+                self.castle_moves(index, moves);
+                KING_ATTACKS[square]
+            }
+        };
+        push_moves(index, targets & !own, false, moves);
+    }
+
+    fn pawn_moves(&self, index: u8, moves: &mut Vec<SimpleMove>) {
+        let pawn: u64 = 1 << index;
+        let empty = !self.occupied();
+        let (single, start_rank) = match self.turn {
+            PieceColour::White => ((pawn << 8) & empty, 1),
+            PieceColour::Black => ((pawn >> 8) & empty, 6),
+        };
+        let double = match self.turn {
+            PieceColour::White if index / 8 == start_rank => (single << 8) & empty,
+            PieceColour::Black if index / 8 == start_rank => (single >> 8) & empty,
+            _ => 0,
+        };
+        let en_passant = self.en_passant.map_or(0, |square| 1 << square.to_index());
+        let takes = pawn_attacks(pawn, self.turn) & (self.colour_occupied(!self.turn) | en_passant);
+        push_moves(index, single | double | takes, true, moves);
+    }
+
+    fn castle_moves(&self, index: u8, moves: &mut Vec<SimpleMove>) {
+        let occupied = self.occupied();
+        for (right, (colour, side)) in Self::CASTLING_RIGHTS.into_iter().enumerate() {
+            let (king_from, king_to, rook_from, rook_to) = self.castling_squares(right);
+            if colour != self.turn
+                || !self.castling_rights[right]
+                || king_from.to_index() != index
+                || self.pieces[Self::index(colour, PieceKind::Rook)] & (1 << rook_from.to_index()) == 0
+            {
+                continue;
+            }
+            if self.variant == Variant::Chess960 {
+                // Both pieces' paths must be empty apart from the two of them, but only the king's
+                // must be safe
+                let castlers = (1 << king_from.to_index()) | (1 << rook_from.to_index());
+                let king_path = span(king_from, king_to);
+                if (king_path | span(rook_from, rook_to)) & occupied & !castlers == 0
+                    && !self.any_attacked(king_path, colour)
+                {
+                    moves.push(SimpleMove::new(king_from, rook_from, None));
+                }
+                continue;
+            }
+            // Every square between the king and rook must be empty, but only those the king
+            // passes through must be safe
+            let towards_rook = match side {
+                CastlingSide::KingSide => 2,
+                CastlingSide::QueenSide => 6,
+            };
+            let rook = usize::from(rook_from.to_index());
+            let between = RAYS[towards_rook][usize::from(index)] & !RAYS[towards_rook][rook] & !(1 << rook);
+            if between & occupied == 0
+                && [king_from, rook_to, king_to]
+                    .iter()
+                    .all(|square| !self.under_attack(usize::from(square.to_index()), colour))
+            {
+                moves.push(SimpleMove::new(king_from, king_to, None));
+            }
+        }
+    }
+
+    fn disambiguate_normal(&self, chess_move: AmbiguousMove) -> Result<SimpleMove, ChessError> {
+        let AmbiguousMove::Normal {
+            piece_kind,
+            src_file,
+            src_rank,
+            takes,
+            dest,
+            promote_to,
+            action,
+        } = chess_move
+        else {
+            return Err(ChessError::ImpossibleMove(chess_move));
+        };
+        let mut matches = vec![];
+        for legal_move in self.all_legal_moves()? {
+            let is_match = self.get_piece(legal_move.src())?.kind() == piece_kind
+                && src_file.is_none_or(|file| legal_move.src().file() == file)
+                && src_rank.is_none_or(|rank| legal_move.src().rank() == rank)
+                && (!takes
+                    || self.get_piece(legal_move.dest()).is_ok()
+                    || (piece_kind == PieceKind::Pawn && Some(legal_move.dest()) == self.en_passant))
+                && legal_move.dest() == dest
+                && legal_move.promote_to() == promote_to;
+            if !is_match {
+                continue;
+            }
+            // Checked apart from the board state, which reports a check into a draw as the draw
+            if let Some(action) = action {
+                let mut board = self.without_history();
+                board.make_move(legal_move)?;
+                let checkmate =
+                    board.king_in_check(board.turn)? && board.all_legal_moves()?.into_iter().next().is_none();
+                let is_action = match action {
+                    MoveAction::Check => board.king_in_check(board.turn)? && !checkmate,
+                    MoveAction::Checkmate => checkmate,
+                };
+                if !is_action {
+                    continue;
+                }
+            }
+            matches.push(legal_move);
+        }
+        match matches[..] {
+            [] => Err(ChessError::ImpossibleMove(chess_move)),
+            [legal_move] => Ok(legal_move),
+            _ => Err(ChessError::AmbiguousMove(chess_move)),
+        }
+    }
+}
+
+/// Squares from `from` to `to` inclusive, which must share a rank
+fn span(from: SimpleSquare, to: SimpleSquare) -> u64 {
+    let (low, high) = (from.to_index().min(to.to_index()), from.to_index().max(to.to_index()));
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
+}
+
+/// Add a move from `index` to each square of `targets` to `moves`, with every promotion for pawns
+/// reaching the back rank if `pawn`
+fn push_moves(index: u8, mut targets: u64, pawn: bool, moves: &mut Vec<SimpleMove>) {
+    let src = SimpleSquare::from_index(index);
+    while targets != 0 {
+        let dest = SimpleSquare::from_index(targets.trailing_zeros() as u8);
+        targets &= targets - 1;
+        if pawn && (dest.rank() == 0 || dest.rank() == 7) {
+            moves.extend(PROMOTIONS.map(|kind| SimpleMove::new(src, dest, Some(kind))));
+        } else {
+            moves.push(SimpleMove::new(src, dest, None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::piece_list::{self, PieceListBoard, tests::square};
+    use proptest::prelude::*;
+
+    #[test]
+    fn perft_known_counts() {
+        let board = BitBoard::starting_board();
+        assert_eq!(board.perft(0).unwrap(), 1);
+        assert_eq!(board.perft(4).unwrap(), 197_281);
+        let kiwipete =
+            BitBoard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(kiwipete.perft(3).unwrap(), 97_862);
+        let endgame = BitBoard::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(endgame.perft(4).unwrap(), 43_238);
+        let promotions =
+            BitBoard::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(promotions.perft(3).unwrap(), 9_467);
+        let discovered = BitBoard::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        assert_eq!(discovered.perft(3).unwrap(), 62_379);
+        // Drawn by insufficient material, but the moves are still counted
+        let drawn = BitBoard::from_fen("8/8/8/8/8/8/8/KN5k w - - 0 1").unwrap();
+        assert_eq!(drawn.perft(1).unwrap(), 5);
+    }
+
+    #[test]
+    fn special_moves() {
+        let mut board = BitBoard::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        board
+            .move_piece_legal(SimpleMove::new(square("e5"), square("d6"), None))
+            .unwrap();
+        assert_eq!(board.as_fen_str(), "r3k2r/1P6/3P4/8/8/8/8/R3K2R b KQkq - 0 1");
+        board
+            .move_piece_legal(SimpleMove::new(square("e8"), square("g8"), None))
+            .unwrap();
+        assert_eq!(board.as_fen_str(), "r4rk1/1P6/3P4/8/8/8/8/R3K2R w KQ - 1 2");
+        assert!(
+            board
+                .is_move_legal(SimpleMove::new(square("e1"), square("c1"), None))
+                .unwrap()
+        );
+        assert!(
+            !board
+                .is_move_legal(SimpleMove::new(square("e1"), square("e3"), None))
+                .unwrap()
+        );
+        let promotion = board.disambiguate_move_pgn("b8=Q").unwrap();
+        board.move_piece_legal(promotion).unwrap();
+        assert_eq!(board.as_fen_str(), "rQ3rk1/8/3P4/8/8/8/8/R3K2R b KQ - 0 2");
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+        assert!(matches!(
+            board.disambiguate_move_pgn("Rxb8"),
+            Err(ChessError::AmbiguousMove(_))
+        ));
+        assert_eq!(
+            board.disambiguate_move_pgn("Raxb8").unwrap(),
+            SimpleMove::new(square("a8"), square("b8"), None)
+        );
+        board.disambiguate_move_pgn("Kd7").unwrap_err();
+    }
+
+    #[test]
+    fn invalid_boards() {
+        let no_king = BitBoard::from_fen("8/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(matches!(no_king.all_legal_moves(), Err(ChessError::InvalidBoard(_))));
+        let board = BitBoard::starting_board();
+        let square = SimpleSquare::from_pgn_str("e4").unwrap();
+        assert!(matches!(board.get_piece(square), Err(ChessError::PieceNotFound(_))));
+    }
+
+    #[test]
+    fn phantom_en_passant() {
+        for fen in [
+            "4k3/8/8/8/3p4/8/8/4K3 b - e3 0 1",
+            "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+        ] {
+            assert!(matches!(BitBoard::from_fen(fen), Err(ChessError::InvalidFEN(_))));
+            let board = BitBoard::from_fen_lenient(fen).unwrap();
+            assert_eq!(
+                board.as_fen_str(),
+                PieceListBoard::from_fen_lenient(fen).unwrap().as_fen_str().unwrap()
+            );
+            board.all_legal_moves().unwrap();
+        }
+        let board = BitBoard::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        assert!(
+            board
+                .all_legal_moves()
+                .unwrap()
+                .into_iter()
+                .any(|chess_move| chess_move.dest() == square("e3"))
+        );
+    }
+
+    #[test]
+    fn draws() {
+        let mut board = BitBoard::starting_board();
+        for _ in 0..2 {
+            for chess_move in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                assert_eq!(board.draw_reason(), None);
+                board.move_piece(SimpleMove::from_pgn_str(chess_move).unwrap()).unwrap();
+            }
+        }
+        assert_eq!(
+            board.state().unwrap(),
+            BoardState::Draw(DrawReason::ThreefoldRepetition)
+        );
+        assert_eq!(board.all_legal_moves().unwrap().into_iter().count(), 20);
+
+        let mut board = BitBoard::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 99 80").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("a1a2").unwrap()).unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Draw(DrawReason::FiftyMoves));
+
+        let board = BitBoard::from_fen("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.state().unwrap(),
+            BoardState::Draw(DrawReason::InsufficientMaterial)
+        );
+        let board = BitBoard::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+        let board = BitBoard::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Stalemate);
+    }
+
+    #[test]
+    fn chess960() {
+        let board = BitBoard::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(board.variant(), Variant::Chess960);
+        assert_eq!(board.perft(4).unwrap(), 326_672);
+        let mut board = BitBoard::from_fen("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1").unwrap();
+        board
+            .move_piece_legal(board.disambiguate_move_pgn("O-O").unwrap())
+            .unwrap();
+        assert_eq!(
+            board.as_fen_str(),
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b gb - 1 1"
+        );
+        let mut board = BitBoard::chess960_starting_board(518).unwrap();
+        for chess_move in ["e2e4", "e7e5", "g1f3", "g8f6", "f1c4", "f8c5", "e1h1"] {
+            board
+                .move_piece_legal(SimpleMove::from_pgn_str(chess_move).unwrap())
+                .unwrap();
+        }
+        assert_eq!(
+            board.as_fen_str(),
+            "rnbqk2r/pppp1ppp/5n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4"
+        );
+    }
+
+    /// Play the moves picked by `choices` from `board`, checking it agrees with
+    /// [`PieceListBoard`] in every position
+    fn agrees_from(mut board: BitBoard, choices: Vec<usize>) -> Result<(), TestCaseError> {
+        for choice in choices {
+            // The board rebuilt from FEN has no history to find repetitions in
+            if board.draw_reason().is_some() {
+                break;
+            }
+            let mut piece_list = PieceListBoard::from_fen(&board.as_fen_str()).unwrap();
+            piece_list.set_variant(board.variant());
+            prop_assert_eq!(board.zobrist_hash(), piece_list.zobrist_hash());
+            prop_assert_eq!(board.to_fen().unwrap(), piece_list.to_fen().unwrap());
+            let mut expected: Vec<SimpleMove> = piece_list.all_legal_moves().unwrap().into_iter().collect();
+            let mut moves: Vec<SimpleMove> = board.all_legal_moves().unwrap().into_iter().collect();
+            expected.sort();
+            moves.sort();
+            prop_assert_eq!(&moves, &expected);
+            prop_assert_eq!(board.state().unwrap(), piece_list.state().unwrap());
+            if moves.is_empty() {
+                break;
+            }
+            board.move_piece(moves[choice % moves.len()]).unwrap();
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_piece_list(choices in prop::collection::vec(any::<usize>(), 0..40)) {
+            agrees_from(BitBoard::starting_board(), choices)?;
+        }
+
+        #[test]
+        fn agrees_with_piece_list_in_chess960(number in 0..960_u16, choices in prop::collection::vec(any::<usize>(), 0..40)) {
+            agrees_from(BitBoard::chess960_starting_board(number).unwrap(), choices)?;
+        }
+    }
+
+    #[test]
+    fn disambiguate_check_into_draw() {
+        piece_list::tests::disambiguates_check_into_draw::<BitBoard>();
+    }
+}
@@ -0,0 +1,256 @@
+//! Builder for setting up arbitrary positions
+//!
+//! Produces any board implementing [`crate::traits::ChessBoard`] without having to write out a FEN
+//! string by hand.
+
+use crate::enums::{CastlingSide, PieceColour, PieceKind};
+use crate::parser::fen::{Fen, STANDARD_CASTLING_FILES};
+use crate::simple_types::{SimplePiece, SimpleSquare};
+use crate::traits::{ChessBoard, ChessPiece as _, ChessSquare as _};
+
+/// How the en passant square of the built board is decided
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EnPassant {
+    /// Set explicitly, including explicitly having none
+    Explicit,
+    /// Inferred from the pawn placement when the board is built
+    Inferred,
+}
+
+/// Builder for chess positions
+///
+/// Starts from an empty board with white to move, no castling rights, no en passant square, a
+/// halfmove clock of 0 and a fullmove number of 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardBuilder {
+    layout: [[Option<SimplePiece>; 8]; 8],
+    turn: PieceColour,
+    castling_rights: [bool; 4],
+    castling_files: [u8; 4],
+    en_passant: Option<SimpleSquare>,
+    en_passant_mode: EnPassant,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self {
+            layout: [[None; 8]; 8],
+            turn: PieceColour::White,
+            castling_rights: [false; 4],
+            castling_files: STANDARD_CASTLING_FILES,
+            en_passant: None,
+            en_passant_mode: EnPassant::Explicit,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+}
+
+impl BoardBuilder {
+    /// Place `piece` on `square`, replacing any piece already there
+    pub fn piece(mut self, square: SimpleSquare, piece: SimplePiece) -> Self {
+        self.layout[7 - square.rank() as usize][square.file() as usize] = Some(piece);
+        self
+    }
+
+    /// Set the side to move
+    pub fn turn(mut self, colour: PieceColour) -> Self {
+        self.turn = colour;
+        self
+    }
+
+    /// Give `colour` the right to castle on `side`
+    pub fn castling_right(self, colour: PieceColour, side: CastlingSide) -> Self {
+        self.castling_rook(colour, side, side.standard_rook_file())
+    }
+
+    /// Give `colour` the right to castle on `side` with the rook on `rook_file`, as in Chess960
+    pub fn castling_rook(mut self, colour: PieceColour, side: CastlingSide, rook_file: u8) -> Self {
+        let offset = match colour {
+            PieceColour::White => 0,
+            PieceColour::Black => 2,
+        };
+        let side = match side {
+            CastlingSide::KingSide => 0,
+            CastlingSide::QueenSide => 1,
+        };
+        self.castling_rights[offset + side] = true;
+        self.castling_files[offset + side] = rook_file;
+        self
+    }
+
+    /// Explicitly set the en passant target square, overriding inference
+    pub fn en_passant(mut self, square: Option<SimpleSquare>) -> Self {
+        self.en_passant = square;
+        self.en_passant_mode = EnPassant::Explicit;
+        self
+    }
+
+    /// Infer the en passant target square from the pawn placement when building
+    ///
+    /// If exactly one pawn of the side that just moved sits on its fourth rank with both squares
+    /// behind it empty, it is assumed to have just double pushed and the square it skipped becomes
+    /// the en passant target. If no pawn or more than one pawn could have just double pushed then
+    /// there is no en passant square, and it must be set explicitly with
+    /// [`BoardBuilder::en_passant`] instead.
+    pub fn infer_en_passant(mut self) -> Self {
+        self.en_passant_mode = EnPassant::Inferred;
+        self
+    }
+
+    /// Set the halfmove clock
+    pub fn halfmove_clock(mut self, halfmove_clock: u32) -> Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+
+    /// Set the fullmove number
+    pub fn fullmove_number(mut self, fullmove_number: u32) -> Self {
+        self.fullmove_number = fullmove_number;
+        self
+    }
+
+    /// Build board
+    pub fn build<B: ChessBoard>(&self) -> B {
+        B::from(self.to_fen())
+    }
+
+    /// Return the built position as a FEN string
+    pub fn as_fen_str(&self) -> String {
+        self.to_fen().to_str()
+    }
+
+    fn to_fen(&self) -> Fen {
+        let en_passant = match self.en_passant_mode {
+            EnPassant::Explicit => self.en_passant,
+            EnPassant::Inferred => self.inferred_en_passant(),
+        };
+        Fen {
+            layout: Box::new(self.layout),
+            turn: self.turn,
+            castling_rights: self.castling_rights,
+            castling_files: self.castling_files,
+            en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+
+    fn inferred_en_passant(&self) -> Option<SimpleSquare> {
+        let mover = !self.turn;
+        let (start_rank, skipped_rank, pushed_rank) = match mover {
+            PieceColour::White => (1, 2, 3),
+            PieceColour::Black => (6, 5, 4),
+        };
+        let mut candidates = (0..8).filter(|&file| {
+            let pushed = self.get(SimpleSquare::new(file, pushed_rank));
+            pushed.is_some_and(|piece| piece.kind() == PieceKind::Pawn && piece.colour() == mover)
+                && self.get(SimpleSquare::new(file, skipped_rank)).is_none()
+                && self.get(SimpleSquare::new(file, start_rank)).is_none()
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(file), None) => Some(SimpleSquare::new(file, skipped_rank)),
+            _ => None,
+        }
+    }
+
+    fn get(&self, square: SimpleSquare) -> Option<SimplePiece> {
+        self.layout[7 - square.rank() as usize][square.file() as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::piece_list::PieceListBoard;
+
+    fn square(s: &str) -> SimpleSquare {
+        SimpleSquare::from_pgn_str(s).unwrap()
+    }
+
+    fn kings() -> BoardBuilder {
+        BoardBuilder::default()
+            .piece(square("e1"), SimplePiece::new(PieceKind::King, PieceColour::White))
+            .piece(square("e8"), SimplePiece::new(PieceKind::King, PieceColour::Black))
+    }
+
+    #[test]
+    fn empty_builder() {
+        assert_eq!(BoardBuilder::default().as_fen_str(), "8/8/8/8/8/8/8/8 w - - 0 1");
+    }
+
+    #[test]
+    fn starting_position() {
+        let mut builder = BoardBuilder::default();
+        let back_rank = [
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ];
+        for (file, kind) in back_rank.into_iter().enumerate() {
+            let file = file as u8;
+            builder = builder
+                .piece(SimpleSquare::new(file, 0), SimplePiece::new(kind, PieceColour::White))
+                .piece(
+                    SimpleSquare::new(file, 1),
+                    SimplePiece::new(PieceKind::Pawn, PieceColour::White),
+                )
+                .piece(
+                    SimpleSquare::new(file, 6),
+                    SimplePiece::new(PieceKind::Pawn, PieceColour::Black),
+                )
+                .piece(SimpleSquare::new(file, 7), SimplePiece::new(kind, PieceColour::Black));
+        }
+        for colour in [PieceColour::White, PieceColour::Black] {
+            for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+                builder = builder.castling_right(colour, side);
+            }
+        }
+        assert_eq!(builder.build::<PieceListBoard>(), PieceListBoard::starting_board());
+    }
+
+    #[test]
+    fn inferred_en_passant() {
+        let builder = kings()
+            .piece(square("e4"), SimplePiece::new(PieceKind::Pawn, PieceColour::White))
+            .piece(square("d4"), SimplePiece::new(PieceKind::Pawn, PieceColour::Black))
+            .turn(PieceColour::Black)
+            .infer_en_passant();
+        assert_eq!(builder.as_fen_str(), "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+    }
+
+    #[test]
+    fn inferred_en_passant_ambiguous() {
+        let builder = kings()
+            .piece(square("e5"), SimplePiece::new(PieceKind::Pawn, PieceColour::Black))
+            .piece(square("a5"), SimplePiece::new(PieceKind::Pawn, PieceColour::Black))
+            .infer_en_passant();
+        assert_eq!(builder.as_fen_str(), "4k3/8/8/p3p3/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn inferred_en_passant_blocked_start() {
+        let builder = kings()
+            .piece(square("d5"), SimplePiece::new(PieceKind::Pawn, PieceColour::Black))
+            .piece(square("d7"), SimplePiece::new(PieceKind::Pawn, PieceColour::Black))
+            .infer_en_passant();
+        assert_eq!(builder.as_fen_str(), "4k3/3p4/8/3p4/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn explicit_en_passant_overrides_inference() {
+        let builder = kings()
+            .piece(square("e4"), SimplePiece::new(PieceKind::Pawn, PieceColour::White))
+            .turn(PieceColour::Black)
+            .infer_en_passant()
+            .en_passant(None);
+        assert_eq!(builder.as_fen_str(), "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+    }
+}
@@ -0,0 +1,63 @@
+//! Position history kept by boards for detecting draws
+
+use std::iter;
+use std::sync::Arc;
+
+use crate::enums::DrawReason;
+
+/// Immutable position in the history, linked to the one before it
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct HistoryNode {
+    hash: u64,
+    pub(super) previous: PositionHistory,
+}
+
+/// Zobrist hashes of the previous positions of a board, most recent first
+///
+/// A persistent linked list, so cloning only copies an [`Arc`] and boards branching from the same
+/// position share every position before the branch instead of each storing their own copy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct PositionHistory(pub(super) Option<Arc<HistoryNode>>);
+
+impl PositionHistory {
+    pub(super) fn push(&mut self, hash: u64) {
+        let previous = Self(self.0.take());
+        self.0 = Some(Arc::new(HistoryNode { hash, previous }));
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = u64> {
+        iter::successors(self.0.as_deref(), |node| node.previous.0.as_deref()).map(|node| node.hash)
+    }
+
+    /// Number of times the position hashed as `hash` has occurred, counting the current one
+    ///
+    /// Only the last `halfmove_clock` positions are searched, since none before the last capture or
+    /// pawn move can be repeated.
+    pub(super) fn repetitions(&self, hash: u64, halfmove_clock: u32) -> usize {
+        1 + self
+            .iter()
+            .take(halfmove_clock as usize)
+            .filter(|&previous| previous == hash)
+            .count()
+    }
+}
+
+/// Why a position is drawn, given how many times it has occurred, the halfmove clock and whether
+/// both sides lack mating material
+///
+/// Draws that apply without being claimed are preferred over those that have to be claimed.
+pub(super) fn draw_reason(repetitions: usize, halfmove_clock: u32, insufficient_material: bool) -> Option<DrawReason> {
+    if repetitions >= 5 {
+        Some(DrawReason::FivefoldRepetition)
+    } else if halfmove_clock >= 150 {
+        Some(DrawReason::SeventyFiveMoves)
+    } else if insufficient_material {
+        Some(DrawReason::InsufficientMaterial)
+    } else if repetitions >= 3 {
+        Some(DrawReason::ThreefoldRepetition)
+    } else if halfmove_clock >= 100 {
+        Some(DrawReason::FiftyMoves)
+    } else {
+        None
+    }
+}
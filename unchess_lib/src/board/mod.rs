@@ -1,4 +1,20 @@
 //! Chess board representations
 
 pub mod bitboard;
+pub mod builder;
+mod history;
 pub mod piece_list;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::enums::Variant;
+
+/// Board as serialized, its FEN along with the variant, which the FEN alone doesn't always show
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedBoard {
+    fen: String,
+    variant: Variant,
+}
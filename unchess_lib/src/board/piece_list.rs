@@ -5,16 +5,30 @@
 //! slow.
 
 use core::fmt;
+use std::collections::HashMap;
+use std::iter;
 use std::ops::{Add, AddAssign, Div, Mul, Sub};
 
-use crate::enums::{AmbiguousMove, BoardState, CastlingSide, PieceColour, PieceKind};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _, ser::Error as _};
+
+#[cfg(feature = "serde")]
+use crate::board::SerializedBoard;
+use crate::board::history::{self, PositionHistory};
+use crate::book::PolyglotKeys;
+use crate::diagram::{self, DiagramOptions};
+use crate::enums::{
+    AmbiguousMove, BoardState, CastlingSide, DrawReason, GameResult, MoveAction, PieceColour, PieceKind, Variant,
+};
 use crate::error::ChessError;
-use crate::parser::fen::Fen;
+use crate::eval::{DefaultEvaluator, Evaluator, MAX_GAME_PHASE};
+use crate::parser::fen::{Fen, STANDARD_CASTLING_FILES};
 use crate::simple_types::{SimpleMove, SimplePiece, SimpleSquare};
+use crate::square_set::SquareSet;
 use crate::traits::{
     ChessBoard as _, ChessMove as _, ChessPiece as _, ChessSquare as _, LegalMoveGenerator, PLegalMoveGenerator,
 };
-use crate::{notation, traits};
+use crate::{parser, traits, zobrist};
 
 use itertools::Itertools as _;
 
@@ -166,24 +180,78 @@ impl PieceWithPos {
     }
 }
 
-type BoardHistoryElem = (Vec<PieceWithPos>, PieceColour, Option<SimpleSquare>, [bool; 4]);
+/// Legal moves out of check, split by how they escape it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckEscapes {
+    /// Moves of the king itself, including the king capturing the checking piece
+    pub king_moves: Vec<SimpleMove>,
+    /// Other pieces capturing the checking piece
+    pub captures: Vec<SimpleMove>,
+    /// Other pieces moving between the king and the checking piece
+    pub blocks: Vec<SimpleMove>,
+}
+
+/// Why an en passant square was cleared when loading a lenient FEN
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum PhantomEnPassant {
+    /// No pawn of the side not to move could have just double pushed past it
+    Unreachable(SimpleSquare),
+    /// No pawn of the side to move stands beside the pushed pawn to take it
+    Uncapturable(SimpleSquare),
+}
 
 /// Piece list representation of chess board
+///
+/// The position history used for repetition detection is shared between clones, so cloning a
+/// board costs the same however many moves have been played, making it reasonable to store a board
+/// at every node of a large variation tree.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PieceListBoard {
     pieces: Vec<PieceWithPos>,
     turn: PieceColour,
     en_passant: Option<SimpleSquare>,
     castling_rights: [bool; 4],
+    /// File of the rook each castling right castles with
+    castling_files: [u8; 4],
+    variant: Variant,
     halfmove_clock: u32,
     fullmove_number: u32,
-    board_history: Vec<BoardHistoryElem>,
+    board_history: PositionHistory,
+    /// En passant square removed when loading a lenient FEN, kept to warn about
+    cleared_en_passant: Option<PhantomEnPassant>,
 }
 
 impl traits::ChessBoard for PieceListBoard {
     type Square = SimpleSquare;
     type Piece = PieceWithPos;
     type Move = SimpleMove;
+    fn from_fen(fen: &str) -> Result<Self, ChessError> {
+        let board = match parser::fen::fen(fen) {
+            Ok((_, parsed)) => Self::from(parsed),
+            Err(_) => return Err(ChessError::InvalidFEN(fen.to_string())),
+        };
+        if board.phantom_en_passant().is_some() {
+            return Err(ChessError::InvalidFEN(fen.to_string()));
+        }
+        Ok(board)
+    }
+
+    fn from_fen_lenient(fen: &str) -> Result<Self, ChessError> {
+        let mut board = match parser::fen::board_lenient(fen) {
+            Ok((_, parsed)) => Self::from(parsed),
+            Err(_) => return Err(ChessError::InvalidFEN(fen.to_string())),
+        };
+        if let Some(phantom) = board.phantom_en_passant() {
+            board.en_passant = None;
+            board.cleared_en_passant = Some(phantom);
+        }
+        Ok(board)
+    }
+
+    fn to_fen(&self) -> Result<String, ChessError> {
+        self.as_fen_str()
+    }
+
     fn get_piece(&self, square: SimpleSquare) -> Result<PieceWithPos, ChessError> {
         let pieces = self.pieces.iter().filter(|&&piece| piece.square() == square);
         match pieces.at_most_one() {
@@ -202,12 +270,13 @@ impl traits::ChessBoard for PieceListBoard {
         let taken_piece = self.pieces.iter().position(|piece| piece.square() == chess_move.dest());
 
         self.halfmove_clock += 1;
-        self.board_history.push((
-            self.all_pieces().into_iter().collect(),
-            self.turn,
-            self.en_passant,
-            self.castling_rights,
-        ));
+        self.board_history.push(self.zobrist_hash());
+
+        if let Some(side) = self.chess960_castle_side(chess_move) {
+            self.castle_chess960(side)?;
+            self.end_turn();
+            return Ok(());
+        }
 
         let piece = self.get_piece_mut(chess_move.src())?;
         piece.move_piece(chess_move.dest());
@@ -231,16 +300,15 @@ impl traits::ChessBoard for PieceListBoard {
         self.take_en_passant(piece, offset)?;
 
         if piece.kind() == PieceKind::Pawn && offset.rank.abs() == PAWN_DOUBLE_PUSH {
-            self.en_passant = Some(chess_move.src() + offset / 2);
+            // Only kept if a pawn could take, so it is never a square strict FEN loading rejects
+            let skipped = chess_move.src() + offset / 2;
+            self.en_passant = self.pawn_attacks(!piece.colour()).contains(skipped).then_some(skipped);
         } else {
             self.en_passant = None;
         }
         self.update_castling_rights(piece, chess_move);
 
-        self.turn = !self.turn;
-        if self.turn == PieceColour::White {
-            self.fullmove_number += 1;
-        }
+        self.end_turn();
         Ok(())
     }
 }
@@ -269,6 +337,16 @@ const KNIGHT_PATTERN: [SquareOffset; 8] = [
 
 const KING_PATTERN: [SquareOffset; 8] = QUEEN_DIRECTIONS;
 
+/// Order of piece kinds within each colour's bitboards
+const BITBOARD_KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
 impl PLegalMoveGenerator for PieceListBoard {
     fn all_plegal_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
         let mut out: Vec<SimpleMove> = vec![];
@@ -283,37 +361,10 @@ impl PLegalMoveGenerator for PieceListBoard {
 
     fn piece_plegal_moves(&self, square: SimpleSquare) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
         let piece = self.get_piece(square)?;
-        if piece.colour != self.turn
-            || self.halfmove_clock >= 50
-            || self
-                .board_history
-                .iter()
-                .filter(|board_state| {
-                    board_state
-                        == &&(
-                            self.all_pieces().into_iter().collect(),
-                            self.turn,
-                            self.en_passant,
-                            self.castling_rights,
-                        )
-                })
-                .count()
-                >= 2
-        {
+        if piece.colour != self.turn {
             return Ok(vec![]);
         }
-        match piece.kind() {
-            PieceKind::King => {
-                let mut moves = self.offset_moves(piece.square, piece.colour, &KING_PATTERN)?;
-                moves.append(&mut self.castle_moves(piece.colour)?);
-                Ok(moves)
-            }
-            PieceKind::Queen => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS),
-            PieceKind::Bishop => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS[0..4]),
-            PieceKind::Knight => self.offset_moves(piece.square, piece.colour, &KNIGHT_PATTERN),
-            PieceKind::Rook => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS[4..8]),
-            PieceKind::Pawn => self.pawn_moves(piece.square, piece.colour),
-        }
+        self.piece_moves(piece)
     }
 
     fn is_move_plegal(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
@@ -336,7 +387,14 @@ impl PLegalMoveGenerator for PieceListBoard {
 impl LegalMoveGenerator for PieceListBoard {
     fn all_legal_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
         let mut moves: Vec<SimpleMove> = vec![];
-        for chess_move in self.all_plegal_moves()? {
+        let king_square = self.king_square(self.turn)?;
+        // Nothing but moving the king can get out of double check
+        let candidates: Vec<SimpleMove> = if self.attackers(king_square, self.turn)?.len() >= 2 {
+            self.piece_plegal_moves(king_square)?.into_iter().collect()
+        } else {
+            self.all_plegal_moves()?.into_iter().collect()
+        };
+        for chess_move in candidates {
             let mut board = self.clone();
             board.move_piece(chess_move)?;
             if !board.king_in_check(self.turn)? {
@@ -358,6 +416,15 @@ impl LegalMoveGenerator for PieceListBoard {
         Ok(moves)
     }
 
+    fn legal_moves_for(&self, colour: PieceColour) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut board = self.clone();
+        if colour != self.turn {
+            board.turn = colour;
+            board.en_passant = None;
+        }
+        Ok(board.all_legal_moves()?.into_iter().collect::<Vec<SimpleMove>>())
+    }
+
     fn is_move_legal(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
         if self.is_move_plegal(chess_move)? {
             let mut board = self.clone();
@@ -372,6 +439,22 @@ impl LegalMoveGenerator for PieceListBoard {
         }
     }
 
+    fn gives_check(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        let mut board = self.clone();
+        board.move_piece(chess_move)?;
+        board.king_in_check(board.turn)
+    }
+
+    fn checking_moves(&self) -> Result<impl IntoIterator<Item = SimpleMove>, ChessError> {
+        let mut moves: Vec<SimpleMove> = vec![];
+        for chess_move in self.all_legal_moves()? {
+            if self.gives_check(chess_move)? {
+                moves.push(chess_move);
+            }
+        }
+        Ok(moves)
+    }
+
     fn move_piece_legal(&mut self, chess_move: SimpleMove) -> Result<(), ChessError> {
         if self.is_move_legal(chess_move)? {
             self.move_piece(chess_move)?;
@@ -383,21 +466,55 @@ impl LegalMoveGenerator for PieceListBoard {
 
     fn state(&self) -> Result<BoardState, ChessError> {
         match (
-            self.all_legal_moves()?.into_iter().try_len().unwrap(),
+            self.has_legal_move()?,
             self.king_in_check(self.turn)?,
+            self.draw_reason(),
         ) {
-            (0, true) => Ok(BoardState::Checkmate),
-            (0, false) => Ok(BoardState::Stalemate),
-            (_, true) => Ok(BoardState::Check),
-            _ => Ok(BoardState::Normal),
+            (false, true, _) => Ok(BoardState::Checkmate),
+            (false, false, _) => Ok(BoardState::Stalemate),
+            (true, _, Some(reason)) => Ok(BoardState::Draw(reason)),
+            (true, true, None) => Ok(BoardState::Check),
+            (true, false, None) => Ok(BoardState::Normal),
         }
     }
 
+    fn draw_reason(&self) -> Option<DrawReason> {
+        history::draw_reason(
+            self.board_history.repetitions(self.zobrist_hash(), self.halfmove_clock),
+            self.halfmove_clock,
+            self.insufficient_material(),
+        )
+    }
+
     fn disambiguate_move(&self, chess_move: AmbiguousMove) -> Result<SimpleMove, ChessError> {
         match chess_move {
             AmbiguousMove::Normal { .. } => self.disambiguate_normal(chess_move),
             AmbiguousMove::Castle { .. } => Ok(self.disambiguate_castling(chess_move)),
+            AmbiguousMove::Null => Err(ChessError::ImpossibleMove(chess_move)),
+        }
+    }
+}
+
+/// Serialized as FEN and variant, e.g. `{"fen":"8/8/8/8/8/8/8/K6k w - - 0 1","variant":"Standard"}`,
+/// so the position history is lost
+#[cfg(feature = "serde")]
+impl Serialize for PieceListBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedBoard {
+            fen: self.to_fen().map_err(S::Error::custom)?,
+            variant: self.variant,
         }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PieceListBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedBoard::deserialize(deserializer)?;
+        let mut board = Self::from_fen(&serialized.fen).map_err(D::Error::custom)?;
+        board.set_variant(serialized.variant);
+        Ok(board)
     }
 }
 
@@ -416,14 +533,22 @@ impl From<Fen> for PieceListBoard {
             }
         }
 
+        let variant = if value.needs_chess960() {
+            Variant::Chess960
+        } else {
+            Variant::Standard
+        };
         Self {
             pieces,
             turn: value.turn,
             en_passant: value.en_passant,
             castling_rights: value.castling_rights,
+            castling_files: value.castling_files,
+            variant,
             halfmove_clock: value.halfmove_clock,
             fullmove_number: value.fullmove_number,
-            board_history: Vec::with_capacity(100),
+            board_history: PositionHistory::default(),
+            cleared_en_passant: None,
         }
     }
 }
@@ -447,14 +572,13 @@ impl PieceListBoard {
     fn castle_rook(&mut self, piece: PieceWithPos, offset: SquareOffset) -> Result<(), ChessError> {
         const KINGSIDE_CASTLE: i8 = 2;
         const QUEENSIDE_CASTLE: i8 = -2;
-        if piece.kind() == PieceKind::King && offset.file == KINGSIDE_CASTLE {
-            let rook = self.get_piece_mut(piece.square() + SquareOffset::new(1, 0))?;
-            rook.move_piece(piece.square() + SquareOffset::new(-1, 0));
-        }
-        if piece.kind() == PieceKind::King && offset.file == QUEENSIDE_CASTLE {
-            let rook = self.get_piece_mut(piece.square() + SquareOffset::new(-2, 0))?;
-            rook.move_piece(piece.square() + SquareOffset::new(1, 0));
-        }
+        let side = match offset.file {
+            KINGSIDE_CASTLE if piece.kind() == PieceKind::King => CastlingSide::KingSide,
+            QUEENSIDE_CASTLE if piece.kind() == PieceKind::King => CastlingSide::QueenSide,
+            _ => return Ok(()),
+        };
+        let (_, _, rook_from, rook_to) = side.castle_squares(piece.colour);
+        self.get_piece_mut(rook_from)?.move_piece(rook_to);
         Ok(())
     }
 
@@ -502,14 +626,142 @@ impl PieceListBoard {
                 self.castling_rights[castling_offset + Self::KINGSIDE] = false;
                 self.castling_rights[castling_offset + Self::QUEENSIDE] = false;
             }
-            PieceKind::Rook if chess_move.src().file() == 0 => {
+            PieceKind::Rook if chess_move.src().file() == self.castling_files[castling_offset + Self::QUEENSIDE] => {
                 self.castling_rights[castling_offset + Self::QUEENSIDE] = false;
             }
-            PieceKind::Rook if chess_move.src().file() == 7 => {
+            PieceKind::Rook if chess_move.src().file() == self.castling_files[castling_offset + Self::KINGSIDE] => {
                 self.castling_rights[castling_offset + Self::KINGSIDE] = false;
             }
             _ => (),
         }
+        // Taking a rook that hasn't moved takes away the right to castle with it
+        let opponent_offset = Self::castling_right_offset(!piece.colour);
+        for (side, offset) in [
+            (CastlingSide::KingSide, Self::KINGSIDE),
+            (CastlingSide::QueenSide, Self::QUEENSIDE),
+        ] {
+            let (_, _, rook_square, _) = self.castling_squares(!piece.colour, side);
+            if chess_move.dest() == rook_square {
+                self.castling_rights[opponent_offset + offset] = false;
+            }
+        }
+    }
+
+    /// Pass the turn to the other side once a move has been made
+    fn end_turn(&mut self) {
+        self.turn = !self.turn;
+        if self.turn == PieceColour::White {
+            self.fullmove_number += 1;
+        }
+    }
+
+    /// Squares involved in `colour` castling on `side`, as for
+    /// [`CastlingSide::castle_squares_from`]
+    ///
+    /// In Chess960 the king castles from wherever it stands on its back rank.
+    fn castling_squares(
+        &self,
+        colour: PieceColour,
+        side: CastlingSide,
+    ) -> (SimpleSquare, SimpleSquare, SimpleSquare, SimpleSquare) {
+        let offset = match side {
+            CastlingSide::KingSide => Self::KINGSIDE,
+            CastlingSide::QueenSide => Self::QUEENSIDE,
+        };
+        let rook_file = self.castling_files[Self::castling_right_offset(colour) + offset];
+        let (standard_king, _, _, _) = side.castle_squares(colour);
+        let king_file = match self.variant {
+            Variant::Standard => standard_king.file(),
+            Variant::Chess960 => self
+                .pieces
+                .iter()
+                .find(|piece| {
+                    piece.kind == PieceKind::King
+                        && piece.colour == colour
+                        && piece.square.rank() == standard_king.rank()
+                })
+                .map_or(standard_king.file(), |king| king.square.file()),
+        };
+        side.castle_squares_from(colour, king_file, rook_file)
+    }
+
+    /// Side castled on if `chess_move` is a Chess960 castling move, the king taking its own rook
+    fn chess960_castle_side(&self, chess_move: SimpleMove) -> Option<CastlingSide> {
+        if self.variant != Variant::Chess960 {
+            return None;
+        }
+        let king = self
+            .get_piece(chess_move.src())
+            .ok()
+            .filter(|piece| piece.kind == PieceKind::King)?;
+        let rook = self
+            .get_piece(chess_move.dest())
+            .ok()
+            .filter(|piece| piece.kind == PieceKind::Rook && piece.colour == king.colour)?;
+        if rook.square.file() > king.square.file() {
+            Some(CastlingSide::KingSide)
+        } else {
+            Some(CastlingSide::QueenSide)
+        }
+    }
+
+    /// Move the king and rook of the side to move to their castled squares
+    fn castle_chess960(&mut self, side: CastlingSide) -> Result<(), ChessError> {
+        let (king_from, king_to, rook_from, rook_to) = self.castling_squares(self.turn, side);
+        // Either piece may land on the other's square, so the rook is lifted off the board first
+        let mut rook = self.get_piece(rook_from)?;
+        self.pieces.retain(|piece| piece.square != rook_from);
+        self.get_piece_mut(king_from)?.move_piece(king_to);
+        rook.move_piece(rook_to);
+        self.pieces.push(rook);
+
+        let castling_offset = Self::castling_right_offset(self.turn);
+        self.castling_rights[castling_offset + Self::KINGSIDE] = false;
+        self.castling_rights[castling_offset + Self::QUEENSIDE] = false;
+        self.en_passant = None;
+        Ok(())
+    }
+
+    /// Castling moves for `colour` in Chess960, written as the king taking its own rook
+    ///
+    /// Every square the king and rook cross must be empty apart from the two of them, and the king
+    /// can't start on, pass through or land on an attacked square.
+    fn chess960_castle_moves(&self, colour: PieceColour) -> Result<Vec<SimpleMove>, ChessError> {
+        let mut out = vec![];
+        for (side, offset) in [
+            (CastlingSide::KingSide, Self::KINGSIDE),
+            (CastlingSide::QueenSide, Self::QUEENSIDE),
+        ] {
+            if !self.castling_rights[Self::castling_right_offset(colour) + offset] {
+                continue;
+            }
+            let (king_from, king_to, rook_from, rook_to) = self.castling_squares(colour, side);
+            let has_own = |square, kind| {
+                self.get_piece(square)
+                    .is_ok_and(|piece| piece.kind == kind && piece.colour == colour)
+            };
+            if !has_own(king_from, PieceKind::King) || !has_own(rook_from, PieceKind::Rook) {
+                continue;
+            }
+            let rank = king_from.rank();
+            let files = [king_from, king_to, rook_from, rook_to].map(|square| square.file());
+            let mut can_castle = true;
+            for file in
+                files.iter().min().copied().unwrap_or_default()..=files.iter().max().copied().unwrap_or_default()
+            {
+                let square = SimpleSquare::new(file, rank);
+                if square != king_from && square != rook_from {
+                    can_castle &= self.square_empty(square)?;
+                }
+            }
+            for file in king_from.file().min(king_to.file())..=king_from.file().max(king_to.file()) {
+                can_castle &= !self.square_under_attack(SimpleSquare::new(file, rank), colour)?;
+            }
+            if can_castle {
+                out.push(SimpleMove::new(king_from, rook_from, None));
+            }
+        }
+        Ok(out)
     }
 
     fn pawn_moves(&self, square: SimpleSquare, colour: PieceColour) -> Result<Vec<SimpleMove>, ChessError> {
@@ -585,19 +837,14 @@ impl PieceListBoard {
     }
 
     fn castle_moves(&self, colour: PieceColour) -> Result<Vec<SimpleMove>, ChessError> {
+        if self.variant == Variant::Chess960 {
+            return self.chess960_castle_moves(colour);
+        }
         let mut out: Vec<SimpleMove> = vec![];
-        let (back_rank, castle_rights_offset) = match colour {
-            PieceColour::Black => (7, 2),
-            PieceColour::White => (0, 0),
-        };
-        let king_square = SimpleSquare::new(4, back_rank);
-
-        let kingside_inbetween = SimpleSquare::new(5, back_rank);
-        let kingside_dest = SimpleSquare::new(6, back_rank);
-
-        let queenside_inbetween = SimpleSquare::new(3, back_rank);
-        let queenside_dest = SimpleSquare::new(2, back_rank);
-        let queenside_knight = SimpleSquare::new(1, back_rank);
+        let castle_rights_offset = Self::castling_right_offset(colour);
+        let (king_square, kingside_dest, _, kingside_inbetween) = CastlingSide::KingSide.castle_squares(colour);
+        let (_, queenside_dest, _, queenside_inbetween) = CastlingSide::QueenSide.castle_squares(colour);
+        let queenside_knight = SimpleSquare::new(1, king_square.rank());
 
         if self.castling_rights[castle_rights_offset + Self::KINGSIDE] {
             let mut can_castle_kingside = !self.square_under_attack(king_square, colour)?;
@@ -644,39 +891,44 @@ impl PieceListBoard {
         }
     }
 
-    fn fmt_board(&self) -> String {
-        let mut outstr = String::with_capacity(172);
-        for i in (0..8).rev() {
-            outstr.push(notation::rank_to_char(i).unwrap());
-            for j in 0..8 {
-                outstr.push(' ');
-                if let Ok(piece) = self.get_piece(SimpleSquare::new(j, i)) {
-                    outstr.push(piece.as_fen());
-                } else if (i + j) % 2 == 1 {
-                    outstr.push('◼');
-                } else {
-                    outstr.push(' ');
-                }
+    /// Pseudo-legal moves of `piece`, whether or not it is its colour's turn
+    fn piece_moves(&self, piece: PieceWithPos) -> Result<Vec<SimpleMove>, ChessError> {
+        match piece.kind() {
+            PieceKind::King => {
+                let mut moves = self.offset_moves(piece.square, piece.colour, &KING_PATTERN)?;
+                moves.append(&mut self.castle_moves(piece.colour)?);
+                Ok(moves)
             }
-            outstr.push('\n');
+            PieceKind::Queen => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS),
+            PieceKind::Bishop => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS[0..4]),
+            PieceKind::Knight => self.offset_moves(piece.square, piece.colour, &KNIGHT_PATTERN),
+            PieceKind::Rook => self.traversal_moves(piece.square, piece.colour, &QUEEN_DIRECTIONS[4..8]),
+            PieceKind::Pawn => self.pawn_moves(piece.square, piece.colour),
         }
+    }
 
-        outstr.push_str("  ");
-        for j in 0..8 {
-            outstr.push(notation::file_to_char(j).unwrap());
-            outstr.push(' ');
+    /// Check if the side to move has any legal move, stopping at the first one found
+    fn has_legal_move(&self) -> Result<bool, ChessError> {
+        for piece in self.pieces.iter().filter(|piece| piece.colour == self.turn) {
+            for chess_move in self.piece_moves(*piece)? {
+                let mut board = self.clone();
+                board.move_piece(chess_move)?;
+                if !board.king_in_check(self.turn)? {
+                    return Ok(true);
+                }
+            }
         }
-        outstr
+        Ok(false)
     }
 
-    fn king_in_check(&self, colour: PieceColour) -> Result<bool, ChessError> {
+    fn king_square(&self, colour: PieceColour) -> Result<SimpleSquare, ChessError> {
         if let Ok(king) = self
             .pieces
             .iter()
             .filter(|piece| piece.kind == PieceKind::King && piece.colour == colour)
             .exactly_one()
         {
-            self.square_under_attack(king.square, king.colour)
+            Ok(king.square)
         } else {
             Err(ChessError::InvalidBoard(format!(
                 "Number of kings of colour {colour:?} on the board not equal to one"
@@ -684,6 +936,10 @@ impl PieceListBoard {
         }
     }
 
+    fn king_in_check(&self, colour: PieceColour) -> Result<bool, ChessError> {
+        self.square_under_attack(self.king_square(colour)?, colour)
+    }
+
     /// Checks if square is under attack by pretending its other pieces and seeing if it can attack
     ///
     /// Symmetry is beautiful!
@@ -717,11 +973,7 @@ impl PieceListBoard {
                 .map(ChessMove::dest),
             &[PieceKind::King],
         )?;
-        attacked |= self.squares_contain(
-            !colour,
-            self.pawn_moves(square, colour)?.iter().map(ChessMove::dest),
-            &[PieceKind::Pawn],
-        )?;
+        attacked |= self.squares_contain(!colour, Self::pawn_capture_squares(square, colour), &[PieceKind::Pawn])?;
 
         Ok(attacked)
     }
@@ -745,6 +997,55 @@ impl PieceListBoard {
         Ok(false)
     }
 
+    /// Squares of `colour`'s opponent's pieces attacking `square`
+    fn attackers(&self, square: SimpleSquare, colour: PieceColour) -> Result<Vec<SimpleSquare>, ChessError> {
+        use traits::ChessMove;
+        let dests = |moves: Vec<SimpleMove>| moves.iter().map(ChessMove::dest).collect::<Vec<_>>();
+        let groups = [
+            (
+                dests(self.traversal_moves(square, colour, &QUEEN_DIRECTIONS[0..4])?),
+                &[PieceKind::Queen, PieceKind::Bishop][..],
+            ),
+            (
+                dests(self.traversal_moves(square, colour, &QUEEN_DIRECTIONS[4..8])?),
+                &[PieceKind::Queen, PieceKind::Rook][..],
+            ),
+            (
+                dests(self.offset_moves(square, colour, &KNIGHT_PATTERN)?),
+                &[PieceKind::Knight][..],
+            ),
+            (
+                dests(self.offset_moves(square, colour, &KING_PATTERN)?),
+                &[PieceKind::King][..],
+            ),
+            (
+                Self::pawn_capture_squares(square, colour).collect(),
+                &[PieceKind::Pawn][..],
+            ),
+        ];
+        let mut attackers = vec![];
+        for (squares, piece_kinds) in groups {
+            for attacker in squares {
+                match self.get_piece(attacker) {
+                    Ok(piece) if piece.colour != colour && piece_kinds.contains(&piece.kind) => {
+                        attackers.push(attacker);
+                    }
+                    Err(ChessError::PieceNotFound(_)) | Ok(_) => (),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(attackers)
+    }
+
+    /// Squares a `colour` pawn on `square` would capture on, leaving out any off the board
+    fn pawn_capture_squares(square: SimpleSquare, colour: PieceColour) -> impl Iterator<Item = SimpleSquare> {
+        [SquareOffset::new(-1, 1) * colour, SquareOffset::new(1, 1) * colour]
+            .into_iter()
+            .filter(move |offset| !offset.would_overflow(square))
+            .map(move |offset| square + offset)
+    }
+
     fn disambiguate_normal(&self, chess_move: AmbiguousMove) -> Result<SimpleMove, ChessError> {
         let (piece_kind, src_file, src_rank, takes, dest, promote_to, action) = match chess_move {
             AmbiguousMove::Normal {
@@ -756,7 +1057,9 @@ impl PieceListBoard {
                 promote_to,
                 action,
             } => (piece_kind, src_file, src_rank, takes, dest, promote_to, action),
-            AmbiguousMove::Castle { .. } => panic!("Can't use normal move disambiguator on castle"),
+            AmbiguousMove::Castle { .. } | AmbiguousMove::Null => {
+                panic!("Can't use normal move disambiguator on {chess_move}")
+            }
         };
         let all_moves: Vec<SimpleMove> = self
             .all_legal_moves()?
@@ -771,14 +1074,20 @@ impl PieceListBoard {
                     is_match &= unambiguous_move.src().rank() == rank;
                 }
                 if takes {
-                    is_match &= self.get_piece(unambiguous_move.dest()).is_ok();
+                    is_match &= self.get_piece(unambiguous_move.dest()).is_ok()
+                        || (piece_kind == PieceKind::Pawn && Some(unambiguous_move.dest()) == self.en_passant);
                 }
                 is_match &= unambiguous_move.dest() == dest;
                 is_match &= unambiguous_move.promote_to() == promote_to;
+                // Checked apart from the board state, which reports a check into a draw as the draw
                 if let Some(action) = action {
                     let mut board = self.clone();
                     board.move_piece(*unambiguous_move).unwrap();
-                    is_match &= board.state().unwrap() == action.into();
+                    let checkmate = board.is_checkmate().unwrap();
+                    is_match &= match action {
+                        MoveAction::Check => board.in_check().unwrap() && !checkmate,
+                        MoveAction::Checkmate => checkmate,
+                    };
                 }
                 is_match
             })
@@ -792,403 +1101,2583 @@ impl PieceListBoard {
 
     fn disambiguate_castling(&self, chess_move: AmbiguousMove) -> SimpleMove {
         let side = match chess_move {
-            AmbiguousMove::Normal { .. } => panic!("Can't use castling move disambiguator on normal move"),
-            AmbiguousMove::Castle { side } => side,
+            AmbiguousMove::Normal { .. } | AmbiguousMove::Null => {
+                panic!("Can't use castling move disambiguator on {chess_move}")
+            }
+            AmbiguousMove::Castle { side, .. } => side,
         };
-        let mut rank = 0;
-        let mut file = 6;
-        let mut src = SimpleSquare::new(4, 0);
-        if side == CastlingSide::QueenSide {
-            file = 2;
-        }
-        if self.turn == PieceColour::Black {
-            rank = 7;
-            src = SimpleSquare::new(4, 7);
+        let (king_from, king_to, rook_from, _) = self.castling_squares(self.turn, side);
+        match self.variant {
+            Variant::Standard => SimpleMove::new(king_from, king_to, None),
+            Variant::Chess960 => SimpleMove::new(king_from, rook_from, None),
         }
-
-        SimpleMove::new(src, SimpleSquare::new(file, rank), None)
     }
 
-    /// Print self as fen string
+    /// Zobrist hash of the position
     ///
-    /// # Errors
-    /// - [`crate::error::ChessError::InvalidBoard`] if board in invalid state
-    pub fn as_fen_str(&self) -> Result<String, ChessError> {
-        Ok(Fen::try_from(self)?.to_str())
-    }
-}
-
-impl fmt::Display for PieceListBoard {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.fmt_board())
+    /// Includes the piece placement, side to move, castling rights and en passant square, but not
+    /// the halfmove clock or fullmove number. Stable between runs so can be stored.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for piece in &self.pieces {
+            hash ^= zobrist::piece_key(piece, &piece.square);
+        }
+        if self.turn == PieceColour::White {
+            hash ^= zobrist::turn_key();
+        }
+        for colour in [PieceColour::White, PieceColour::Black] {
+            for (side, offset) in [
+                (CastlingSide::KingSide, Self::KINGSIDE),
+                (CastlingSide::QueenSide, Self::QUEENSIDE),
+            ] {
+                if self.castling_rights[Self::castling_right_offset(colour) + offset] {
+                    hash ^= zobrist::castling_key(colour, side);
+                }
+            }
+        }
+        if let Some(en_passant) = self.en_passant {
+            hash ^= zobrist::en_passant_key(en_passant.file());
+        }
+        hash
     }
-}
-
-impl TryFrom<&PieceListBoard> for Fen {
-    type Error = ChessError;
 
-    fn try_from(value: &PieceListBoard) -> Result<Self, ChessError> {
-        let mut layout: Box<[[Option<SimplePiece>; 8]; 8]> = Box::new([[None; 8]; 8]);
-        for (inverse_rank_number, rank) in layout.iter_mut().enumerate() {
-            for (file_number, piece) in rank.iter_mut().enumerate() {
-                *piece = match value.get_piece(SimpleSquare::new(file_number as u8, 7 - inverse_rank_number as u8)) {
-                    Ok(piece) => Some(SimplePiece::from(piece)),
-                    Err(ChessError::PieceNotFound(_)) => None,
-                    Err(e) => return Err(e),
+    /// Polyglot key of the position, for looking it up in opening books
+    ///
+    /// Built from `keys` as described by the Polyglot book format. Unlike
+    /// [`PieceListBoard::zobrist_hash`], the en passant file is only included when a pawn of the
+    /// side to move stands beside the pawn that just moved, whether or not capturing is legal.
+    pub fn polyglot_key(&self, keys: &PolyglotKeys) -> u64 {
+        let mut key = 0;
+        for piece in &self.pieces {
+            key ^= keys.piece(piece.kind, piece.colour, piece.square);
+        }
+        for colour in [PieceColour::White, PieceColour::Black] {
+            for (side, offset) in [
+                (CastlingSide::KingSide, Self::KINGSIDE),
+                (CastlingSide::QueenSide, Self::QUEENSIDE),
+            ] {
+                if self.castling_rights[Self::castling_right_offset(colour) + offset] {
+                    key ^= keys.castling(colour, side);
                 }
             }
         }
-        Ok(Self {
-            layout,
-            turn: value.turn,
-            castling_rights: value.castling_rights,
-            en_passant: value.en_passant,
-            halfmove_clock: value.halfmove_clock, // Until board implements 50 move rule
-            fullmove_number: value.fullmove_number,
-        })
+        if let Some(en_passant) = self.en_passant {
+            let rank = match self.turn {
+                PieceColour::White => en_passant.rank() - 1,
+                PieceColour::Black => en_passant.rank() + 1,
+            };
+            let capturable = self.pieces.iter().any(|piece| {
+                piece.kind == PieceKind::Pawn
+                    && piece.colour == self.turn
+                    && piece.square.rank() == rank
+                    && piece.square.file().abs_diff(en_passant.file()) == 1
+            });
+            if capturable {
+                key ^= keys.en_passant(en_passant.file());
+            }
+        }
+        if self.turn == PieceColour::White {
+            key ^= keys.turn();
+        }
+        key
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Return the side castled on if moving the piece on `from` to `to` would be a castling move
+    ///
+    /// Recognises the king moving two squares from its starting square, as written in UCI, without
+    /// checking whether castling is legal. In Chess960 castling is instead recognised as the king
+    /// taking its own rook.
+    pub fn detect_castle(&self, from: SimpleSquare, to: SimpleSquare) -> Option<CastlingSide> {
+        if self.variant == Variant::Chess960 {
+            return self.chess960_castle_side(SimpleMove::new(from, to, None));
+        }
+        let king = self
+            .get_piece(from)
+            .ok()
+            .filter(|piece| piece.kind == PieceKind::King)?;
+        [CastlingSide::KingSide, CastlingSide::QueenSide]
+            .into_iter()
+            .find(|side| {
+                let (king_from, king_to, _, _) = side.castle_squares(king.colour);
+                (king_from, king_to) == (from, to)
+            })
+    }
 
-    fn moves_from_strs(moves: Vec<&str>) -> Vec<SimpleMove> {
-        let mut new_moves: Vec<SimpleMove> = moves
+    /// Board after moving the piece on `from` to `to`, promoting to `promote_to`
+    ///
+    /// The most direct entry point for user interfaces where a piece is picked up and dropped on a
+    /// square. The board is left unchanged.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    /// - [`crate::error::ChessError::PieceNotFound`] if no piece present at `from`
+    /// - [`crate::error::ChessError::IllegalMove`] if the move is illegal
+    pub fn apply_to(
+        &self,
+        from: SimpleSquare,
+        to: SimpleSquare,
+        promote_to: Option<PieceKind>,
+    ) -> Result<PieceListBoard, ChessError> {
+        let mut board = self.clone();
+        board.move_piece_legal(SimpleMove::new(from, to, promote_to))?;
+        Ok(board)
+    }
+
+    /// Legal en passant captures for the side to move
+    ///
+    /// Captures that would leave the king in check are excluded, including the case where removing
+    /// both pawns from the rank exposes the king to a rook or queen along it.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn en_passant_moves(&self) -> Result<Vec<SimpleMove>, ChessError> {
+        let Some(en_passant) = self.en_passant else {
+            return Ok(vec![]);
+        };
+        Ok(self
+            .all_legal_moves()?
             .into_iter()
-            .map(|s| SimpleMove::from_pgn_str(s).unwrap())
-            .collect();
-        new_moves.sort();
-        new_moves
+            .filter(|chess_move| {
+                chess_move.dest() == en_passant
+                    && self
+                        .get_piece(chess_move.src())
+                        .is_ok_and(|piece| piece.kind == PieceKind::Pawn)
+            })
+            .collect())
     }
 
-    #[test]
-    fn normal_square() {
-        let square = SimpleSquare::new(5, 4);
-        assert_eq!(square.file(), 5);
-        assert_eq!(square.rank(), 4);
+    /// Legal moves for the side to move split into king moves, captures and blocks
+    ///
+    /// All empty if the side to move is not in check. In double check only king moves are legal.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn check_escapes(&self) -> Result<CheckEscapes, ChessError> {
+        let mut escapes = CheckEscapes::default();
+        if !self.king_in_check(self.turn)? {
+            return Ok(escapes);
+        }
+        for chess_move in self.all_legal_moves()? {
+            let piece = self.get_piece(chess_move.src())?;
+            if piece.kind == PieceKind::King {
+                escapes.king_moves.push(chess_move);
+            } else if !self.square_empty(chess_move.dest())?
+                || (piece.kind == PieceKind::Pawn && Some(chess_move.dest()) == self.en_passant)
+            {
+                escapes.captures.push(chess_move);
+            } else {
+                escapes.blocks.push(chess_move);
+            }
+        }
+        Ok(escapes)
     }
 
-    #[test]
-    #[should_panic(expected = "Rank must be between 0-7 inclusive, 8 > 7")]
-    fn wrong_range_square() {
-        let _ = SimpleSquare::new(3, 8);
+    /// Empty squares where a piece of `kind` for the side to move would check the enemy king
+    ///
+    /// For building "place the piece to give check" puzzles. Pieces already on the board block
+    /// sliders as usual, and pawns are never placed on the first or last rank.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn check_giving_squares(&self, kind: PieceKind) -> Result<SquareSet, ChessError> {
+        let king_square = self.king_square(!self.turn)?;
+        let mut squares = SquareSet::EMPTY;
+        for square in (0..64).map(SimpleSquare::from_index) {
+            if self.get_piece(square).is_ok() || (kind == PieceKind::Pawn && [0, 7].contains(&square.rank())) {
+                continue;
+            }
+            let mut board = self.clone();
+            board.pieces.push(PieceWithPos::new(square, kind, self.turn));
+            if board.attackers(king_square, !self.turn)?.contains(&square) {
+                squares.insert(square);
+            }
+        }
+        Ok(squares)
     }
 
-    #[test]
-    #[should_panic(expected = "Chess move cannot originate and terminate at same square")]
-    fn duplicate_move() {
-        let _ = SimpleMove::new(SimpleSquare::new(3, 4), SimpleSquare::new(3, 4), None);
+    /// Squares between the king of the side to move and the single slider checking it
+    ///
+    /// Moving a piece onto any of them blocks the check. Empty if not in check, in double check or
+    /// if the checker is next to the king or is a knight or pawn.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn block_squares(&self) -> Result<SquareSet, ChessError> {
+        let king_square = self.king_square(self.turn)?;
+        let mut squares = SquareSet::EMPTY;
+        let [checker] = self.attackers(king_square, self.turn)?[..] else {
+            return Ok(squares);
+        };
+        if matches!(
+            self.get_piece(checker)?.kind,
+            PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen
+        ) {
+            let offset = checker - king_square;
+            let step = SquareOffset::new(offset.file.signum(), offset.rank.signum());
+            let mut square = king_square + step;
+            while square != checker {
+                squares.insert(square);
+                square += step;
+            }
+        }
+        Ok(squares)
     }
 
-    #[test]
-    fn two_on_same_square() {
-        let square = SimpleSquare::new(3, 2);
-        let board = PieceListBoard {
-            pieces: vec![
-                PieceWithPos::new(square, PieceKind::Knight, PieceColour::Black),
-                PieceWithPos::new(square, PieceKind::Bishop, PieceColour::White),
-            ],
-            turn: PieceColour::White,
-            en_passant: None,
-            castling_rights: [false, false, false, false],
-            halfmove_clock: 0,
-            fullmove_number: 1,
-            board_history: vec![],
+    /// Whether the side to move is in check
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn in_check(&self) -> Result<bool, ChessError> {
+        self.king_in_check(self.turn)
+    }
+
+    /// Whether the side to move is checkmated, without generating every legal move
+    ///
+    /// Tries king moves first, then only moves capturing or blocking the checker, so is usually
+    /// much faster than [`LegalMoveGenerator::state`] when the king has an escape. In double check
+    /// only king moves are tried.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn is_checkmate(&self) -> Result<bool, ChessError> {
+        let king_square = self.king_square(self.turn)?;
+        let checkers = self.attackers(king_square, self.turn)?;
+        if checkers.is_empty() {
+            return Ok(false);
+        }
+        let is_legal = |chess_move: SimpleMove| -> Result<bool, ChessError> {
+            let mut board = self.clone();
+            board.move_piece(chess_move)?;
+            Ok(!board.king_in_check(self.turn)?)
         };
-        let e = board.get_piece(square).unwrap_err();
-        match e {
-            ChessError::InvalidBoard(s) => assert_eq!(s, format!("Two pieces found at {square}")),
-            _ => panic!("Wrong error type {e}"),
+        for chess_move in self.offset_moves(king_square, self.turn, &KING_PATTERN)? {
+            if is_legal(chess_move)? {
+                return Ok(false);
+            }
+        }
+        let [checker] = checkers[..] else {
+            return Ok(true);
+        };
+        let mut targets = self.block_squares()?;
+        targets.insert(checker);
+        if let Some(en_passant) = self.en_passant
+            && self.get_piece(checker)?.kind == PieceKind::Pawn
+        {
+            targets.insert(en_passant);
+        }
+        for chess_move in self.all_plegal_moves()? {
+            if targets.contains(chess_move.dest())
+                && self.get_piece(chess_move.src())?.kind != PieceKind::King
+                && is_legal(chess_move)?
+            {
+                return Ok(false);
+            }
         }
+        Ok(true)
     }
 
-    #[test]
-    fn none_on_square() {
-        let square = SimpleSquare::new(3, 2);
-        let board = PieceListBoard {
-            pieces: vec![],
-            turn: PieceColour::White,
+    /// Legal moves for the side to move landing on one of `targets`, for restricted analysis
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn legal_moves_to(&self, targets: SquareSet) -> Result<Vec<SimpleMove>, ChessError> {
+        Ok(self
+            .all_legal_moves()?
+            .into_iter()
+            .filter(|chess_move| targets.contains(chess_move.dest()))
+            .collect())
+    }
+
+    /// Legal moves for the side to move made by its pieces of `kind`
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn moves_of_kind(&self, kind: PieceKind) -> Result<Vec<SimpleMove>, ChessError> {
+        let mut moves = vec![];
+        for piece in self
+            .pieces
+            .iter()
+            .filter(|piece| piece.kind == kind && piece.colour == self.turn)
+        {
+            moves.extend(self.piece_legal_moves(piece.square)?);
+        }
+        Ok(moves)
+    }
+
+    /// Return true if `other` is this position reflected or rotated, optionally with colours
+    /// swapped
+    ///
+    /// Tries all eight symmetries of the square, each with and without swapping the colour of
+    /// every piece, and compares the piece placement and side to move, which swaps along with the
+    /// colours. Castling rights and en passant squares are ignored since most symmetries would
+    /// make them meaningless. Intended for deduplicating puzzles, where mirrored copies are common.
+    pub fn is_symmetric_to(&self, other: &PieceListBoard) -> bool {
+        let target: Vec<PieceWithPos> = other.pieces.iter().copied().sorted_unstable().collect();
+        let placement_matches = |flip_file: bool, flip_rank: bool, transpose: bool, swap: bool| {
+            let transformed: Vec<PieceWithPos> = self
+                .pieces
+                .iter()
+                .map(|piece| {
+                    let (mut file, mut rank) = (piece.square.file(), piece.square.rank());
+                    if transpose {
+                        (file, rank) = (rank, file);
+                    }
+                    if flip_file {
+                        file = 7 - file;
+                    }
+                    if flip_rank {
+                        rank = 7 - rank;
+                    }
+                    let colour = if swap { !piece.colour } else { piece.colour };
+                    PieceWithPos::new(SimpleSquare::new(file, rank), piece.kind, colour)
+                })
+                .sorted_unstable()
+                .collect();
+            transformed == target
+        };
+        [false, true].into_iter().any(|swap| {
+            let turn = if swap { !self.turn } else { self.turn };
+            turn == other.turn
+                && iter::repeat_n([false, true], 3)
+                    .multi_cartesian_product()
+                    .any(|flips| placement_matches(flips[0], flips[1], flips[2], swap))
+        })
+    }
+
+    /// Piece colour and kind on each square, for frontends drawing their own boards
+    ///
+    /// Indexed as `grid[row][file]`, with row 0 being rank 8 so the grid reads top to bottom from
+    /// white's point of view, as in a FEN.
+    pub fn to_grid(&self) -> [[Option<(PieceColour, PieceKind)>; 8]; 8] {
+        let mut grid = [[None; 8]; 8];
+        for piece in &self.pieces {
+            grid[7 - piece.square.rank() as usize][piece.square.file() as usize] = Some((piece.colour, piece.kind));
+        }
+        grid
+    }
+
+    /// One bitboard per colour and piece kind, as used by many engines
+    ///
+    /// Ordered white pawns, knights, bishops, rooks, queens and king, then the same for black. Bit
+    /// [`SimpleSquare::to_index`] is set for each square the piece stands on, so bit 0 is a1.
+    pub fn to_bitboards(&self) -> [u64; 12] {
+        let mut bitboards = [0; 12];
+        for piece in &self.pieces {
+            bitboards[Self::bitboard_index(piece.colour, piece.kind)] |= 1 << piece.square.to_index();
+        }
+        bitboards
+    }
+
+    /// Board with the pieces of `bitboards`, as laid out by [`PieceListBoard::to_bitboards`]
+    ///
+    /// `turn` is to move, with no castling rights or en passant square, a halfmove clock of 0 and
+    /// fullmove number of 1.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if a square is set in more than one bitboard
+    pub fn from_bitboards(bitboards: &[u64; 12], turn: PieceColour) -> Result<Self, ChessError> {
+        let mut pieces = vec![];
+        let mut occupied = SquareSet::EMPTY;
+        for colour in [PieceColour::White, PieceColour::Black] {
+            for kind in BITBOARD_KINDS {
+                let squares = SquareSet::from_bits(bitboards[Self::bitboard_index(colour, kind)]);
+                if !(occupied & squares).is_empty() {
+                    return Err(ChessError::InvalidBoard(
+                        "Two pieces found on the same square".to_string(),
+                    ));
+                }
+                occupied = occupied | squares;
+                pieces.extend(squares.iter().map(|square| PieceWithPos::new(square, kind, colour)));
+            }
+        }
+        Ok(Self {
+            pieces,
+            turn,
             en_passant: None,
-            castling_rights: [false, false, false, false],
+            castling_rights: [false; 4],
+            castling_files: STANDARD_CASTLING_FILES,
+            variant: Variant::Standard,
             halfmove_clock: 0,
             fullmove_number: 1,
-            board_history: vec![],
+            board_history: PositionHistory::default(),
+            cleared_en_passant: None,
+        })
+    }
+
+    fn bitboard_index(colour: PieceColour, kind: PieceKind) -> usize {
+        let offset = match colour {
+            PieceColour::White => 0,
+            PieceColour::Black => 6,
         };
-        let e = board.get_piece(square).unwrap_err();
-        match e {
-            ChessError::PieceNotFound(_) => (),
-            _ => panic!("Wrong error type {e}"),
+        offset
+            + BITBOARD_KINDS
+                .iter()
+                .position(|&other| other == kind)
+                .unwrap_or_default()
+    }
+
+    /// Return the colour whose king is attacked, if any
+    ///
+    /// Only the side to move may legally be in check, so positions where this returns the other
+    /// colour are illegal, since the side that just moved left its own king in check. If both
+    /// kings are attacked the side that just moved is returned, as the position is illegal either
+    /// way.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if there is not exactly one king of each colour
+    pub fn checked_side(&self) -> Result<Option<PieceColour>, ChessError> {
+        if self.king_in_check(!self.turn)? {
+            Ok(Some(!self.turn))
+        } else if self.king_in_check(self.turn)? {
+            Ok(Some(self.turn))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Squares attacked diagonally by `colour`'s pawns
+    ///
+    /// Only the capture squares, not the squares the pawns could push to, and regardless of whether
+    /// anything stands on them.
+    pub fn pawn_attacks(&self, colour: PieceColour) -> SquareSet {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Pawn && piece.colour == colour)
+            .flat_map(|piece| Self::pawn_capture_squares(piece.square, colour))
+            .collect()
+    }
+
+    /// Shortest sequence of at most `max_depth` plies from this position to `target`
+    ///
+    /// Searches with iterative deepening, so the first path found is a shortest one. Positions are
+    /// compared by [`PieceListBoard::zobrist_hash`], which also prunes positions already searched
+    /// at least as deep, and lines are abandoned once fewer pieces remain than in `target`. The
+    /// search grows exponentially with `max_depth`, so keep it small. `None` if no path is found.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn path_to(&self, target: &PieceListBoard, max_depth: u32) -> Result<Option<Vec<AmbiguousMove>>, ChessError> {
+        for depth in 0..=max_depth {
+            let mut path = vec![];
+            if self.search_path(target, depth, &mut path, &mut HashMap::new())? {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Depth first search for `target` within `depth` plies, pushing the moves taken onto `path`
+    fn search_path(
+        &self,
+        target: &PieceListBoard,
+        depth: u32,
+        path: &mut Vec<AmbiguousMove>,
+        searched: &mut HashMap<u64, u32>,
+    ) -> Result<bool, ChessError> {
+        let hash = self.zobrist_hash();
+        if hash == target.zobrist_hash() {
+            return Ok(true);
+        }
+        if depth == 0 || self.pieces.len() < target.pieces.len() {
+            return Ok(false);
+        }
+        if searched
+            .get(&hash)
+            .is_some_and(|&searched_depth| searched_depth >= depth)
+        {
+            return Ok(false);
+        }
+        searched.insert(hash, depth);
+        for chess_move in self.all_legal_moves()? {
+            let mut board = self.clone();
+            board.move_piece(chess_move)?;
+            path.push(self.ambiguous_move(chess_move)?);
+            if board.search_path(target, depth - 1, path, searched)? {
+                return Ok(true);
+            }
+            path.pop();
+        }
+        Ok(false)
+    }
+
+    /// Legal moves taking the piece on `target`, including en passant captures of a pawn there
+    ///
+    /// Empty if there is no enemy piece on `target`.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn captures_of(&self, target: SimpleSquare) -> Result<Vec<AmbiguousMove>, ChessError> {
+        if !self.get_piece(target).is_ok_and(|piece| piece.colour != self.turn) {
+            return Ok(vec![]);
+        }
+        let mut captures = vec![];
+        for chess_move in self.all_legal_moves()? {
+            let (src, dest) = (chess_move.src(), chess_move.dest());
+            let takes_en_passant = Some(dest) == self.en_passant
+                && self.get_piece(src)?.kind == PieceKind::Pawn
+                && SimpleSquare::new(dest.file(), src.rank()) == target;
+            if dest == target || takes_en_passant {
+                captures.push(self.ambiguous_move(chess_move)?);
+            }
         }
+        Ok(captures)
+    }
+
+    /// Moves for the side to move that stalemate the opponent, for warning before a win is thrown
+    /// away
+    ///
+    /// A move stalemates if it leaves the opponent with no legal moves while not in check.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn stalemate_risk_moves(&self) -> Result<Vec<AmbiguousMove>, ChessError> {
+        let mut moves = vec![];
+        for chess_move in self.all_legal_moves()? {
+            if self.stalemates(chess_move)? {
+                moves.push(self.ambiguous_move(chess_move)?);
+            }
+        }
+        Ok(moves)
+    }
+
+    /// Underpromotions for the side to move that avoid the stalemate promoting to a queen would give
+    ///
+    /// For each promotion where queening stalemates the opponent, the promotions to other pieces on
+    /// the same square that don't.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn underpromotion_saves(&self) -> Result<Vec<AmbiguousMove>, ChessError> {
+        let legal_moves: Vec<SimpleMove> = self.all_legal_moves()?.into_iter().collect();
+        let mut saves = vec![];
+        for &queening in legal_moves
+            .iter()
+            .filter(|chess_move| chess_move.promote_to() == Some(PieceKind::Queen))
+        {
+            if !self.stalemates(queening)? {
+                continue;
+            }
+            for &underpromotion in legal_moves.iter().filter(|chess_move| {
+                (chess_move.src(), chess_move.dest()) == (queening.src(), queening.dest())
+                    && chess_move.promote_to() != Some(PieceKind::Queen)
+            }) {
+                if !self.stalemates(underpromotion)? {
+                    saves.push(self.ambiguous_move(underpromotion)?);
+                }
+            }
+        }
+        Ok(saves)
+    }
+
+    /// Check if playing `chess_move` leaves the opponent with no legal moves while not in check
+    fn stalemates(&self, chess_move: SimpleMove) -> Result<bool, ChessError> {
+        let mut board = self.clone();
+        board.move_piece(chess_move)?;
+        Ok(!board.has_legal_move()? && !board.in_check()?)
+    }
+
+    /// Squares of `colour`'s pieces that could be taken for free
+    ///
+    /// A piece is hanging if it is attacked and either undefended or attacked by a piece worth less
+    /// than it, by [`traits::ChessPiece::value`]. Only direct attacks count, ignoring pins and
+    /// pieces lined up behind each other. Kings are never hanging.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state
+    pub fn hanging_pieces(&self, colour: PieceColour) -> Result<Vec<SimpleSquare>, ChessError> {
+        let mut hanging = vec![];
+        for piece in self
+            .pieces
+            .iter()
+            .filter(|piece| piece.colour == colour && piece.kind != PieceKind::King)
+        {
+            let attackers = self.attackers(piece.square, colour)?;
+            if attackers.is_empty() {
+                continue;
+            }
+            let defended = !self.attackers(piece.square, !colour)?.is_empty();
+            let mut cheaper_attacker = false;
+            for attacker in attackers {
+                let attacker = self.get_piece(attacker)?;
+                cheaper_attacker |= attacker.kind != PieceKind::King && attacker.value() < piece.value();
+            }
+            if !defended || cheaper_attacker {
+                hanging.push(piece.square);
+            }
+        }
+        hanging.sort_unstable();
+        Ok(hanging)
+    }
+
+    /// Number of legal moves for the side to move minus the number for its opponent
+    ///
+    /// The opponent's moves are counted as if it were their turn, so negative values mean the side
+    /// to move is the more cramped.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there is not one king of each colour on the board.
+    pub fn mobility_balance(&self) -> Result<i32, ChessError> {
+        let mobility = |colour| -> Result<i32, ChessError> {
+            let moves = self.legal_moves_for(colour)?.into_iter().count();
+            Ok(i32::try_from(moves).unwrap_or(i32::MAX))
+        };
+        Ok(mobility(self.turn)? - mobility(!self.turn)?)
+    }
+
+    /// Number of `colour`'s pieces of `kind` on the board
+    pub fn count(&self, colour: PieceColour, kind: PieceKind) -> u8 {
+        let count = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.colour == colour && piece.kind == kind)
+            .count();
+        u8::try_from(count).unwrap_or(u8::MAX)
+    }
+
+    /// Return true if `colour` has at least one piece of `kind` on the board
+    pub fn has(&self, colour: PieceColour, kind: PieceKind) -> bool {
+        self.pieces
+            .iter()
+            .any(|piece| piece.colour == colour && piece.kind == kind)
+    }
+
+    /// Return true if `colour` has the material to checkmate a lone king
+    ///
+    /// Only `colour`'s own pieces are counted. Any pawn, rook or queen is enough, as are two minor
+    /// pieces unless they are all bishops on the same coloured squares. A lone king or king and
+    /// single minor piece isn't, even though a minor piece can mate when the opponent's own pieces
+    /// hem their king in, such as a knight against a king and pawn.
+    pub fn has_mating_material(&self, colour: PieceColour) -> bool {
+        let pieces: Vec<&PieceWithPos> = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.colour == colour && piece.kind != PieceKind::King)
+            .collect();
+        if pieces
+            .iter()
+            .any(|piece| matches!(piece.kind, PieceKind::Pawn | PieceKind::Rook | PieceKind::Queen))
+        {
+            return true;
+        }
+        let knights = pieces.iter().filter(|piece| piece.kind == PieceKind::Knight).count();
+        let bishop_square_colours: Vec<PieceColour> = pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Bishop)
+            .map(|piece| piece.square.colour())
+            .unique()
+            .collect();
+        pieces.len() >= 2 && (knights > 0 || bishop_square_colours.len() == 2)
+    }
+
+    /// Return true if only the kings and at most one minor piece or same coloured bishops are left
+    ///
+    /// Neither side can checkmate by any series of legal moves in these positions. Unlike
+    /// [`Self::has_mating_material`] this considers both sides together, since a knight each can
+    /// still be mated with the other side's help.
+    pub fn insufficient_material(&self) -> bool {
+        let others: Vec<&PieceWithPos> = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.kind != PieceKind::King)
+            .collect();
+        if others.len() <= 1 {
+            return others
+                .iter()
+                .all(|piece| matches!(piece.kind, PieceKind::Knight | PieceKind::Bishop));
+        }
+        others.iter().all(|piece| piece.kind == PieceKind::Bishop)
+            && others.iter().map(|piece| piece.square.colour()).all_equal()
+    }
+
+    /// Colour of the squares `colour`'s bishop moves on, if it has exactly one bishop
+    pub fn bishop_color_complex(&self, colour: PieceColour) -> Option<PieceColour> {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Bishop && piece.colour == colour)
+            .exactly_one()
+            .ok()
+            .map(|bishop| bishop.square.colour())
+    }
+
+    /// Return true if the material on the board could have come about in a real game
+    ///
+    /// A lightweight retrograde check: each side must have one king, at most 8 pawns, none of them
+    /// on the first or last rank, and no more pieces beyond the starting set than it has lost pawns
+    /// to promote, counting bishops on each colour of square separately. Doesn't look at whether
+    /// the pieces could have reached their squares.
+    pub fn is_plausibly_legal(&self) -> bool {
+        [PieceColour::White, PieceColour::Black].into_iter().all(|colour| {
+            let pawns = self.count(colour, PieceKind::Pawn);
+            let bishops_on = |square_colour| {
+                self.pieces
+                    .iter()
+                    .filter(|piece| {
+                        piece.colour == colour
+                            && piece.kind == PieceKind::Bishop
+                            && piece.square.colour() == square_colour
+                    })
+                    .count()
+            };
+            let promoted = [
+                (usize::from(self.count(colour, PieceKind::Queen)), 1),
+                (usize::from(self.count(colour, PieceKind::Rook)), 2),
+                (usize::from(self.count(colour, PieceKind::Knight)), 2),
+                (bishops_on(PieceColour::White), 1),
+                (bishops_on(PieceColour::Black), 1),
+            ]
+            .into_iter()
+            .map(|(count, starting)| count.saturating_sub(starting))
+            .sum::<usize>();
+            self.count(colour, PieceKind::King) == 1
+                && pawns <= 8
+                && promoted <= usize::from(8 - pawns)
+                && !self.pieces.iter().any(|piece| {
+                    piece.colour == colour && piece.kind == PieceKind::Pawn && [0, 7].contains(&piece.square.rank())
+                })
+        })
+    }
+
+    /// Return true if each side has a single bishop and they move on opposite coloured squares
+    ///
+    /// Only the bishops are considered, so combine with a material check to detect the drawish
+    /// opposite coloured bishop endgame.
+    pub fn opposite_colored_bishops(&self) -> bool {
+        match (
+            self.bishop_color_complex(PieceColour::White),
+            self.bishop_color_complex(PieceColour::Black),
+        ) {
+            (Some(white), Some(black)) => white != black,
+            _ => false,
+        }
+    }
+
+    /// Each of `colour`'s pawns with the number of ranks it has left to reach promotion
+    ///
+    /// Counts ranks, so a pawn on its starting rank is 6 away even though its first move may be a
+    /// double push. Pawns are in no particular order.
+    pub fn pawn_promotion_distances(&self, colour: PieceColour) -> Vec<(SimpleSquare, u8)> {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Pawn && piece.colour == colour)
+            .map(|piece| (piece.square, piece.square.rank().abs_diff(colour.promotion_rank())))
+            .collect()
+    }
+
+    /// Each of `colour`'s pawns with the promotion squares it could reach
+    ///
+    /// These are the square straight ahead on the last rank and those either side of it that the
+    /// pawn could capture on, ignoring any pieces in the way. Pawns are in no particular order.
+    pub fn promotion_paths(&self, colour: PieceColour) -> Vec<(SimpleSquare, Vec<SimpleSquare>)> {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::Pawn && piece.colour == colour)
+            .map(|piece| {
+                let file = piece.square.file();
+                let squares = (file.saturating_sub(1)..=(file + 1).min(7))
+                    .map(|file| SimpleSquare::new(file, colour.promotion_rank()))
+                    .collect();
+                (piece.square, squares)
+            })
+            .collect()
+    }
+
+    /// Result of the game if `flagged` runs out of time in this position
+    ///
+    /// A loss for `flagged`, unless their opponent has no mating material by
+    /// [`Self::has_mating_material`], in which case it is a draw.
+    pub fn timeout_result(&self, flagged: PieceColour) -> GameResult {
+        if self.has_mating_material(!flagged) {
+            GameResult::win_for(!flagged)
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    /// Return true if `colour`'s king stands on an open file, one with no pawns of either colour
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if there is not exactly one king of `colour`
+    pub fn king_on_open_file(&self, colour: PieceColour) -> Result<bool, ChessError> {
+        let king = self.king_square(colour)?;
+        Ok(!self
+            .pieces
+            .iter()
+            .any(|piece| piece.kind == PieceKind::Pawn && piece.square.file() == king.file()))
+    }
+
+    /// Count `colour`'s pawns sheltering its king
+    ///
+    /// Counts friendly pawns on the king's file and the files either side of it, one or two ranks
+    /// in front of the king, so a king castled behind unmoved pawns has a shield of 3.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if there is not exactly one king of `colour`
+    pub fn pawn_shield(&self, colour: PieceColour) -> Result<u8, ChessError> {
+        let king = self.king_square(colour)?;
+        let shield = self
+            .pieces
+            .iter()
+            .filter(|piece| {
+                let ranks_ahead = match colour {
+                    PieceColour::White => i16::from(piece.square.rank()) - i16::from(king.rank()),
+                    PieceColour::Black => i16::from(king.rank()) - i16::from(piece.square.rank()),
+                };
+                piece.kind == PieceKind::Pawn
+                    && piece.colour == colour
+                    && piece.square.file().abs_diff(king.file()) <= 1
+                    && (1..=2).contains(&ranks_ahead)
+            })
+            .count();
+        // At most 6 squares are in front of the king
+        Ok(u8::try_from(shield).unwrap_or(u8::MAX))
+    }
+
+    /// Print self as fen string
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if board in invalid state
+    pub fn as_fen_str(&self) -> Result<String, ChessError> {
+        Ok(Fen::try_from(self)?.to_str())
+    }
+
+    /// FEN followed by a comment line listing the legal moves in SAN, for move generation bug
+    /// reports
+    ///
+    /// Moves are sorted so the output only changes if the generated moves do.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if board in invalid state
+    pub fn debug_fen(&self) -> Result<String, ChessError> {
+        let mut sans: Vec<String> = self
+            .legal_moves_with_boards()?
+            .into_iter()
+            .map(|(san, _)| san)
+            .collect();
+        sans.sort_unstable();
+        Ok(format!("{}\n; Legal moves: {}", self.as_fen_str()?, sans.join(" ")))
+    }
+
+    /// Pass the turn to the other side without moving, for replaying null moves in analysis
+    ///
+    /// Clears the en passant square and advances the move counters as a normal quiet move would.
+    /// Castling rights are kept.
+    pub fn apply_null(&mut self) {
+        self.halfmove_clock += 1;
+        self.board_history.push(self.zobrist_hash());
+        self.en_passant = None;
+        self.turn = !self.turn;
+        if self.turn == PieceColour::White {
+            self.fullmove_number += 1;
+        }
+    }
+
+    /// Rules the board is played under
+    ///
+    /// Boards loaded from a FEN are Chess960 if a rook with castling rights isn't in the corner, as
+    /// written in Shredder-FEN or X-FEN, and standard otherwise.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Play under the rules of `variant`
+    ///
+    /// Needed for Chess960 positions with the rooks in the corners, which castle by the king taking
+    /// its rook rather than moving two squares.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Chess960 starting position numbered `number` in Scharnagl's numbering, from 0 to 959
+    ///
+    /// Pick a number with [`rand::random_range`] for a random start. Standard chess is number 518.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidChess960Position`] if `number` is over 959
+    pub fn chess960_starting_board(number: u16) -> Result<Self, ChessError> {
+        let mut board = Self::from(Fen::chess960(number)?);
+        board.variant = Variant::Chess960;
+        Ok(board)
+    }
+
+    /// Number of plies played before this position, from the fullmove number and side to move
+    ///
+    /// 0 for the standard starting position, 1 after white's first move and so on, so positions
+    /// loaded from FEN mid game continue the count.
+    pub fn ply(&self) -> usize {
+        (self.fullmove_number.saturating_sub(1) as usize) * 2 + usize::from(self.turn == PieceColour::Black)
+    }
+
+    /// En passant square if no pawn of the side not to move could have just double pushed past it,
+    /// or no pawn of the side to move stands beside the pushed pawn to take it
+    fn phantom_en_passant(&self) -> Option<PhantomEnPassant> {
+        let square = self.en_passant?;
+        let (origin_rank, en_passant_rank, pushed_rank) = match self.turn {
+            PieceColour::White => (6, 5, 4),
+            PieceColour::Black => (1, 2, 3),
+        };
+        let pushed = SimpleSquare::new(square.file(), pushed_rank);
+        let origin = SimpleSquare::new(square.file(), origin_rank);
+        let possible = square.rank() == en_passant_rank
+            && self
+                .get_piece(pushed)
+                .is_ok_and(|piece| piece.kind == PieceKind::Pawn && piece.colour != self.turn)
+            && self.get_piece(square).is_err()
+            && self.get_piece(origin).is_err();
+        if !possible {
+            Some(PhantomEnPassant::Unreachable(square))
+        } else if !self.pawn_attacks(self.turn).contains(square) {
+            Some(PhantomEnPassant::Uncapturable(square))
+        } else {
+            None
+        }
+    }
+
+    /// How far the game is from the endgame, from [`MAX_GAME_PHASE`] with all pieces on the board
+    /// down to 0 with only kings and pawns
+    ///
+    /// Knights and bishops count 1, rooks 2 and queens 4, capped at [`MAX_GAME_PHASE`] so extra
+    /// promoted pieces don't push past the opening.
+    pub fn game_phase(&self) -> i32 {
+        let phase: i32 = self
+            .pieces
+            .iter()
+            .map(|piece| match piece.kind {
+                PieceKind::Knight | PieceKind::Bishop => 1,
+                PieceKind::Rook => 2,
+                PieceKind::Queen => 4,
+                PieceKind::Pawn | PieceKind::King => 0,
+            })
+            .sum();
+        phase.min(MAX_GAME_PHASE)
+    }
+
+    /// Piece square table score of `colour`'s pieces with the default tables
+    ///
+    /// See [`PieceListBoard::piece_square_score_with`].
+    pub fn piece_square_score(&self, colour: PieceColour) -> i32 {
+        self.piece_square_score_with(colour, &DefaultEvaluator)
+    }
+
+    /// Piece square table score of `colour`'s pieces with the tables of `evaluator`
+    ///
+    /// Blends the midgame and endgame tables by [`PieceListBoard::game_phase`], so scores shift
+    /// smoothly as pieces are traded.
+    pub fn piece_square_score_with(&self, colour: PieceColour, evaluator: &impl Evaluator) -> i32 {
+        let (mut midgame, mut endgame) = (0, 0);
+        for piece in self.pieces.iter().filter(|piece| piece.colour == colour) {
+            let index = match colour {
+                PieceColour::White => piece.square.to_index(),
+                PieceColour::Black => piece.square.to_index() ^ 56,
+            } as usize;
+            midgame += evaluator.midgame_table(piece.kind)[index];
+            endgame += evaluator.endgame_table(piece.kind)[index];
+        }
+        let phase = self.game_phase();
+        (midgame * phase + endgame * (MAX_GAME_PHASE - phase)) / MAX_GAME_PHASE
+    }
+
+    /// Castling rights of each side for status displays, e.g. `White: K,Q  Black: (none)`
+    ///
+    /// Lists `K` for kingside and `Q` for queenside. Rights are shown even when castling is
+    /// currently prevented by check or pieces in the way.
+    pub fn castling_summary(&self) -> String {
+        let sides = [PieceColour::White, PieceColour::Black].map(|colour| {
+            let offset = Self::castling_right_offset(colour);
+            let rights: Vec<&str> = [(Self::KINGSIDE, "K"), (Self::QUEENSIDE, "Q")]
+                .into_iter()
+                .filter(|&(side, _)| self.castling_rights[offset + side])
+                .map(|(_, letter)| letter)
+                .collect();
+            let rights = if rights.is_empty() {
+                String::from("(none)")
+            } else {
+                rights.join(",")
+            };
+            format!("{colour:?}: {rights}")
+        });
+        sides.join("  ")
+    }
+
+    /// Likely mistakes in a loaded position that don't make the board invalid
+    ///
+    /// Flags an impossible or uncapturable en passant square cleared by
+    /// [`traits::ChessBoard::from_fen_lenient`], an en passant square set up some other way that
+    /// no pawn of the side to move could capture on, and castling rights
+    /// where the king or rook is missing from its starting square. Useful for debugging
+    /// hand written FEN strings.
+    pub fn after_load_warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        match self.cleared_en_passant {
+            Some(PhantomEnPassant::Unreachable(en_passant)) => warnings.push(format!(
+                "en passant square {en_passant} cleared since no pawn could have just skipped it"
+            )),
+            Some(PhantomEnPassant::Uncapturable(en_passant)) => warnings.push(format!(
+                "en passant square {en_passant} cleared since no pawn can capture there"
+            )),
+            None => (),
+        }
+        if let Some(en_passant) = self.en_passant
+            && !self.pawn_attacks(self.turn).contains(en_passant)
+        {
+            warnings.push(format!(
+                "en passant square {en_passant} set but no pawn can capture there"
+            ));
+        }
+        for colour in [PieceColour::White, PieceColour::Black] {
+            for (offset, side) in [
+                (Self::KINGSIDE, CastlingSide::KingSide),
+                (Self::QUEENSIDE, CastlingSide::QueenSide),
+            ] {
+                if !self.castling_rights[Self::castling_right_offset(colour) + offset] {
+                    continue;
+                }
+                let (king_square, _, rook_square, _) = self.castling_squares(colour, side);
+                for (kind, square) in [(PieceKind::King, king_square), (PieceKind::Rook, rook_square)] {
+                    if !self
+                        .get_piece(square)
+                        .is_ok_and(|piece| piece.kind == kind && piece.colour == colour)
+                    {
+                        warnings.push(format!(
+                            "{colour:?} {} castling rights present but {kind:?} missing from {square}",
+                            side.as_str()
+                        ));
+                    }
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl fmt::Display for PieceListBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&diagram::to_diagram(self, &DiagramOptions::default()))
+    }
+}
+
+impl TryFrom<&PieceListBoard> for Fen {
+    type Error = ChessError;
+
+    fn try_from(value: &PieceListBoard) -> Result<Self, ChessError> {
+        let mut layout: Box<[[Option<SimplePiece>; 8]; 8]> = Box::new([[None; 8]; 8]);
+        for (inverse_rank_number, rank) in layout.iter_mut().enumerate() {
+            for (file_number, piece) in rank.iter_mut().enumerate() {
+                *piece = match value.get_piece(SimpleSquare::new(file_number as u8, 7 - inverse_rank_number as u8)) {
+                    Ok(piece) => Some(SimplePiece::from(piece)),
+                    Err(ChessError::PieceNotFound(_)) => None,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(Self {
+            layout,
+            turn: value.turn,
+            castling_rights: value.castling_rights,
+            castling_files: value.castling_files,
+            en_passant: value.en_passant,
+            halfmove_clock: value.halfmove_clock,
+            fullmove_number: value.fullmove_number,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::board::builder::BoardBuilder;
+    use proptest::prelude::*;
+    use std::sync::Arc;
+
+    /// Check that `B` still reads the check marker on a move that also draws the game
+    pub(crate) fn disambiguates_check_into_draw<B: LegalMoveGenerator<Move = SimpleMove>>() {
+        // Qh5+ is the hundredth halfmove without a capture or pawn move, drawing by the fifty move rule
+        let board = B::from_fen("4k3/8/8/8/8/8/8/4K2Q w - - 99 80").unwrap();
+        let expected = SimpleMove::from_uci_str("h1h5").unwrap();
+        assert_eq!(board.disambiguate_move_pgn("Qh5+").unwrap(), expected);
+        assert_eq!(board.disambiguate_move_pgn("Qh5").unwrap(), expected);
+        assert!(matches!(
+            board.disambiguate_move_pgn("Qh5#"),
+            Err(ChessError::ImpossibleMove(_))
+        ));
+    }
+
+    /// Square written in PGN, such as `e4`
+    pub(crate) fn square(square: &str) -> SimpleSquare {
+        SimpleSquare::from_pgn_str(square).unwrap()
+    }
+
+    fn moves_from_strs(moves: Vec<&str>) -> Vec<SimpleMove> {
+        let mut new_moves: Vec<SimpleMove> = moves
+            .into_iter()
+            .map(|s| SimpleMove::from_pgn_str(s).unwrap())
+            .collect();
+        new_moves.sort();
+        new_moves
+    }
+
+    #[test]
+    fn normal_square() {
+        let square = SimpleSquare::new(5, 4);
+        assert_eq!(square.file(), 5);
+        assert_eq!(square.rank(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rank must be between 0-7 inclusive, 8 > 7")]
+    fn wrong_range_square() {
+        let _ = SimpleSquare::new(3, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "Chess move cannot originate and terminate at same square")]
+    fn duplicate_move() {
+        let _ = SimpleMove::new(SimpleSquare::new(3, 4), SimpleSquare::new(3, 4), None);
+    }
+
+    #[test]
+    fn two_on_same_square() {
+        let square = SimpleSquare::new(3, 2);
+        let board = PieceListBoard {
+            pieces: vec![
+                PieceWithPos::new(square, PieceKind::Knight, PieceColour::Black),
+                PieceWithPos::new(square, PieceKind::Bishop, PieceColour::White),
+            ],
+            turn: PieceColour::White,
+            en_passant: None,
+            castling_rights: [false, false, false, false],
+            castling_files: STANDARD_CASTLING_FILES,
+            variant: Variant::Standard,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            board_history: PositionHistory::default(),
+            cleared_en_passant: None,
+        };
+        let e = board.get_piece(square).unwrap_err();
+        match e {
+            ChessError::InvalidBoard(s) => assert_eq!(s, format!("Two pieces found at {square}")),
+            _ => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn none_on_square() {
+        let square = SimpleSquare::new(3, 2);
+        let board = PieceListBoard {
+            pieces: vec![],
+            turn: PieceColour::White,
+            en_passant: None,
+            castling_rights: [false, false, false, false],
+            castling_files: STANDARD_CASTLING_FILES,
+            variant: Variant::Standard,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            board_history: PositionHistory::default(),
+            cleared_en_passant: None,
+        };
+        let e = board.get_piece(square).unwrap_err();
+        match e {
+            ChessError::PieceNotFound(_) => (),
+            _ => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn white_pawn_double_push() {
+        let board = PieceListBoard::starting_board();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("e2").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["e2e3", "e2e4"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn black_pawn_double_push() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("h7").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["h7h6", "h7h5"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn pawn_double_push_blocked() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/2P5/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("c7").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["c7c6"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn pawn_takes() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("e4").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["e4e5", "e4d5"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn pawn_takes_en_passant_behind() {
+        // Built from the parsed FEN directly since loading rejects the impossible en passant square
+        let fen = parser::fen::fen("rnbqkbnr/pppppppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 2").unwrap();
+        let board = PieceListBoard::from(fen.1);
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("e4").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["e4e5", "e4d5"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn en_passant() {
+        let mut board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5P2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("g7g5").unwrap()).unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["f5f6", "f5g6"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn knight_moves() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/ppppppp1/7p/8/6N1/8/PPPPPPPP/RNBQKB1R w KQkq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("g4").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["g4h6", "g4f6", "g4e3", "g4e5"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn bishop_moves() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5b2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["f5g6", "f5e6", "f5e4", "f5d3", "f5c2", "f5g4", "f5h3"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn rook_moves() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/2R2P2/8/8/PPPPP1PP/RNBQKBNR w KQkq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("c5").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["c5b5", "c5a5", "c5d5", "c5e5", "c5c6", "c5c7", "c5c4", "c5c3"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn queen_moves() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5Q2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec![
+            "f5f6", "f5f7", "f5f4", "f5f3", "f5g5", "f5h5", "f5e5", "f5d5", "f5c5", "f5b5", "f5a5", "f5g6", "f5h7",
+            "f5g4", "f5h3", "f5e6", "f5d7", "f5e4", "f5d3",
+        ]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn king_moves() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppp1ppp/4p3/4KP2/8/8/PPPP1PPP/RNBQ1BNR w kq - 0 2").unwrap();
+        let mut moves: Vec<SimpleMove> = board
+            .piece_plegal_moves(SimpleSquare::from_pgn_str("e5").unwrap())
+            .unwrap()
+            .into_iter()
+            .collect();
+        moves.sort();
+        let exp_moves = moves_from_strs(vec!["e5e6", "e5f6", "e5d6", "e5d5", "e5d4", "e5e4", "e5f4"]);
+        assert_eq!(moves, exp_moves);
+    }
+
+    #[test]
+    fn king_in_check() {
+        let board = PieceListBoard::from_fen("k3r3/1P6/4K3/8/8/8/8/8 w - - 0 2").unwrap();
+        assert!(board.king_in_check(PieceColour::White).unwrap());
+        assert!(board.king_in_check(PieceColour::Black).unwrap());
+        assert_eq!(board.state().unwrap(), BoardState::Check);
+    }
+
+    #[test]
+    fn king_not_in_check() {
+        let board = PieceListBoard::from_fen("k3r3/8/1P6/3K4/8/8/8/8 w - - 0 2").unwrap();
+        assert!(!board.king_in_check(PieceColour::White).unwrap());
+        assert!(!board.king_in_check(PieceColour::Black).unwrap());
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+    }
+
+    #[test]
+    fn checkmate_or_stalemate() {
+        let positions = [
+            (
+                "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+                BoardState::Checkmate,
+            ),
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", BoardState::Stalemate),
+            ("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", BoardState::Checkmate),
+            ("7k/6Q1/8/6K1/8/8/8/8 b - - 0 1", BoardState::Check),
+        ];
+        for (fen, state) in positions {
+            let board = PieceListBoard::from_fen(fen).unwrap();
+            assert_eq!(board.has_legal_move().unwrap(), state == BoardState::Check, "{fen}");
+            assert_eq!(board.state().unwrap(), state, "{fen}");
+        }
+    }
+
+    #[test]
+    fn checking_moves() {
+        let board = PieceListBoard::from_fen("k7/8/8/8/8/8/8/K5RR w - - 0 1").unwrap();
+        let moves: Vec<SimpleMove> = board.checking_moves().unwrap().into_iter().sorted().collect();
+        assert_eq!(moves, moves_from_strs(vec!["g1g8", "h1h8"]));
+    }
+
+    #[test]
+    fn pinned_piece() {
+        let board = PieceListBoard::from_fen("k3r3/8/4N3/8/4K3/8/8/8 w - - 0 2").unwrap();
+        assert!(
+            board
+                .piece_legal_moves(SimpleSquare::from_pgn_str("e6").unwrap())
+                .unwrap()
+                .into_iter()
+                .next()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn legal_moves_for_side_to_move() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let moves: Vec<SimpleMove> = board.all_legal_moves().unwrap().into_iter().sorted().collect();
+        let moves_for: Vec<SimpleMove> = board
+            .legal_moves_for(PieceColour::Black)
+            .unwrap()
+            .into_iter()
+            .sorted()
+            .collect();
+        assert_eq!(moves, moves_for);
+    }
+
+    #[test]
+    fn legal_moves_for_other_side() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let moves: Vec<SimpleMove> = board.legal_moves_for(PieceColour::White).unwrap().into_iter().collect();
+        assert_eq!(moves.len(), 30);
+        assert!(moves.contains(&SimpleMove::from_pgn_str("d1h5").unwrap()));
+        assert_eq!(board.turn, PieceColour::Black);
+    }
+
+    #[test]
+    fn zobrist_transposition() {
+        let mut board = PieceListBoard::starting_board();
+        let mut transposed = PieceListBoard::starting_board();
+        for chess_move in ["g1f3", "g8f6", "b1c3"] {
+            board.move_piece(SimpleMove::from_pgn_str(chess_move).unwrap()).unwrap();
+        }
+        for chess_move in ["b1c3", "g8f6", "g1f3"] {
+            transposed
+                .move_piece(SimpleMove::from_pgn_str(chess_move).unwrap())
+                .unwrap();
+        }
+        assert_eq!(board.zobrist_hash(), transposed.zobrist_hash());
+        assert_ne!(board.zobrist_hash(), PieceListBoard::starting_board().zobrist_hash());
+    }
+
+    #[test]
+    fn zobrist_en_passant() {
+        let board = PieceListBoard::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let no_en_passant =
+            PieceListBoard::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_ne!(board.zobrist_hash(), no_en_passant.zobrist_hash());
+    }
+
+    #[test]
+    fn zobrist_ignores_move_counters() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let later = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 37 80").unwrap();
+        assert_eq!(board.zobrist_hash(), later.zobrist_hash());
+        let black_to_move = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1").unwrap();
+        assert_ne!(board.zobrist_hash(), black_to_move.zobrist_hash());
+    }
+
+    #[test]
+    fn castle_through_attacked_square() {
+        // The bishop on a6 attacks f1, which the king would pass through
+        let board = PieceListBoard::from_fen("4k3/8/b7/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(!board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+        // The bishop on e4 attacks b1, which only the rook passes through
+        let board = PieceListBoard::from_fen("4k3/8/8/8/4b3/8/8/R3K3 w Q - 0 1").unwrap();
+        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1c1").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn illegal_castle() {
+        let board = PieceListBoard::from_fen("rn1qkbnr/ppp2ppp/3p4/1b2N3/4P3/8/PPPP1PPP/RNBQK2R w KQkq - 0 1").unwrap();
+        assert!(!board.is_move_plegal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+        assert!(!board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn legal_castle() {
+        let board =
+            PieceListBoard::from_fen("rn1qkbnr/pppb1ppp/3p4/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1").unwrap();
+        assert!(board.is_move_plegal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn queenside_castle_no_knight() {
+        let board =
+            PieceListBoard::from_fen("r1bqk2r/ppp1bppp/2np1n2/4p3/4P3/2NPB3/PPP1QPPP/R3KBNR w KQkq - 0 1").unwrap();
+        assert!(board.is_move_plegal(SimpleMove::from_pgn_str("e1c1").unwrap()).unwrap());
+        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1c1").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn castling_invalidation_king_move() {
+        let mut board =
+            PieceListBoard::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("e1e2").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [false, false, true, true]);
+        board.move_piece(SimpleMove::from_pgn_str("e8e7").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [false, false, false, false]);
+    }
+
+    #[test]
+    fn castling_invalidation_queenside_rook_move() {
+        let mut board =
+            PieceListBoard::from_fen("r1bqkbnr/pppppppp/2n5/8/8/2N5/PPPPPPPP/R1BQKBNR w KQkq - 0 1").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("a1b1").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [true, false, true, true]);
+        board.move_piece(SimpleMove::from_pgn_str("a8b8").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [true, false, true, false]);
+    }
+
+    #[test]
+    fn castling_invalidation_kingside_rook_move() {
+        let mut board =
+            PieceListBoard::from_fen("rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("h1g1").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [false, true, true, true]);
+        board.move_piece(SimpleMove::from_pgn_str("h8g8").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [false, true, false, true]);
+    }
+
+    #[test]
+    fn fifty_move_draw() {
+        let mut board = PieceListBoard::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 99 80").unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+        board.move_piece(SimpleMove::from_pgn_str("a1a2").unwrap()).unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Draw(DrawReason::FiftyMoves));
+        // The draw is only reported, moves are still generated
+        assert_eq!(board.all_legal_moves().unwrap().into_iter().count(), 5);
+
+        let mut board = PieceListBoard::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 149 105").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("a1a2").unwrap()).unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Draw(DrawReason::SeventyFiveMoves));
+
+        // Checkmate on the last move ends the game before the draw
+        let mut board = PieceListBoard::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("a1a8").unwrap()).unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Checkmate);
+    }
+
+    #[test]
+    fn fifty_move_not_draw() {
+        let mut board = PieceListBoard::starting_board();
+        for _ in 0..12 {
+            board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("f6g8").unwrap()).unwrap();
+        }
+        board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("e7e5").unwrap()).unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut board = PieceListBoard::starting_board();
+        for _ in 0..3 {
+            board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
+            board.move_piece(SimpleMove::from_pgn_str("f6g8").unwrap()).unwrap();
+        }
+        assert_eq!(
+            board.state().unwrap(),
+            BoardState::Draw(DrawReason::ThreefoldRepetition)
+        );
+        for chess_move in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            board.move_piece(SimpleMove::from_pgn_str(chess_move).unwrap()).unwrap();
+        }
+        assert_eq!(board.state().unwrap(), BoardState::Draw(DrawReason::FivefoldRepetition));
+
+        // Same pieces on the same squares, but a pawn move in between makes them different positions
+        let mut board = PieceListBoard::starting_board();
+        for chess_move in ["g1f3", "g8f6", "f3g1", "f6g8", "e2e4"] {
+            board.move_piece(SimpleMove::from_pgn_str(chess_move).unwrap()).unwrap();
+        }
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let positions = [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", false),
+            ("4kn2/8/8/8/8/8/8/4KN2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4KNN1 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4KR2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ];
+        for (fen, insufficient) in positions {
+            let board = PieceListBoard::from_fen(fen).unwrap();
+            assert_eq!(board.insufficient_material(), insufficient, "{fen}");
+            assert_eq!(
+                board.state().unwrap() == BoardState::Draw(DrawReason::InsufficientMaterial),
+                insufficient,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn perft_known_counts() {
+        let board = PieceListBoard::starting_board();
+        assert_eq!(board.perft(0).unwrap(), 1);
+        assert_eq!(board.perft(3).unwrap(), 8902);
+        let kiwipete =
+            PieceListBoard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(kiwipete.perft(2).unwrap(), 2039);
+        let endgame = PieceListBoard::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(endgame.perft(3).unwrap(), 2812);
+        // Drawn by insufficient material, but the moves are still counted
+        let drawn = PieceListBoard::from_fen("8/8/8/8/8/8/8/KN5k w - - 0 1").unwrap();
+        assert_eq!(drawn.perft(1).unwrap(), 5);
+    }
+
+    #[test]
+    fn branching_stats() {
+        let board = PieceListBoard::starting_board();
+        assert_eq!(board.branching_stats(3).unwrap(), [20, 400, 8902]);
+        assert!(board.branching_stats(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn perft_hash() {
+        let board = PieceListBoard::starting_board();
+        assert_eq!(board.perft_hash(3).unwrap(), 399_531_318_405_714_452);
+        assert_ne!(board.perft_hash(2).unwrap(), board.perft_hash(3).unwrap());
+        let mirrored = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(mirrored.perft(3).unwrap(), board.perft(3).unwrap());
+        assert_ne!(mirrored.perft_hash(3).unwrap(), board.perft_hash(3).unwrap());
+    }
+
+    #[test]
+    fn perft_divide_sums_to_perft() {
+        let board = PieceListBoard::starting_board();
+        let divide = board.perft_divide(2).unwrap();
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|&(_, nodes)| nodes == 20));
+        assert!(board.perft_divide(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn castled_king_safety() {
+        let board = PieceListBoard::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert!(!board.king_on_open_file(PieceColour::White).unwrap());
+        assert_eq!(board.pawn_shield(PieceColour::White).unwrap(), 3);
+        assert_eq!(board.pawn_shield(PieceColour::Black).unwrap(), 3);
+        let advanced = PieceListBoard::from_fen("6k1/5p1p/6p1/8/8/6P1/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(advanced.pawn_shield(PieceColour::White).unwrap(), 3);
+        assert_eq!(advanced.pawn_shield(PieceColour::Black).unwrap(), 3);
+    }
+
+    #[test]
+    fn exposed_king_safety() {
+        let board = PieceListBoard::from_fen("6k1/5p1p/8/8/8/7P/5P2/6K1 w - - 0 1").unwrap();
+        assert!(board.king_on_open_file(PieceColour::White).unwrap());
+        assert!(board.king_on_open_file(PieceColour::Black).unwrap());
+        assert_eq!(board.pawn_shield(PieceColour::White).unwrap(), 2);
+        assert_eq!(board.pawn_shield(PieceColour::Black).unwrap(), 2);
+        let pawn_storm = PieceListBoard::from_fen("6k1/8/8/8/8/8/6p1/6K1 w - - 0 1").unwrap();
+        assert!(!pawn_storm.king_on_open_file(PieceColour::White).unwrap());
+        assert_eq!(pawn_storm.pawn_shield(PieceColour::White).unwrap(), 0);
+    }
+
+    #[test]
+    fn variation_tree_shares_history() {
+        let mut board = PieceListBoard::starting_board();
+        for chess_move in ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6"] {
+            board.move_piece(SimpleMove::from_pgn_str(chess_move).unwrap()).unwrap();
+        }
+        let children: Vec<PieceListBoard> = board
+            .all_legal_moves()
+            .unwrap()
+            .into_iter()
+            .map(|chess_move| {
+                let mut child = board.clone();
+                child.move_piece(chess_move).unwrap();
+                child
+            })
+            .collect();
+        assert!(!children.is_empty());
+        let shared = board.board_history.0.as_ref().unwrap();
+        for child in &children {
+            assert_eq!(child.board_history.iter().count(), 9);
+            assert!(Arc::ptr_eq(
+                child.board_history.0.as_ref().unwrap().previous.0.as_ref().unwrap(),
+                shared
+            ));
+        }
+    }
+
+    #[test]
+    fn legal_moves_with_boards_mate_in_one() {
+        let board = PieceListBoard::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let moves = board.legal_moves_with_boards().unwrap();
+        assert_eq!(moves.len(), board.all_legal_moves().unwrap().into_iter().count());
+        let mates: Vec<&str> = moves
+            .iter()
+            .filter(|(_, board)| board.state().unwrap() == BoardState::Checkmate)
+            .map(|(san, _)| san.as_str())
+            .collect();
+        assert_eq!(mates, ["Ra8#"]);
+    }
+
+    #[test]
+    fn ambiguous_move_disambiguation() {
+        let board = PieceListBoard::from_fen("8/7k/8/R3p3/3P4/8/8/RN3NK1 w - - 0 1").unwrap();
+        for (chess_move, san) in [
+            ("b1d2", "Nbd2"),
+            ("a1a3", "R1a3"),
+            ("a5a3", "R5a3"),
+            ("d4e5", "dxe5"),
+            ("a5e5", "Rxe5"),
+            ("a5a7", "Ra7+"),
+        ] {
+            let chess_move = SimpleMove::from_pgn_str(chess_move).unwrap();
+            assert_eq!(board.ambiguous_move(chess_move).unwrap().as_pgn_str(), san);
+        }
+    }
+
+    #[test]
+    fn three_queens_disambiguation() {
+        // The h4 queen shares its file with the h1 queen and its rank with the e4 queen
+        let board = PieceListBoard::from_fen("2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1").unwrap();
+        for (chess_move, san) in [("h4e1", "Qh4e1"), ("e4e1", "Qee1"), ("h1e1", "Q1e1")] {
+            let chess_move = SimpleMove::from_pgn_str(chess_move).unwrap();
+            assert_eq!(board.ambiguous_move(chess_move).unwrap().as_pgn_str(), san);
+        }
+    }
+
+    #[test]
+    fn pawn_san_source_file() {
+        // Both pawns can take on d5, and the e-pawn can also push
+        let board = PieceListBoard::from_fen("4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1").unwrap();
+        for (chess_move, san) in [("c4d5", "cxd5"), ("e4d5", "exd5"), ("e4e5", "e5"), ("c4c5", "c5")] {
+            let chess_move = SimpleMove::from_pgn_str(chess_move).unwrap();
+            assert_eq!(board.ambiguous_move(chess_move).unwrap().as_pgn_str(), san);
+        }
+    }
+
+    #[test]
+    fn ambiguous_move_round_trip() {
+        let board =
+            PieceListBoard::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for chess_move in board.all_legal_moves().unwrap() {
+            let ambiguous = board.ambiguous_move(chess_move).unwrap();
+            assert_eq!(board.disambiguate_move(ambiguous).unwrap(), chess_move, "{ambiguous}");
+        }
+    }
+
+    #[test]
+    fn checked_side() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4KR2 w - - 0 1").unwrap();
+        assert_eq!(board.checked_side().unwrap(), None);
+        let legal = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(legal.checked_side().unwrap(), Some(PieceColour::White));
+        let illegal = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K2r b - - 0 1").unwrap();
+        assert_eq!(illegal.checked_side().unwrap(), Some(PieceColour::White));
+        let both = PieceListBoard::from_fen("4k2R/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(both.checked_side().unwrap(), Some(PieceColour::Black));
+        let no_king = PieceListBoard::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        no_king.checked_side().unwrap_err();
+    }
+
+    #[test]
+    fn pawn_attacks() {
+        let board = PieceListBoard::starting_board();
+        assert_eq!(board.pawn_attacks(PieceColour::White), SquareSet::from_bits(0xFF << 16));
+        assert_eq!(board.pawn_attacks(PieceColour::Black), SquareSet::from_bits(0xFF << 40));
+        let edge = PieceListBoard::from_fen("4k3/8/8/8/8/8/P6P/4K3 w - - 0 1").unwrap();
+        let attacks: Vec<String> = edge
+            .pawn_attacks(PieceColour::White)
+            .iter()
+            .map(|s| s.as_str())
+            .collect();
+        assert_eq!(attacks, ["b3", "g3"]);
+    }
+
+    #[test]
+    fn ordered_moves() {
+        let board = PieceListBoard::from_fen("4k3/7P/8/3q4/p3P3/8/8/3QK3 w - - 0 1").unwrap();
+        let moves: Vec<String> = board.ordered_moves().unwrap().iter().map(SimpleMove::as_str).collect();
+        assert_eq!(moves.len(), board.all_legal_moves().unwrap().into_iter().count());
+        assert_eq!(
+            moves[..7],
+            ["e4d5", "d1d5", "d1a4", "h7h8=Q", "h7h8=R", "h7h8=B", "h7h8=N"]
+        );
+        assert!(!moves[7..].iter().any(|chess_move| chess_move.contains('=')));
+    }
+
+    #[test]
+    fn mating_material() {
+        for (fen, white, black) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", false, false),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", false, false),
+            ("4k3/8/8/8/8/8/8/4KR2 w - - 0 1", true, false),
+            ("4k3/8/8/8/8/8/8/3BKB2 w - - 0 1", false, false),
+            ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", true, false),
+            ("4kn2/8/8/8/8/8/8/4KN2 w - - 0 1", false, false),
+            ("4kn2/p7/8/8/8/8/8/3NKN2 w - - 0 1", true, true),
+        ] {
+            let board = PieceListBoard::from_fen(fen).unwrap();
+            assert_eq!(board.has_mating_material(PieceColour::White), white, "{fen}");
+            assert_eq!(board.has_mating_material(PieceColour::Black), black, "{fen}");
+        }
+    }
+
+    #[test]
+    fn mobility_balance() {
+        assert_eq!(PieceListBoard::starting_board().mobility_balance().unwrap(), 0);
+        let cramped = PieceListBoard::from_fen("7k/6pp/8/8/8/8/8/Q3K3 b - - 0 1").unwrap();
+        // The g7 pawn is pinned, leaving black with Kg8, h6 and h5 against white's 16 queen and 5
+        // king moves
+        assert_eq!(cramped.mobility_balance().unwrap(), 3 - 21);
+    }
+
+    #[test]
+    fn to_grid() {
+        let grid = PieceListBoard::starting_board().to_grid();
+        assert_eq!(grid[0][0], Some((PieceColour::Black, PieceKind::Rook)));
+        assert_eq!(grid[0][4], Some((PieceColour::Black, PieceKind::King)));
+        assert_eq!(grid[6][3], Some((PieceColour::White, PieceKind::Pawn)));
+        assert_eq!(grid[7][3], Some((PieceColour::White, PieceKind::Queen)));
+        assert_eq!(grid[4][4], None);
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let mut board = PieceListBoard::from_fen("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1").unwrap();
+        assert_eq!(board.variant(), Variant::Chess960);
+        let castles: Vec<SimpleMove> = board
+            .all_legal_moves()
+            .unwrap()
+            .into_iter()
+            .filter(|chess_move| board.detect_castle(chess_move.src(), chess_move.dest()).is_some())
+            .sorted()
+            .collect();
+        assert_eq!(castles, moves_from_strs(vec!["e1b1", "e1g1"]));
+        assert_eq!(
+            board.ambiguous_move(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap(),
+            AmbiguousMove::Castle {
+                side: CastlingSide::KingSide,
+                action: None
+            }
+        );
+        board
+            .move_piece_legal(board.disambiguate_move_pgn("O-O-O").unwrap())
+            .unwrap();
+        assert_eq!(
+            board.as_fen_str().unwrap(),
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 b gb - 1 1"
+        );
+
+        // The king can stay put while the rook moves past it
+        let mut board = PieceListBoard::from_fen("6kr/8/8/8/8/8/8/6KR w Kk - 0 1").unwrap();
+        assert_eq!(board.variant(), Variant::Standard);
+        board.set_variant(Variant::Chess960);
+        board
+            .move_piece_legal(SimpleMove::from_pgn_str("g1h1").unwrap())
+            .unwrap();
+        assert_eq!(board.as_fen_str().unwrap(), "6kr/8/8/8/8/8/8/5RK1 b k - 1 1");
+
+        // Only the squares the king crosses need to be safe
+        let board = PieceListBoard::from_fen("1r2kr2/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1b1").unwrap()).unwrap());
+        assert!(!board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+        let board = PieceListBoard::from_fen("1r2k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1b1").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn chess960_perft() {
+        let board =
+            PieceListBoard::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(board.perft(1).unwrap(), 21);
+        assert_eq!(board.perft(2).unwrap(), 528);
+        assert_eq!(board.perft(3).unwrap(), 12_189);
+        let board = PieceListBoard::chess960_starting_board(518).unwrap();
+        assert_eq!(board.variant(), Variant::Chess960);
+        assert_eq!(
+            board.all_pieces().into_iter().collect_vec(),
+            PieceListBoard::starting_board().all_pieces().into_iter().collect_vec()
+        );
+        PieceListBoard::chess960_starting_board(960).unwrap_err();
+    }
+
+    #[test]
+    fn detect_castle() {
+        let board = PieceListBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            board.detect_castle(square("e1"), square("g1")),
+            Some(CastlingSide::KingSide)
+        );
+        assert_eq!(
+            board.detect_castle(square("e8"), square("c8")),
+            Some(CastlingSide::QueenSide)
+        );
+        assert_eq!(board.detect_castle(square("e1"), square("f1")), None);
+        assert_eq!(board.detect_castle(square("a1"), square("c1")), None);
+    }
+
+    #[test]
+    fn en_passant_moves() {
+        let board = PieceListBoard::from_fen("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let mut moves = board.en_passant_moves().unwrap();
+        moves.sort();
+        assert_eq!(moves, moves_from_strs(vec!["c5d6", "e5d6"]));
+        assert!(PieceListBoard::starting_board().en_passant_moves().unwrap().is_empty());
+    }
+
+    #[test]
+    fn en_passant_exposes_king_on_rank() {
+        let board = PieceListBoard::from_fen("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1").unwrap();
+        assert!(board.en_passant_moves().unwrap().is_empty());
+        assert!(!board.is_move_legal(SimpleMove::from_pgn_str("b5c6").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn opposite_colored_bishops() {
+        let board = PieceListBoard::from_fen("4k3/5p2/4b3/8/8/4B3/5P2/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.bishop_color_complex(PieceColour::White), Some(PieceColour::Black));
+        assert_eq!(board.bishop_color_complex(PieceColour::Black), Some(PieceColour::White));
+        assert!(board.opposite_colored_bishops());
+        let same = PieceListBoard::from_fen("4k3/5p2/3b4/8/8/4B3/5P2/4K3 w - - 0 1").unwrap();
+        assert!(!same.opposite_colored_bishops());
+        assert_eq!(
+            PieceListBoard::starting_board().bishop_color_complex(PieceColour::White),
+            None
+        );
+    }
+
+    #[test]
+    fn count_and_has() {
+        let mut board = PieceListBoard::starting_board();
+        assert_eq!(board.count(PieceColour::White, PieceKind::Pawn), 8);
+        assert!(board.has(PieceColour::White, PieceKind::Queen));
+        for pgn in ["e4", "e5", "Qh5", "Nc6", "Qxf7+", "Kxf7"] {
+            let chess_move = board.disambiguate_move_pgn(pgn).unwrap();
+            board.move_piece_legal(chess_move).unwrap();
+        }
+        assert_eq!(board.count(PieceColour::White, PieceKind::Queen), 0);
+        assert!(!board.has(PieceColour::White, PieceKind::Queen));
+        assert_eq!(board.count(PieceColour::Black, PieceKind::Pawn), 7);
+    }
+
+    #[test]
+    fn pinned_rook_moves_along_pin() {
+        let board = PieceListBoard::from_fen("k3r3/8/8/8/4R3/8/8/4K3 w - - 0 1").unwrap();
+        let square = SimpleSquare::from_pgn_str("e4").unwrap();
+        let mut moves: Vec<SimpleMove> = board.piece_legal_moves(square).unwrap().into_iter().collect();
+        moves.sort();
+        assert_eq!(
+            moves,
+            moves_from_strs(vec!["e4e2", "e4e3", "e4e5", "e4e6", "e4e7", "e4e8"])
+        );
+    }
+
+    #[test]
+    fn debug_fen() {
+        let board = PieceListBoard::from_fen("7k/8/8/8/8/8/8/K5R1 w - - 0 1").unwrap();
+        let debug = board.debug_fen().unwrap();
+        let (fen, moves) = debug.split_once('\n').unwrap();
+        assert_eq!(fen, "7k/8/8/8/8/8/8/K5R1 w - - 0 1");
+        assert!(moves.starts_with("; Legal moves: Ka2 Kb1 Kb2 Rb1 "));
+        assert!(moves.contains(" Rg8+ "));
+        assert_eq!(moves.split_whitespace().count(), 3 + 3 + 13);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 0 3",
+            "8/8/4k3/8/8/4K3/8/7R w - - 37 102",
+        ] {
+            assert_eq!(PieceListBoard::from_fen(fen).unwrap().as_fen_str().unwrap(), fen);
+        }
+        for fen in [
+            "",
+            "8/8/8/8/8/8/8 w - - 0 1",
+            "8/8/8/8/8/8/8/8 x - - 0 1",
+            "8/8/8/8/8/8/8/8 w X - 0 1",
+        ] {
+            assert!(matches!(PieceListBoard::from_fen(fen), Err(ChessError::InvalidFEN(_))));
+        }
+    }
+
+    #[test]
+    fn pawn_promotion_distances() {
+        let board = PieceListBoard::from_fen("4k3/P7/8/8/8/8/5p2/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_promotion_distances(PieceColour::White), [(square("a7"), 1)]);
+        assert_eq!(board.pawn_promotion_distances(PieceColour::Black), [(square("f2"), 1)]);
+        let mut start = PieceListBoard::starting_board().pawn_promotion_distances(PieceColour::Black);
+        start.dedup_by_key(|(_, distance)| *distance);
+        assert_eq!(start.len(), 1);
+        assert_eq!(start[0].1, 6);
+    }
+
+    #[test]
+    fn check_escapes() {
+        // Rook on e8 checks the king on e1, the bishop on b5 can take it or block on e2 and the
+        // knight on c3 can block on e2 or e4
+        let board = PieceListBoard::from_fen("4r2k/8/8/1B6/8/2N5/8/4K3 w - - 0 1").unwrap();
+        let escapes = board.check_escapes().unwrap();
+        let mut king_moves = escapes.king_moves;
+        king_moves.sort();
+        assert_eq!(king_moves, moves_from_strs(vec!["e1d1", "e1d2", "e1f1", "e1f2"]));
+        assert_eq!(escapes.captures, moves_from_strs(vec!["b5e8"]));
+        let mut blocks = escapes.blocks;
+        blocks.sort();
+        assert_eq!(blocks, moves_from_strs(vec!["b5e2", "c3e2", "c3e4"]));
+        assert_eq!(
+            PieceListBoard::starting_board().check_escapes().unwrap(),
+            CheckEscapes::default()
+        );
+    }
+
+    #[test]
+    fn legal_moves_to() {
+        let centre: SquareSet = ["d4", "e4", "d5", "e5"]
+            .into_iter()
+            .map(|s| SimpleSquare::from_pgn_str(s).unwrap())
+            .collect();
+        let mut board = PieceListBoard::starting_board();
+        let mut moves = board.legal_moves_to(centre).unwrap();
+        moves.sort();
+        assert_eq!(moves, moves_from_strs(vec!["d2d4", "e2e4"]));
+        board.move_piece(SimpleMove::from_pgn_str("e2e4").unwrap()).unwrap();
+        let mut moves = board.legal_moves_to(centre).unwrap();
+        moves.sort();
+        assert_eq!(moves, moves_from_strs(vec!["d7d5", "e7e5"]));
+        assert!(board.legal_moves_to(SquareSet::EMPTY).unwrap().is_empty());
+    }
+
+    #[test]
+    fn timeout_result() {
+        let board = PieceListBoard::starting_board();
+        assert_eq!(board.timeout_result(PieceColour::White), GameResult::BlackWin);
+        assert_eq!(board.timeout_result(PieceColour::Black), GameResult::WhiteWin);
+        let lone_king = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/QR2K3 b - - 0 1").unwrap();
+        assert_eq!(lone_king.timeout_result(PieceColour::White), GameResult::Draw);
+        assert_eq!(lone_king.timeout_result(PieceColour::Black), GameResult::WhiteWin);
+    }
+
+    #[test]
+    fn moves_of_kind() {
+        let board = PieceListBoard::starting_board();
+        let mut knight_moves = board.moves_of_kind(PieceKind::Knight).unwrap();
+        knight_moves.sort();
+        assert_eq!(knight_moves, moves_from_strs(vec!["b1a3", "b1c3", "g1f3", "g1h3"]));
+        assert!(board.moves_of_kind(PieceKind::Queen).unwrap().is_empty());
+        assert_eq!(board.moves_of_kind(PieceKind::Pawn).unwrap().len(), 16);
+    }
+
+    #[test]
+    fn is_symmetric_to() {
+        let board = PieceListBoard::from_fen("6k1/5ppp/8/8/8/8/1Q6/K7 w - - 0 1").unwrap();
+        let mirrored = PieceListBoard::from_fen("1k6/ppp5/8/8/8/8/6Q1/7K w - - 0 1").unwrap();
+        assert!(board.is_symmetric_to(&mirrored));
+        let colours_swapped = PieceListBoard::from_fen("k7/1q6/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        assert!(board.is_symmetric_to(&colours_swapped));
+        let wrong_turn = PieceListBoard::from_fen("1k6/ppp5/8/8/8/8/6Q1/7K b - - 0 1").unwrap();
+        assert!(!board.is_symmetric_to(&wrong_turn));
+        assert!(!board.is_symmetric_to(&PieceListBoard::starting_board()));
+    }
+
+    #[test]
+    fn legal_sans_sorted() {
+        let sans = PieceListBoard::starting_board().legal_sans_sorted().unwrap();
+        let pawn_moves = [
+            "a3", "a4", "b3", "b4", "c3", "c4", "d3", "d4", "e3", "e4", "f3", "f4", "g3", "g4", "h3", "h4",
+        ];
+        assert_eq!(sans[..4], ["Na3", "Nc3", "Nf3", "Nh3"]);
+        assert_eq!(sans[4..], pawn_moves);
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(
+            board.legal_sans_sorted().unwrap()[..6],
+            ["O-O-O", "Kd1", "Kd2", "Ke2", "Kf1", "Kf2"]
+        );
+    }
+
+    #[test]
+    fn castle_gives_check() {
+        let board = PieceListBoard::from_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let castle = board.ambiguous_move(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap();
+        assert_eq!(castle.as_pgn_str(), "O-O+");
+        assert_eq!(
+            board.disambiguate_move_pgn("O-O+").unwrap(),
+            SimpleMove::from_pgn_str("e1g1").unwrap()
+        );
+    }
+
+    #[test]
+    fn after_load_warnings() {
+        let board: PieceListBoard = BoardBuilder::default()
+            .piece(square("e8"), SimplePiece::new(PieceKind::King, PieceColour::Black))
+            .piece(square("e4"), SimplePiece::new(PieceKind::Pawn, PieceColour::White))
+            .piece(square("e1"), SimplePiece::new(PieceKind::King, PieceColour::White))
+            .turn(PieceColour::Black)
+            .en_passant(Some(square("e3")))
+            .build();
+        assert_eq!(
+            board.after_load_warnings(),
+            ["en passant square e3 set but no pawn can capture there"]
+        );
+        let board = PieceListBoard::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        assert!(board.after_load_warnings().is_empty());
+        let board = PieceListBoard::from_fen("r2k4/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        assert_eq!(
+            board.after_load_warnings(),
+            ["Black O-O-O castling rights present but King missing from e8"]
+        );
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").unwrap();
+        assert_eq!(
+            board.after_load_warnings(),
+            ["White O-O castling rights present but Rook missing from h1"]
+        );
+        assert!(PieceListBoard::starting_board().after_load_warnings().is_empty());
+    }
+
+    #[test]
+    fn fast_checkmate() {
+        for (fen, checkmate) in [
+            // Back rank mate, and the same with an escape square
+            ("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", true),
+            ("R5k1/5pp1/8/8/8/8/8/6K1 b - - 0 1", false),
+            // Smothered mate
+            ("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1", true),
+            // Checking rook can be blocked or taken
+            ("R5k1/5ppp/8/8/1b6/8/8/6K1 b - - 0 1", false),
+            ("R5k1/5ppp/1n6/8/8/8/8/6K1 b - - 0 1", false),
+            // Double check can only be escaped by moving the king
+            ("4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1", false),
+            ("3rkr2/3p1p2/5N2/8/8/8/8/4RK2 b - - 0 1", true),
+            // Checking pawn can only be taken en passant
+            ("8/3B4/pp6/k7/1Pp5/3N4/2B5/7K b - b3 0 1", false),
+            ("8/3B4/pp6/k7/1Pp5/3N4/2B5/7K b - - 0 1", true),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", false),
+        ] {
+            let board = PieceListBoard::from_fen(fen).unwrap();
+            assert_eq!(board.is_checkmate().unwrap(), checkmate, "{fen}");
+            assert_eq!(board.state().unwrap() == BoardState::Checkmate, checkmate, "{fen}");
+        }
+    }
+
+    proptest! {
+        // Random games are slow to play out, so fewer are needed to hit plenty of checks
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn fast_checkmate_agrees(choices in prop::collection::vec(any::<usize>(), 0..100)) {
+            let mut board = PieceListBoard::starting_board();
+            for choice in choices {
+                if board.king_in_check(board.turn).unwrap() {
+                    prop_assert_eq!(board.is_checkmate().unwrap(), !board.has_legal_move().unwrap());
+                }
+                let moves: Vec<SimpleMove> = board.all_legal_moves().unwrap().into_iter().collect();
+                if moves.is_empty() {
+                    break;
+                }
+                board.move_piece(moves[choice % moves.len()]).unwrap();
+            }
+        }
+
+        #[test]
+        fn bitboards_round_trip(choices in prop::collection::vec(any::<usize>(), 0..40)) {
+            let mut board = PieceListBoard::starting_board();
+            for choice in choices {
+                let moves: Vec<SimpleMove> = board.all_legal_moves().unwrap().into_iter().collect();
+                if moves.is_empty() {
+                    break;
+                }
+                board.move_piece(moves[choice % moves.len()]).unwrap();
+            }
+            let rebuilt = PieceListBoard::from_bitboards(&board.to_bitboards(), board.turn).unwrap();
+            prop_assert_eq!(rebuilt.to_bitboards(), board.to_bitboards());
+            prop_assert_eq!(rebuilt.to_grid(), board.to_grid());
+        }
+    }
+
+    #[test]
+    fn taking_unmoved_rook_removes_castling_right() {
+        let mut board = PieceListBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        board.move_piece(SimpleMove::from_pgn_str("a1a8").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [true, false, true, false]);
+        board.move_piece(SimpleMove::from_pgn_str("h8h1").unwrap()).unwrap();
+        assert_eq!(board.castling_rights, [false, false, false, false]);
     }
 
     #[test]
-    fn white_pawn_double_push() {
+    fn apply_to() {
         let board = PieceListBoard::starting_board();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("e2").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["e2e3", "e2e4"]);
-        assert_eq!(moves, exp_moves);
+        let after = board.apply_to(square("e2"), square("e4"), None).unwrap();
+        assert_eq!(
+            after.as_fen_str().unwrap(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert!(matches!(
+            board.apply_to(square("e2"), square("e5"), None),
+            Err(ChessError::IllegalMove(_))
+        ));
+        assert_eq!(board, PieceListBoard::starting_board());
     }
 
     #[test]
-    fn black_pawn_double_push() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("h7").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["h7h6", "h7h5"]);
-        assert_eq!(moves, exp_moves);
+    fn double_check_only_king_moves() {
+        // Rook and knight both check, and the bishop could take the knight if it were single check
+        let board = PieceListBoard::from_fen("1b2k3/8/3N4/8/8/8/8/4RK2 b - - 0 1").unwrap();
+        let moves: Vec<SimpleMove> = board.all_legal_moves().unwrap().into_iter().collect();
+        assert!(!moves.is_empty());
+        for chess_move in moves {
+            assert_eq!(board.get_piece(chess_move.src()).unwrap().kind, PieceKind::King);
+        }
     }
 
     #[test]
-    fn pawn_double_push_blocked() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/2P5/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("c7").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["c7c6"]);
-        assert_eq!(moves, exp_moves);
+    fn block_squares() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/4R3/8/8/5K2 b - - 0 1").unwrap();
+        assert_eq!(
+            board.block_squares().unwrap(),
+            [square("e7"), square("e6"), square("e5")].into_iter().collect()
+        );
+        let board = PieceListBoard::from_fen("4k3/8/8/4R3/8/8/8/5K2 b - - 0 1").unwrap();
+        assert_eq!(
+            board.block_squares().unwrap(),
+            [square("e7"), square("e6")].into_iter().collect()
+        );
+        for fen in [
+            "4k3/4R3/8/8/8/8/8/5K2 b - - 0 1",
+            "4k3/8/3N4/8/8/8/8/5K2 b - - 0 1",
+            "4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1",
+            "4k3/8/8/8/8/8/8/5K2 b - - 0 1",
+        ] {
+            let board = PieceListBoard::from_fen(fen).unwrap();
+            assert_eq!(board.block_squares().unwrap(), SquareSet::EMPTY, "{fen}");
+        }
     }
 
     #[test]
-    fn pawn_takes() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("e4").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["e4e5", "e4d5"]);
-        assert_eq!(moves, exp_moves);
+    fn hanging_pieces() {
+        // The rooks attack each other undefended, the pawns attack more valuable pieces and the
+        // knight is attacked by both the pawn and the queen
+        let board = PieceListBoard::from_fen("4k3/8/2p5/1B1N3q/r7/1P6/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.hanging_pieces(PieceColour::White).unwrap(),
+            [square("a1"), square("b5"), square("d5")]
+        );
+        assert_eq!(
+            board.hanging_pieces(PieceColour::Black).unwrap(),
+            [square("a4"), square("c6")]
+        );
+        // Defended knight attacked by a rook isn't hanging, undefended one is
+        let board = PieceListBoard::from_fen("3rk3/8/8/3N4/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.hanging_pieces(PieceColour::White).unwrap(), [square("d5")]);
+        let board = PieceListBoard::from_fen("3rk3/8/8/3N4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert!(board.hanging_pieces(PieceColour::White).unwrap().is_empty());
+        assert!(
+            PieceListBoard::starting_board()
+                .hanging_pieces(PieceColour::White)
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[test]
-    fn pawn_takes_en_passant_behind() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("e4").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["e4e5", "e4d5"]);
-        assert_eq!(moves, exp_moves);
+    fn attacks_on_edge_ranks() {
+        // Looking for pawn attacks on the king must not look off the board
+        let board = PieceListBoard::from_fen("K7/8/8/8/8/8/7P/4k3 w - - 0 1").unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
+        let board = PieceListBoard::from_fen("K7/8/8/8/8/8/7P/4k3 b - - 0 1").unwrap();
+        assert_eq!(board.state().unwrap(), BoardState::Normal);
     }
 
     #[test]
-    fn en_passant() {
-        let mut board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5P2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("g7g5").unwrap()).unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["f5f6", "f5g6"]);
-        assert_eq!(moves, exp_moves);
+    fn bitboards() {
+        let bitboards = PieceListBoard::starting_board().to_bitboards();
+        assert_eq!(bitboards[0], 0xFF00);
+        assert_eq!(bitboards[5], 1 << 4);
+        assert_eq!(bitboards[9], 0x8100_0000_0000_0000);
+        let mut overlapping = bitboards;
+        overlapping[1] |= 1;
+        assert!(matches!(
+            PieceListBoard::from_bitboards(&overlapping, PieceColour::White),
+            Err(ChessError::InvalidBoard(_))
+        ));
     }
 
     #[test]
-    fn knight_moves() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/ppppppp1/7p/8/6N1/8/PPPPPPPP/RNBQKB1R w KQkq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("g4").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["g4h6", "g4f6", "g4e3", "g4e5"]);
-        assert_eq!(moves, exp_moves);
+    fn is_legal_san() {
+        let board = PieceListBoard::starting_board();
+        for san in ["e4", "Nf3", "Nc3"] {
+            assert!(board.is_legal_san(san), "{san}");
+        }
+        for san in ["e5", "Ke2", "O-O", "Bc4", "", "e4 e5", "hello", "Zz9"] {
+            assert!(!board.is_legal_san(san), "{san}");
+        }
+        let board = PieceListBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(board.is_legal_san("exd6"));
+        assert!(!board.is_legal_san("exf6"));
     }
 
     #[test]
-    fn bishop_moves() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5b2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["f5g6", "f5e6", "f5e4", "f5d3", "f5c2", "f5g4", "f5h3"]);
-        assert_eq!(moves, exp_moves);
+    fn promotion_paths() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/P7/3p4/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.promotion_paths(PieceColour::White),
+            [(square("a3"), vec![square("a8"), square("b8")])]
+        );
+        assert_eq!(
+            board.promotion_paths(PieceColour::Black),
+            [(square("d2"), vec![square("c1"), square("d1"), square("e1")])]
+        );
     }
 
     #[test]
-    fn rook_moves() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/2R2P2/8/8/PPPPP1PP/RNBQKBNR w KQkq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("c5").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["c5b5", "c5a5", "c5d5", "c5e5", "c5c6", "c5c7", "c5c4", "c5c3"]);
-        assert_eq!(moves, exp_moves);
+    fn is_plausibly_legal() {
+        assert!(PieceListBoard::starting_board().is_plausibly_legal());
+        for (fen, plausible) in [
+            // Third bishop needs a pawn to have promoted
+            ("rnbqkbnr/pppppppp/8/8/8/2B5/PPPPPPPP/RNBQKBNR w KQkq - 0 1", false),
+            ("rnbqkbnr/pppppppp/8/8/8/2B5/PPPPPPP1/RNBQKBNR w KQkq - 0 1", true),
+            // Two bishops on light squares need a promotion even with only two bishops
+            ("4k3/8/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/PPPPPPPP/3BKB2 w - - 0 1", false),
+            // Too many pawns, and a pawn on the last rank
+            ("4k3/pppppppp/p7/8/8/8/8/4K3 w - - 0 1", false),
+            ("P3k3/8/8/8/8/8/8/4K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4K1Q1 b - - 0 1", true),
+        ] {
+            assert_eq!(
+                PieceListBoard::from_fen(fen).unwrap().is_plausibly_legal(),
+                plausible,
+                "{fen}"
+            );
+        }
     }
 
     #[test]
-    fn queen_moves() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/5Q2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("f5").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec![
-            "f5f6", "f5f7", "f5f4", "f5f3", "f5g5", "f5h5", "f5e5", "f5d5", "f5c5", "f5b5", "f5a5", "f5g6", "f5h7",
-            "f5g4", "f5h3", "f5e6", "f5d7", "f5e4", "f5d3",
-        ]);
-        assert_eq!(moves, exp_moves);
+    fn ply() {
+        assert_eq!(PieceListBoard::starting_board().ply(), 0);
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(board.ply(), 1);
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 30").unwrap();
+        assert_eq!(board.ply(), 58);
     }
 
     #[test]
-    fn king_moves() {
-        let board = PieceListBoard::from_fen("rnbqkbnr/pppp1ppp/4p3/4KP2/8/8/PPPP1PPP/RNBQ1BNR w kq - 0 2").unwrap();
-        let mut moves: Vec<SimpleMove> = board
-            .piece_plegal_moves(SimpleSquare::from_pgn_str("e5").unwrap())
-            .unwrap()
-            .into_iter()
-            .collect();
-        moves.sort();
-        let exp_moves = moves_from_strs(vec!["e5e6", "e5f6", "e5d6", "e5d5", "e5d4", "e5e4", "e5f4"]);
-        assert_eq!(moves, exp_moves);
+    fn legal_moves_coord_strings() {
+        let moves = PieceListBoard::starting_board().legal_moves_coord_strings().unwrap();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.contains(&String::from("e2e4")));
+        let board = PieceListBoard::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(
+            board
+                .legal_moves_coord_strings()
+                .unwrap()
+                .contains(&String::from("a7a8q"))
+        );
     }
 
     #[test]
-    fn king_in_check() {
-        let board = PieceListBoard::from_fen("k3r3/1P6/4K3/8/8/8/8/8 w - - 0 2").unwrap();
-        assert!(board.king_in_check(PieceColour::White).unwrap());
-        assert!(board.king_in_check(PieceColour::Black).unwrap());
-        assert_eq!(board.state().unwrap(), BoardState::Check);
+    fn apply_null() {
+        let mut board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        board.apply_null();
+        assert_eq!(
+            board.as_fen_str().unwrap(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+        board.disambiguate_move(AmbiguousMove::Null).unwrap_err();
     }
 
     #[test]
-    fn king_not_in_check() {
-        let board = PieceListBoard::from_fen("k3r3/8/1P6/3K4/8/8/8/8 w - - 0 2").unwrap();
-        assert!(!board.king_in_check(PieceColour::White).unwrap());
-        assert!(!board.king_in_check(PieceColour::Black).unwrap());
-        assert_eq!(board.state().unwrap(), BoardState::Normal);
+    fn in_check() {
+        assert!(!PieceListBoard::starting_board().in_check().unwrap());
+        let board = PieceListBoard::from_fen("rnbqkbnr/ppppp2p/5p2/6pQ/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 3").unwrap();
+        assert!(board.in_check().unwrap());
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        assert!(board.in_check().unwrap());
     }
 
     #[test]
-    fn pinned_piece() {
-        let board = PieceListBoard::from_fen("k3r3/8/4N3/8/4K3/8/8/8 w - - 0 2").unwrap();
+    fn stalemate_risk_moves() {
+        let board = PieceListBoard::from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        let moves: Vec<String> = board
+            .stalemate_risk_moves()
+            .unwrap()
+            .iter()
+            .map(AmbiguousMove::as_pgn_str)
+            .collect();
+        assert_eq!(moves, ["Qf4", "Qc7"]);
         assert!(
-            board
-                .piece_legal_moves(SimpleSquare::from_pgn_str("e6").unwrap())
+            PieceListBoard::starting_board()
+                .stalemate_risk_moves()
                 .unwrap()
-                .into_iter()
-                .next()
-                .is_none()
+                .is_empty()
         );
     }
 
     #[test]
-    fn illegal_castle() {
-        let board = PieceListBoard::from_fen("rn1qkbnr/ppp2ppp/3p4/1b2N3/4P3/8/PPPP1PPP/RNBQK2R w KQkq - 0 1").unwrap();
-        assert!(!board.is_move_plegal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
-        assert!(!board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+    fn impossible_en_passant() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        PieceListBoard::from_fen(fen).unwrap_err();
+        let board = PieceListBoard::from_fen_lenient(fen).unwrap();
+        assert_eq!(board.en_passant_moves().unwrap(), []);
+        assert_eq!(
+            board.as_fen_str().unwrap(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+        );
+        assert_eq!(
+            board.after_load_warnings(),
+            ["en passant square d6 cleared since no pawn could have just skipped it"]
+        );
+        PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1").unwrap_err();
+        // A pawn could have just skipped e6, but no white pawn stands beside it to take
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2";
+        PieceListBoard::from_fen(fen).unwrap_err();
+        let board = PieceListBoard::from_fen_lenient(fen).unwrap();
+        assert_eq!(
+            board.as_fen_str().unwrap(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+        );
+        assert_eq!(
+            board.after_load_warnings(),
+            ["en passant square e6 cleared since no pawn can capture there"]
+        );
     }
 
     #[test]
-    fn legal_castle() {
-        let board =
-            PieceListBoard::from_fen("rn1qkbnr/pppb1ppp/3p4/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1").unwrap();
-        assert!(board.is_move_plegal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
-        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1g1").unwrap()).unwrap());
+    fn path_to() {
+        let start = PieceListBoard::starting_board();
+        let mut target = start.clone();
+        for chess_move in ["g1f3", "g8f6"] {
+            target
+                .move_piece(SimpleMove::from_pgn_str(chess_move).unwrap())
+                .unwrap();
+        }
+        let path: Vec<String> = start
+            .path_to(&target, 3)
+            .unwrap()
+            .unwrap()
+            .iter()
+            .map(AmbiguousMove::as_pgn_str)
+            .collect();
+        assert_eq!(path, ["Nf3", "Nf6"]);
+        assert_eq!(start.path_to(&target, 1).unwrap(), None);
+        assert_eq!(start.path_to(&start, 0).unwrap(), Some(vec![]));
     }
 
     #[test]
-    fn queenside_castle_no_knight() {
-        let board =
-            PieceListBoard::from_fen("r1bqk2r/ppp1bppp/2np1n2/4p3/4P3/2NPB3/PPP1QPPP/R3KBNR w KQkq - 0 1").unwrap();
-        assert!(board.is_move_plegal(SimpleMove::from_pgn_str("e1c1").unwrap()).unwrap());
-        assert!(board.is_move_legal(SimpleMove::from_pgn_str("e1c1").unwrap()).unwrap());
+    fn castling_summary() {
+        assert_eq!(
+            PieceListBoard::starting_board().castling_summary(),
+            "White: K,Q  Black: K,Q"
+        );
+        let board = PieceListBoard::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        assert_eq!(board.castling_summary(), "White: K  Black: Q");
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.castling_summary(), "White: (none)  Black: (none)");
     }
 
     #[test]
-    fn castling_invalidation_king_move() {
-        let mut board =
-            PieceListBoard::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 1").unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("e1e2").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [false, false, true, true]);
-        board.move_piece(SimpleMove::from_pgn_str("e8e7").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [false, false, false, false]);
+    fn piece_square_score() {
+        struct Flat;
+        impl Evaluator for Flat {
+            fn midgame_table(&self, _: PieceKind) -> &[i32; 64] {
+                &[1; 64]
+            }
+        }
+
+        let start = PieceListBoard::starting_board();
+        assert_eq!(start.game_phase(), MAX_GAME_PHASE);
+        assert_eq!(
+            start.piece_square_score(PieceColour::White),
+            start.piece_square_score(PieceColour::Black)
+        );
+        let centre = PieceListBoard::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let rim = PieceListBoard::from_fen("4k3/8/8/8/N7/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(centre.game_phase(), 1);
+        assert!(centre.piece_square_score(PieceColour::White) > rim.piece_square_score(PieceColour::White));
+        assert_eq!(start.piece_square_score_with(PieceColour::White, &Flat), 16);
     }
 
     #[test]
-    fn castling_invalidation_queenside_rook_move() {
-        let mut board =
-            PieceListBoard::from_fen("r1bqkbnr/pppppppp/2n5/8/8/2N5/PPPPPPPP/R1BQKBNR w KQkq - 0 1").unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("a1b1").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [true, false, true, true]);
-        board.move_piece(SimpleMove::from_pgn_str("a8b8").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [true, false, true, false]);
+    fn captures_of() {
+        let sans = |board: &PieceListBoard, square: &str| -> Vec<String> {
+            let mut sans: Vec<String> = board
+                .captures_of(SimpleSquare::from_pgn_str(square).unwrap())
+                .unwrap()
+                .iter()
+                .map(AmbiguousMove::as_pgn_str)
+                .collect();
+            sans.sort();
+            sans
+        };
+        let board = PieceListBoard::from_fen("4k3/8/8/3q4/4P3/2N5/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(sans(&board, "d5"), ["Nxd5", "Rxd5", "exd5"]);
+        assert!(sans(&board, "e4").is_empty());
+        assert!(sans(&board, "a1").is_empty());
+        let board = PieceListBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(sans(&board, "d5"), ["exd6"]);
     }
 
     #[test]
-    fn castling_invalidation_kingside_rook_move() {
-        let mut board =
-            PieceListBoard::from_fen("rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("h1g1").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [false, true, true, true]);
-        board.move_piece(SimpleMove::from_pgn_str("h8g8").unwrap()).unwrap();
-        assert_eq!(board.castling_rights, [false, true, false, true]);
+    fn from_placement() {
+        let board = PieceListBoard::from_placement("4k3/8/8/8/8/8/8/R3K2R").unwrap();
+        assert_eq!(board.as_fen_str().unwrap(), "4k3/8/8/8/8/8/8/R3K2R w - - 0 1");
+        PieceListBoard::from_placement("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap_err();
+        PieceListBoard::from_placement("4k3/8/8").unwrap_err();
     }
 
     #[test]
-    fn fifty_move_draw() {
-        let mut board = PieceListBoard::starting_board();
-        for _ in 0..12 {
-            board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f6g8").unwrap()).unwrap();
-        }
-        board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
-        assert_eq!(board.state().unwrap(), BoardState::Stalemate);
+    fn underpromotion_saves() {
+        let board = PieceListBoard::from_fen("8/6P1/8/8/8/8/2K5/k7 w - - 0 1").unwrap();
+        let mut saves: Vec<String> = board
+            .underpromotion_saves()
+            .unwrap()
+            .iter()
+            .map(AmbiguousMove::as_pgn_str)
+            .collect();
+        saves.sort();
+        assert_eq!(saves, ["g8=N", "g8=R"]);
+        let board = PieceListBoard::from_fen("8/6P1/8/8/8/8/8/k3K3 w - - 0 1").unwrap();
+        assert!(board.underpromotion_saves().unwrap().is_empty());
     }
 
     #[test]
-    fn fifty_move_not_draw() {
-        let mut board = PieceListBoard::starting_board();
-        for _ in 0..12 {
-            board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f6g8").unwrap()).unwrap();
-        }
-        board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("e7e5").unwrap()).unwrap();
-        board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
-        assert_eq!(board.state().unwrap(), BoardState::Normal);
+    fn check_giving_squares() {
+        let squares = |board: &PieceListBoard, kind| -> Vec<String> {
+            board
+                .check_giving_squares(kind)
+                .unwrap()
+                .iter()
+                .map(|square| square.as_str())
+                .collect()
+        };
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(
+            squares(&board, PieceKind::Rook),
+            [
+                "e1", "e2", "e3", "e4", "e5", "e6", "e7", "a8", "b8", "c8", "d8", "f8", "g8", "h8"
+            ]
+        );
+        let board = PieceListBoard::from_fen("4k3/8/4p3/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(
+            squares(&board, PieceKind::Rook),
+            ["e7", "a8", "b8", "c8", "d8", "f8", "g8", "h8"]
+        );
+        assert_eq!(squares(&board, PieceKind::Pawn), ["d7", "f7"]);
+        assert_eq!(squares(&board, PieceKind::Knight), ["d6", "f6", "c7", "g7"]);
     }
 
     #[test]
-    fn threefold_repetition() {
-        let mut board = PieceListBoard::starting_board();
-        for _ in 0..3 {
-            board.move_piece(SimpleMove::from_pgn_str("g1f3").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("g8f6").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f3g1").unwrap()).unwrap();
-            board.move_piece(SimpleMove::from_pgn_str("f6g8").unwrap()).unwrap();
+    fn ambiguous_move_actions() {
+        // Any quiet move reaches the fifty move rule, which mustn't hide the check from Rh8
+        let board = PieceListBoard::from_fen("k7/4P3/8/3pP3/8/8/8/4K2R w K d6 99 60").unwrap();
+        for (chess_move, san) in [
+            ("e5d6", "exd6"),
+            ("e7e8q", "e8=Q+"),
+            ("e7e8n", "e8=N"),
+            ("h1h8", "Rh8+"),
+        ] {
+            let chess_move = SimpleMove::from_uci_str(chess_move).unwrap();
+            assert_eq!(board.ambiguous_move(chess_move).unwrap().as_pgn_str(), san);
+            // The check marker on a move into the draw must still read back
+            assert_eq!(board.disambiguate_move_pgn(san).unwrap(), chess_move);
         }
-        assert_eq!(board.state().unwrap(), BoardState::Stalemate);
+        assert!(matches!(
+            board.ambiguous_move(SimpleMove::from_pgn_str("e1e3").unwrap()),
+            Err(ChessError::IllegalMove(_))
+        ));
+        assert!(matches!(
+            board.ambiguous_move(SimpleMove::from_pgn_str("a1a2").unwrap()),
+            Err(ChessError::PieceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn disambiguate_check_into_draw() {
+        disambiguates_check_into_draw::<PieceListBoard>();
     }
 }
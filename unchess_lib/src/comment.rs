@@ -0,0 +1,198 @@
+//! Commands embedded in PGN comments
+//!
+//! Tools such as Lichess and ChessBase store structured data in comments as `[%name args]`, like
+//! clock times (`[%clk 0:03:00]`), engine evaluations (`[%eval -0.35]`) and search depths
+//! (`[%depth 20]`), arrows (`[%cal Ge2e4]`) and highlighted squares (`[%csl Rd4]`). These are
+//! parsed into [`CommentCommand`]s, and any other commands, or known commands with malformed
+//! arguments, are kept raw so new extensions never stop a game from being read.
+
+use std::time::Duration;
+
+use nom::{Parser as _, character::complete::u32, combinator::all_consuming};
+
+use crate::{parser::pgn, simple_types::SimpleSquare};
+
+/// Colour of an arrow or highlighted square
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)] // Colours self explanatory
+pub enum MarkColour {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+/// Arrow drawn from one square to another
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Arrow {
+    /// Colour of the arrow
+    pub colour: MarkColour,
+    /// Square the arrow starts on
+    pub from: SimpleSquare,
+    /// Square the arrow points to
+    pub to: SimpleSquare,
+}
+
+/// Highlighted square
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    /// Colour of the highlight
+    pub colour: MarkColour,
+    /// Highlighted square
+    pub square: SimpleSquare,
+}
+
+/// Engine evaluation of a position from white's point of view
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Evaluation {
+    /// Advantage in hundredths of a pawn
+    Centipawns(i32),
+    /// Forced mate in this many moves, negative if black is mating
+    Mate(i32),
+}
+
+/// Comment command with a known meaning
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CommentCommand {
+    /// Time left on the clock of the side that just moved, `%clk`
+    Clock(Duration),
+    /// Engine evaluation after the move, `%eval`
+    Eval(Evaluation),
+    /// Depth in plies the engine searched to for its evaluation, `%depth`
+    Depth(u32),
+    /// Arrows drawn on the board, `%cal`
+    Arrows(Vec<Arrow>),
+    /// Highlighted squares, `%csl`
+    Highlights(Vec<Highlight>),
+}
+
+/// All commands found in a comment
+///
+/// Every command in the comment is collected, not just the first, and each list keeps the order the
+/// commands appear in the comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommentCommands {
+    /// Recognised commands in source order
+    pub known: Vec<CommentCommand>,
+    /// Unrecognised or malformed commands as `(name, args)` in source order, e.g.
+    /// `("c_effect", "e4;square;e4")`
+    pub unknown: Vec<(String, String)>,
+}
+
+impl CommentCommands {
+    /// Collect the commands in a comment's text
+    pub fn from_comment(comment: &str) -> Self {
+        let mut commands = Self::default();
+        for (name, args) in pgn::comment_commands(comment) {
+            match known_command(name, args) {
+                Some(command) => commands.known.push(command),
+                None => commands.unknown.push((name.to_owned(), args.to_owned())),
+            }
+        }
+        commands
+    }
+}
+
+fn known_command(name: &str, args: &str) -> Option<CommentCommand> {
+    let command = match name {
+        "clk" => all_consuming(pgn::clock).map(CommentCommand::Clock).parse(args),
+        "eval" => all_consuming(pgn::evaluation).map(CommentCommand::Eval).parse(args),
+        "depth" => all_consuming(u32).map(CommentCommand::Depth).parse(args),
+        "cal" => all_consuming(pgn::arrows).map(CommentCommand::Arrows).parse(args),
+        "csl" => all_consuming(pgn::highlights)
+            .map(CommentCommand::Highlights)
+            .parse(args),
+        _ => return None,
+    };
+    command.ok().map(|(_, command)| command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(s: &str) -> SimpleSquare {
+        SimpleSquare::from_pgn_str(s).unwrap()
+    }
+
+    #[test]
+    fn known_commands() {
+        let commands = CommentCommands::from_comment(
+            "Good move [%clk 1:02:03.5] [%eval #-3] [%cal Ge2e4,Rd1d8] [%csl Yd4] [%eval -0.35,22]",
+        );
+        assert_eq!(
+            commands.known,
+            [
+                CommentCommand::Clock(Duration::from_millis(3_723_500)),
+                CommentCommand::Eval(Evaluation::Mate(-3)),
+                CommentCommand::Arrows(vec![
+                    Arrow {
+                        colour: MarkColour::Green,
+                        from: square("e2"),
+                        to: square("e4"),
+                    },
+                    Arrow {
+                        colour: MarkColour::Red,
+                        from: square("d1"),
+                        to: square("d8"),
+                    },
+                ]),
+                CommentCommand::Highlights(vec![Highlight {
+                    colour: MarkColour::Yellow,
+                    square: square("d4"),
+                }]),
+                CommentCommand::Eval(Evaluation::Centipawns(-35)),
+            ]
+        );
+        assert!(commands.unknown.is_empty());
+    }
+
+    #[test]
+    fn eval_and_clock_in_order() {
+        let commands = CommentCommands::from_comment("[%eval 0.3] [%clk 0:05:00]");
+        assert_eq!(
+            commands.known,
+            [
+                CommentCommand::Eval(Evaluation::Centipawns(30)),
+                CommentCommand::Clock(Duration::from_secs(300)),
+            ]
+        );
+        let reversed = CommentCommands::from_comment("[%clk 0:05:00][%eval 0.3]");
+        assert_eq!(reversed.known, commands.known.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unknown_commands_kept_raw() {
+        let commands = CommentCommands::from_comment("[%foo bar] [%c_effect e4;square;e4;type;Brilliant] [%clk soon]");
+        assert!(commands.known.is_empty());
+        assert_eq!(
+            commands.unknown,
+            [
+                ("foo".to_owned(), "bar".to_owned()),
+                ("c_effect".to_owned(), "e4;square;e4;type;Brilliant".to_owned()),
+                ("clk".to_owned(), "soon".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_command_ignored() {
+        assert_eq!(CommentCommands::from_comment("[%eval 0.5"), CommentCommands::default());
+    }
+
+    #[test]
+    fn eval_and_depth() {
+        let commands = CommentCommands::from_comment("[%eval 0.5] [%depth 20]");
+        assert_eq!(
+            commands.known,
+            [
+                CommentCommand::Eval(Evaluation::Centipawns(50)),
+                CommentCommand::Depth(20),
+            ]
+        );
+        assert_eq!(
+            CommentCommands::from_comment("[%depth -1]").unknown,
+            [("depth".to_owned(), "-1".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Text diagrams of boards
+//!
+//! Draws boards as 8 lines of squares with rank and file coordinates, for printing to a terminal.
+//! Pieces are written as FEN letters by default or as Unicode chess glyphs, and squares such as
+//! the last move can be highlighted. The `Display` implementations of the boards use the default
+//! [`DiagramOptions`].
+
+use crate::{
+    simple_types::{SimpleMove, SimpleSquare},
+    square_set::SquareSet,
+    svg,
+    traits::{ChessBoard, ChessMove as _, ChessPiece as _},
+};
+
+/// Options for drawing text diagrams
+///
+/// By default pieces are written as FEN letters with `.` for empty squares, white is at the
+/// bottom, coordinates are shown and nothing is highlighted.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DiagramOptions {
+    unicode: bool,
+    flipped: bool,
+    hide_coordinates: bool,
+    highlighted: SquareSet,
+}
+
+impl DiagramOptions {
+    /// Write pieces as Unicode chess glyphs such as `♔` and empty squares as `·`
+    pub fn unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Draw the board from black's side
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Set whether rank numbers and file letters are drawn along the edges
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.hide_coordinates = !coordinates;
+        self
+    }
+
+    /// Highlight `squares` as well as any squares already highlighted
+    pub fn highlight(mut self, squares: SquareSet) -> Self {
+        self.highlighted = self.highlighted | squares;
+        self
+    }
+
+    /// Highlight the source and destination squares of `chess_move`
+    pub fn last_move(mut self, chess_move: SimpleMove) -> Self {
+        self.highlighted.insert(chess_move.src());
+        self.highlighted.insert(chess_move.dest());
+        self
+    }
+}
+
+/// Draw `board` as a text diagram, one line per rank without a trailing newline
+///
+/// Squares are separated by spaces, except when squares are highlighted, when every square is
+/// wrapped in either spaces or brackets, as in `[e4]`, so the columns still line up.
+pub fn to_diagram<B: ChessBoard<Square = SimpleSquare>>(board: &B, options: &DiagramOptions) -> String {
+    let order = |flipped: bool| -> Vec<u8> {
+        if flipped {
+            (0..8).collect()
+        } else {
+            (0..8).rev().collect()
+        }
+    };
+    let (ranks, files) = (order(options.flipped), order(!options.flipped));
+    let bracketed = !options.highlighted.is_empty();
+    let mut lines = vec![];
+    for &rank in &ranks {
+        let mut line = String::new();
+        if !options.hide_coordinates {
+            line.push(char::from(b'1' + rank));
+        }
+        for &file in &files {
+            let square = SimpleSquare::new(file, rank);
+            let symbol = match board.get_piece(square) {
+                Ok(piece) if options.unicode => svg::glyph(piece.colour(), piece.kind()),
+                Ok(piece) => piece.as_fen(),
+                Err(_) if options.unicode => '·',
+                Err(_) => '.',
+            };
+            match (bracketed, options.highlighted.contains(square)) {
+                (true, true) => line.extend(['[', symbol, ']']),
+                (true, false) => line.extend([' ', symbol, ' ']),
+                (false, _) => line.extend([' ', symbol]),
+            }
+        }
+        if options.hide_coordinates && !bracketed {
+            line.remove(0);
+        }
+        lines.push(line);
+    }
+    if !options.hide_coordinates {
+        let mut line = String::from(" ");
+        for &file in &files {
+            let letter = char::from(b'a' + file);
+            if bracketed {
+                line.extend([' ', letter, ' ']);
+            } else {
+                line.extend([' ', letter]);
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{bitboard::BitBoard, piece_list::PieceListBoard};
+
+    #[test]
+    fn starting_board_diagram() {
+        let expected = "\
+8 r n b q k b n r
+7 p p p p p p p p
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . . . . .
+3 . . . . . . . .
+2 P P P P P P P P
+1 R N B Q K B N R
+  a b c d e f g h";
+        assert_eq!(PieceListBoard::starting_board().to_string(), expected);
+        assert_eq!(BitBoard::starting_board().to_string(), expected);
+    }
+
+    #[test]
+    fn diagram_options() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        let options = DiagramOptions::default().unicode(true).flipped(true).coordinates(false);
+        let diagram = to_diagram(&board, &options);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "· · · ♔ · · · ·");
+        assert_eq!(lines[3], "· · · ♙ · · · ·");
+        assert_eq!(lines[7], "· · · ♚ · · · ·");
+
+        let options = DiagramOptions::default().last_move(SimpleMove::from_uci_str("e2e4").unwrap());
+        let diagram = to_diagram(&board, &options);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines[4], "4 .  .  .  . [P] .  .  . ");
+        assert_eq!(lines[6], "2 .  .  .  . [.] .  .  . ");
+        assert_eq!(lines[8], "  a  b  c  d  e  f  g  h ");
+    }
+}
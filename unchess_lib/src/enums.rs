@@ -5,12 +5,20 @@ use std::ops::Not;
 
 #[cfg(test)]
 use proptest::prelude::Strategy;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
 
-use crate::{error::ChessError, notation, parser, simple_types::SimpleSquare, traits::ChessSquare as _};
+use crate::{
+    error::ChessError,
+    notation, parser,
+    simple_types::SimpleSquare,
+    traits::{ChessMove as _, ChessSquare as _, LegalMoveGenerator},
+};
 
 /// Colour of piece
 #[allow(missing_docs)] // Enum variants self explanatory
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PieceColour {
     Black,
     White,
@@ -28,6 +36,14 @@ impl Not for PieceColour {
 }
 
 impl PieceColour {
+    /// Rank `self`'s pawns promote on
+    pub fn promotion_rank(self) -> u8 {
+        match self {
+            PieceColour::Black => 0,
+            PieceColour::White => 7,
+        }
+    }
+
     /// Strategy for generating either colour
     #[cfg(test)]
     pub fn strategy() -> impl Strategy<Value = Self> {
@@ -38,8 +54,12 @@ impl PieceColour {
 }
 
 /// Type of piece
+///
+/// The derived [`Ord`] follows declaration order (King < Queen < Bishop < Knight < Rook < Pawn),
+/// not material value, use [`PieceKind::value_ord_key`] to sort by value.
 #[allow(missing_docs)] // Enum variants self explanatory
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PieceKind {
     King,
     Queen,
@@ -78,9 +98,25 @@ impl TryFrom<char> for PieceKind {
     }
 }
 
-#[cfg(test)]
 impl PieceKind {
+    /// Key for sorting by material value, from pawn lowest to king highest
+    ///
+    /// Follows [`crate::traits::ChessPiece::value`] but ranks the king above the queen and the
+    /// bishop above the knight, so every kind has a distinct key, e.g.
+    /// `kinds.sort_by_key(|kind| kind.value_ord_key())`.
+    pub fn value_ord_key(self) -> u8 {
+        match self {
+            PieceKind::Pawn => 0,
+            PieceKind::Knight => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Rook => 3,
+            PieceKind::Queen => 4,
+            PieceKind::King => 5,
+        }
+    }
+
     /// Strategy for all pieces
+    #[cfg(test)]
     pub fn strategy() -> impl Strategy<Value = Self> {
         use proptest::{prelude::Just, prop_oneof};
 
@@ -95,6 +131,7 @@ impl PieceKind {
     }
 
     /// Strategy for promotable pieces
+    #[cfg(test)]
     pub fn promotable_stategy() -> impl Strategy<Value = Self> {
         use proptest::{prelude::Just, prop_oneof};
 
@@ -109,6 +146,7 @@ impl PieceKind {
 
 /// Basic states of board based on king safety
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BoardState {
     /// Normal play in game, no restrictions on moves
     Normal,
@@ -118,6 +156,25 @@ pub enum BoardState {
     Stalemate,
     /// Game is over in a checkmate, king has no legal moves and is checked
     Checkmate,
+    /// Game is over in a draw, no moves may be played
+    Draw(DrawReason),
+}
+
+/// Reason a game is drawn other than stalemate
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DrawReason {
+    /// The same position has occurred three times
+    ThreefoldRepetition,
+    /// The same position has occurred five times, drawn even if neither player claims it
+    FivefoldRepetition,
+    /// Fifty moves by each side without a capture or pawn move
+    FiftyMoves,
+    /// Seventy-five moves by each side without a capture or pawn move, drawn even if neither player
+    /// claims it
+    SeventyFiveMoves,
+    /// Neither side can checkmate by any series of legal moves
+    InsufficientMaterial,
 }
 
 impl From<MoveAction> for BoardState {
@@ -129,9 +186,73 @@ impl From<MoveAction> for BoardState {
     }
 }
 
+/// Result of a game, as in the PGN termination marker
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GameResult {
+    /// White won, `1-0`
+    WhiteWin,
+    /// Black won, `0-1`
+    BlackWin,
+    /// Game drawn, `1/2-1/2`
+    Draw,
+    /// Game still in progress or result unknown, `*`
+    Ongoing,
+}
+
+impl GameResult {
+    /// Result where `colour` won
+    pub fn win_for(colour: PieceColour) -> Self {
+        match colour {
+            PieceColour::White => Self::WhiteWin,
+            PieceColour::Black => Self::BlackWin,
+        }
+    }
+
+    /// Convert to PGN termination marker
+    pub fn as_pgn_str(&self) -> &'static str {
+        match self {
+            Self::WhiteWin => "1-0",
+            Self::BlackWin => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Ongoing => "*",
+        }
+    }
+}
+
+impl TryFrom<&str> for GameResult {
+    type Error = ChessError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "1-0" => Ok(Self::WhiteWin),
+            "0-1" => Ok(Self::BlackWin),
+            "1/2-1/2" => Ok(Self::Draw),
+            "*" => Ok(Self::Ongoing),
+            _ => Err(ChessError::InvalidPGN(value.to_string())),
+        }
+    }
+}
+
+/// Serialized as the PGN termination marker, e.g. `"1/2-1/2"`
+#[cfg(feature = "serde")]
+impl Serialize for GameResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_pgn_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GameResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::try_from(s.as_str()).map_err(D::Error::custom)
+    }
+}
+
 /// Action caused by move
 #[allow(missing_docs)] // Enum variants self explanatory
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MoveAction {
     Check,
     Checkmate,
@@ -168,9 +289,22 @@ impl MoveAction {
     }
 }
 
+/// Rules the board is played under
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Variant {
+    /// Standard chess
+    #[default]
+    Standard,
+    /// Chess960, with the back rank pieces shuffled and castling written as the king taking its
+    /// own rook, as in UCI
+    Chess960,
+}
+
 /// Side to castle on
 #[allow(missing_docs)] // Enum variants self explanatory
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CastlingSide {
     KingSide,
     QueenSide,
@@ -185,6 +319,48 @@ impl CastlingSide {
         }
     }
 
+    /// Squares involved in castling on this side for `colour`
+    ///
+    /// Returns `(king_from, king_to, rook_from, rook_to)` for standard chess.
+    pub fn castle_squares(&self, colour: PieceColour) -> (SimpleSquare, SimpleSquare, SimpleSquare, SimpleSquare) {
+        self.castle_squares_from(colour, 4, self.standard_rook_file())
+    }
+
+    /// Squares involved in castling on this side for `colour` with the king starting on
+    /// `king_file` and the rook on `rook_file`
+    ///
+    /// Returns `(king_from, king_to, rook_from, rook_to)`. The king and rook end on the same
+    /// squares as in standard chess wherever they start, as in Chess960.
+    pub fn castle_squares_from(
+        &self,
+        colour: PieceColour,
+        king_file: u8,
+        rook_file: u8,
+    ) -> (SimpleSquare, SimpleSquare, SimpleSquare, SimpleSquare) {
+        let back_rank = match colour {
+            PieceColour::Black => 7,
+            PieceColour::White => 0,
+        };
+        let (king_to, rook_to) = match self {
+            CastlingSide::KingSide => (6, 5),
+            CastlingSide::QueenSide => (2, 3),
+        };
+        (
+            SimpleSquare::new(king_file, back_rank),
+            SimpleSquare::new(king_to, back_rank),
+            SimpleSquare::new(rook_file, back_rank),
+            SimpleSquare::new(rook_to, back_rank),
+        )
+    }
+
+    /// File the rook castling on this side starts on in standard chess
+    pub fn standard_rook_file(&self) -> u8 {
+        match self {
+            CastlingSide::KingSide => 7,
+            CastlingSide::QueenSide => 0,
+        }
+    }
+
     /// Proptest test strategy
     #[cfg(test)]
     pub fn strategy() -> impl Strategy<Value = Self> {
@@ -218,14 +394,18 @@ pub enum AmbiguousMove {
     Castle {
         /// Side to castle on
         side: CastlingSide,
+        /// The action resulting from the move (check, checkmate)
+        action: Option<MoveAction>,
     },
+    /// Null move passing the turn, written `--` or `Z0` in engine analysis
+    Null,
 }
 
 impl AmbiguousMove {
     /// Convert to string according to pgn standard
     ///
     /// # Panics
-    /// Panics if src_file or src_range are out of range
+    /// Panics if src_file or src_rank are out of range
     pub fn as_pgn_str(&self) -> String {
         match self {
             AmbiguousMove::Normal {
@@ -260,8 +440,117 @@ impl AmbiguousMove {
                 }
                 s
             }
-            AmbiguousMove::Castle { side } => side.as_str().to_string(),
+            AmbiguousMove::Castle { side, action } => {
+                let mut s = side.as_str().to_string();
+                if let Some(a) = action {
+                    s.push(char::from(*a));
+                }
+                s
+            }
+            AmbiguousMove::Null => String::from("--"),
+        }
+    }
+
+    /// Compact JSON object of every field, for logs and snapshot tests
+    ///
+    /// Unlike the [`fmt::Debug`] output the format is stable between versions. Fields are always
+    /// written in the same order, with absent optional fields written as `null`, files and ranks as
+    /// their characters and pieces as their SAN letters.
+    ///
+    /// # Panics
+    /// Panics if src_file or src_rank are out of range
+    pub fn to_debug_json(&self) -> String {
+        let quoted = |value: &str| format!("\"{value}\"");
+        let quoted_or_null = |value: Option<String>| value.map_or_else(|| "null".to_string(), |value| quoted(&value));
+        let action_json = |action: &Option<MoveAction>| {
+            quoted_or_null(action.map(|action| match action {
+                MoveAction::Check => "check".to_string(),
+                MoveAction::Checkmate => "checkmate".to_string(),
+            }))
+        };
+        let fields = match self {
+            AmbiguousMove::Normal {
+                piece_kind,
+                src_file,
+                src_rank,
+                takes,
+                dest,
+                promote_to,
+                action,
+            } => vec![
+                ("type", quoted("normal")),
+                ("piece", quoted(&char::from(*piece_kind).to_string())),
+                (
+                    "src_file",
+                    quoted_or_null(src_file.map(|file| notation::file_to_char(file).unwrap().to_string())),
+                ),
+                (
+                    "src_rank",
+                    quoted_or_null(src_rank.map(|rank| notation::rank_to_char(rank).unwrap().to_string())),
+                ),
+                ("takes", takes.to_string()),
+                ("dest", quoted(&dest.as_str())),
+                (
+                    "promote_to",
+                    quoted_or_null(promote_to.map(|piece| char::from(piece).to_string())),
+                ),
+                ("action", action_json(action)),
+            ],
+            AmbiguousMove::Castle { side, action } => vec![
+                ("type", quoted("castle")),
+                (
+                    "side",
+                    quoted(match side {
+                        CastlingSide::KingSide => "kingside",
+                        CastlingSide::QueenSide => "queenside",
+                    }),
+                ),
+                ("action", action_json(action)),
+            ],
+            AmbiguousMove::Null => vec![("type", quoted("null"))],
+        };
+        let fields: Vec<String> = fields
+            .into_iter()
+            .map(|(key, value)| format!("\"{key}\":{value}"))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// Source and destination squares of the move played on `board`
+    ///
+    /// Castling gives the king's squares.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state
+    /// - [`crate::error::ChessError::ImpossibleMove`] if the move can't be played on `board`,
+    ///   including null moves
+    /// - [`crate::error::ChessError::AmbiguousMove`] if multiple legal moves match the move
+    pub fn to_coords(&self, board: &impl LegalMoveGenerator) -> Result<(SimpleSquare, SimpleSquare), ChessError> {
+        let chess_move = board.disambiguate_move(*self)?;
+        let (src, dest) = (chess_move.src(), chess_move.dest());
+        Ok((
+            SimpleSquare::new(src.file(), src.rank()),
+            SimpleSquare::new(dest.file(), dest.rank()),
+        ))
+    }
+
+    /// File and rank displacement of the piece moving when played on `board`, for animating it
+    ///
+    /// Castling gives the king's two square hop, and null moves don't move anything.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state
+    /// - [`crate::error::ChessError::ImpossibleMove`] if the move can't be played on `board`
+    /// - [`crate::error::ChessError::AmbiguousMove`] if multiple legal moves match the move
+    pub fn move_vector(&self, board: &impl LegalMoveGenerator) -> Result<(i8, i8), ChessError> {
+        if *self == AmbiguousMove::Null {
+            return Ok((0, 0));
         }
+        let (src, dest) = self.to_coords(board)?;
+        Ok((
+            dest.file() as i8 - src.file() as i8,
+            dest.rank() as i8 - src.rank() as i8,
+        ))
     }
 
     /// Strategy for creating pgn style moves. Not guaranteed to be possible.
@@ -282,7 +571,7 @@ impl AmbiguousMove {
         (castle, castling_side, piece_kind, src_file, src_rank, takes, dest, promote_to, action).prop_map(
             |(castle,castling_side, piece_kind, src_file, src_rank, takes, dest, promote_to, action,)| {
                 if castle {
-                    AmbiguousMove::Castle { side: castling_side }
+                    AmbiguousMove::Castle { side: castling_side, action }
                 } else {
                     AmbiguousMove::Normal {piece_kind, src_file, src_rank, takes, dest, promote_to, action }
                 }
@@ -308,3 +597,123 @@ impl TryFrom<&str> for AmbiguousMove {
         }
     }
 }
+
+/// Serialized in SAN, e.g. `"Nbxd2+"`
+#[cfg(feature = "serde")]
+impl Serialize for AmbiguousMove {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_pgn_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AmbiguousMove {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::try_from(s.as_str()).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(pgn: [&str; 4]) -> (SimpleSquare, SimpleSquare, SimpleSquare, SimpleSquare) {
+        let [king_from, king_to, rook_from, rook_to] = pgn.map(|s| SimpleSquare::from_pgn_str(s).unwrap());
+        (king_from, king_to, rook_from, rook_to)
+    }
+
+    #[test]
+    fn white_kingside_castle_squares() {
+        assert_eq!(
+            CastlingSide::KingSide.castle_squares(PieceColour::White),
+            squares(["e1", "g1", "h1", "f1"])
+        );
+    }
+
+    #[test]
+    fn white_queenside_castle_squares() {
+        assert_eq!(
+            CastlingSide::QueenSide.castle_squares(PieceColour::White),
+            squares(["e1", "c1", "a1", "d1"])
+        );
+    }
+
+    #[test]
+    fn black_kingside_castle_squares() {
+        assert_eq!(
+            CastlingSide::KingSide.castle_squares(PieceColour::Black),
+            squares(["e8", "g8", "h8", "f8"])
+        );
+    }
+
+    #[test]
+    fn black_queenside_castle_squares() {
+        assert_eq!(
+            CastlingSide::QueenSide.castle_squares(PieceColour::Black),
+            squares(["e8", "c8", "a8", "d8"])
+        );
+    }
+
+    #[test]
+    fn sort_by_value() {
+        let mut kinds = vec![
+            PieceKind::Rook,
+            PieceKind::King,
+            PieceKind::Pawn,
+            PieceKind::Queen,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+        ];
+        kinds.sort_by_key(|kind| kind.value_ord_key());
+        assert_eq!(
+            kinds,
+            [
+                PieceKind::Pawn,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+                PieceKind::King,
+            ]
+        );
+    }
+
+    #[test]
+    fn debug_json() {
+        let chess_move = AmbiguousMove::try_from("Nbxd2+").unwrap();
+        assert_eq!(
+            chess_move.to_debug_json(),
+            r#"{"type":"normal","piece":"N","src_file":"b","src_rank":null,"takes":true,"dest":"d2","promote_to":null,"action":"check"}"#
+        );
+        let chess_move = AmbiguousMove::try_from("e8=Q").unwrap();
+        assert_eq!(
+            chess_move.to_debug_json(),
+            r#"{"type":"normal","piece":"P","src_file":null,"src_rank":null,"takes":false,"dest":"e8","promote_to":"Q","action":null}"#
+        );
+        let chess_move = AmbiguousMove::try_from("O-O-O#").unwrap();
+        assert_eq!(
+            chess_move.to_debug_json(),
+            r#"{"type":"castle","side":"queenside","action":"checkmate"}"#
+        );
+    }
+
+    #[test]
+    fn move_vector() {
+        use crate::board::piece_list::PieceListBoard;
+        use crate::traits::ChessBoard as _;
+
+        let board = PieceListBoard::starting_board();
+        let knight = parser::pgn::chess_move("Nf3").unwrap().1;
+        assert_eq!(knight.move_vector(&board).unwrap(), (-1, 2));
+        assert_eq!(
+            knight.to_coords(&board).unwrap(),
+            (SimpleSquare::new(6, 0), SimpleSquare::new(5, 2))
+        );
+        let board = PieceListBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let castle = parser::pgn::chess_move("O-O").unwrap().1;
+        assert_eq!(castle.move_vector(&board).unwrap(), (2, 0));
+        assert_eq!(AmbiguousMove::Null.move_vector(&board).unwrap(), (0, 0));
+        AmbiguousMove::Null.to_coords(&board).unwrap_err();
+    }
+}
@@ -1,5 +1,5 @@
 //! Library wide error handling
-use std::fmt::Debug;
+use std::{fmt::Debug, io};
 use thiserror::Error;
 
 use crate::{
@@ -32,12 +32,51 @@ pub enum ChessError {
     #[error("Rank must be between 0-7 inclusive, {0} > 7")]
     InvalidRank(u8),
 
+    #[error("Square index must be between 0-63 inclusive, {0} > 63")]
+    OutOfBounds(u8),
+
+    #[error("Chess960 starting position number must be between 0-959 inclusive, {0} > 959")]
+    InvalidChess960Position(u16),
+
     #[error("{0:?} is not an actionable move")]
     NotAction(BoardState),
 
     #[error("Invalid PGN: {0}")]
     InvalidPGN(String),
 
+    #[error("Malformed tag pair: {0}")]
+    InvalidTag(String),
+
+    #[error("Unterminated comment: {0}")]
+    UnterminatedComment(String),
+
+    #[error("Move number doesn't match the side to move: {0}")]
+    UnexpectedMoveNumber(String),
+
     #[error("Invalid FEN: {0}")]
     InvalidFEN(String),
+
+    #[error("Invalid UCI move: {0}")]
+    InvalidUCI(String),
+
+    #[error("Ply {0} out of range")]
+    PlyOutOfRange(usize),
+
+    #[error("Variations nested deeper than {0}")]
+    VariationTooDeep(usize),
+
+    #[error("No move at path {0:?}")]
+    PathNotFound(Vec<usize>),
+
+    #[error("Expected 781 Polyglot random numbers, got {0}")]
+    InvalidPolyglotKeys(usize),
+
+    #[error("Polyglot book of {0} bytes ends partway through an entry")]
+    TruncatedBook(usize),
+
+    #[error("Failed to read input: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Engine exited while waiting for {0}")]
+    EngineExited(String),
 }
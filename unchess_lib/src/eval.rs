@@ -0,0 +1,156 @@
+//! Evaluation terms
+//!
+//! Piece square tables score each piece by the square it stands on. The tables used are chosen by
+//! an [`Evaluator`], so engines can tune them without reimplementing the scoring.
+
+use crate::enums::PieceKind;
+
+/// Game phase of a position with all the starting pieces, see
+/// [`crate::board::piece_list::PieceListBoard::game_phase`]
+pub const MAX_GAME_PHASE: i32 = 24;
+
+/// Source of the piece square tables used for evaluation
+///
+/// Tables are from white's point of view, indexed by [`crate::simple_types::SimpleSquare::to_index`],
+/// and mirrored for black. Scores are in centipawns.
+pub trait Evaluator {
+    /// Table for `kind` when most pieces are still on the board
+    fn midgame_table(&self, kind: PieceKind) -> &[i32; 64] {
+        match kind {
+            PieceKind::Pawn => &PAWN_TABLE,
+            PieceKind::Knight => &KNIGHT_TABLE,
+            PieceKind::Bishop => &BISHOP_TABLE,
+            PieceKind::Rook => &ROOK_TABLE,
+            PieceKind::Queen => &QUEEN_TABLE,
+            PieceKind::King => &KING_MIDGAME_TABLE,
+        }
+    }
+
+    /// Table for `kind` once the pieces have been traded off
+    ///
+    /// The same as [`Evaluator::midgame_table`] except for the king, which should head for the
+    /// centre in the endgame.
+    fn endgame_table(&self, kind: PieceKind) -> &[i32; 64] {
+        match kind {
+            PieceKind::King => &KING_ENDGAME_TABLE,
+            _ => self.midgame_table(kind),
+        }
+    }
+}
+
+/// Evaluator using the simplified evaluation function tables of Tomasz Michniewski
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DefaultEvaluator;
+
+impl Evaluator for DefaultEvaluator {}
+
+/// Reorder a table written as the board is printed, rank 8 first, to be indexed from a1
+const fn from_printed(printed: [i32; 64]) -> [i32; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        table[index] = printed[index ^ 56];
+        index += 1;
+    }
+    table
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = from_printed([
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+]);
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = from_printed([
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+]);
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = from_printed([
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+]);
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = from_printed([
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+]);
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = from_printed([
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+]);
+
+#[rustfmt::skip]
+const KING_MIDGAME_TABLE: [i32; 64] = from_printed([
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+]);
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = from_printed([
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_types::SimpleSquare;
+
+    #[test]
+    fn tables_indexed_from_a1() {
+        let table = DefaultEvaluator.midgame_table(PieceKind::Pawn);
+        assert_eq!(table[SimpleSquare::new(3, 1).to_index() as usize], -20);
+        assert_eq!(table[SimpleSquare::new(0, 6).to_index() as usize], 50);
+        let king = SimpleSquare::new(6, 0).to_index() as usize;
+        assert_eq!(DefaultEvaluator.midgame_table(PieceKind::King)[king], 30);
+        assert_eq!(DefaultEvaluator.endgame_table(PieceKind::King)[king], -30);
+    }
+}
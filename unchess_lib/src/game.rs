@@ -0,0 +1,1876 @@
+//! Recorded chess games
+//!
+//! A [`Game`] holds the tag pairs and annotated moves of a PGN game. Moves are stored as
+//! [`AmbiguousMove`]s exactly as they appear in the movetext, so a game can be read and written
+//! without needing a board, and is only replayed onto a [`PieceListBoard`] when positions are
+//! needed. A [`GameTree`] steps through a game and its variations a move at a time, keeping the
+//! boards along the way.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    io::{BufRead as _, BufReader, Read},
+    iter, mem,
+    time::Duration,
+};
+
+use nom::{Err, Parser as _, combinator::all_consuming, error::ErrorKind};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::{
+    board::piece_list::PieceListBoard,
+    comment::CommentCommands,
+    enums::{AmbiguousMove, GameResult, PieceColour, PieceKind, Variant},
+    error::ChessError,
+    notation, parser,
+    simple_types::SimpleSquare,
+    traits::{ChessBoard as _, ChessMove as _, ChessPiece as _, LegalMoveGenerator as _},
+};
+
+/// Options for parsing PGN games
+///
+/// By default variations may be nested up to 256 deep.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    max_variation_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_variation_depth: 256,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Set how deeply variations may be nested before parsing fails
+    ///
+    /// Guards against stack overflows from pathological or malicious PGNs.
+    pub fn max_variation_depth(mut self, max_variation_depth: usize) -> Self {
+        self.max_variation_depth = max_variation_depth;
+        self
+    }
+}
+
+/// Options for writing PGN games
+///
+/// By default tags are written in the order they were added and the movetext is a single line,
+/// as [`Game::as_pgn_str`] writes them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    roster_first: bool,
+    other_tags: bool,
+    line_width: Option<usize>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            roster_first: false,
+            other_tags: true,
+            line_width: None,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Set whether the seven tag roster is written first, in the standard order
+    ///
+    /// Missing roster tags are filled in as unknown, and the `Result` tag always matches the
+    /// termination marker.
+    pub fn roster_first(mut self, roster_first: bool) -> Self {
+        self.roster_first = roster_first;
+        self
+    }
+
+    /// Set whether tags outside the seven tag roster are written
+    pub fn other_tags(mut self, other_tags: bool) -> Self {
+        self.other_tags = other_tags;
+        self
+    }
+
+    /// Set the width to wrap movetext lines at, or `None` to write it all on one line
+    ///
+    /// Tokens are never split, so a token longer than `line_width` gets a line to itself.
+    pub fn line_width(mut self, line_width: Option<usize>) -> Self {
+        self.line_width = line_width;
+        self
+    }
+}
+
+/// Tags required in every PGN game, in the order they should be written
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Value of the `Round` tag
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Round {
+    /// Round not known, `?`
+    Unknown,
+    /// Game not played as part of a round, `-`
+    NotApplicable,
+    /// Numbered round, e.g. `3`
+    Number(u32),
+    /// Board within a numbered round, e.g. `3.2` is board 2 of round 3
+    Board(u32, u32),
+    /// Any other value, kept as written
+    Other(String),
+}
+
+impl Round {
+    /// Parse the value of a `Round` tag, keeping unrecognised values as [`Round::Other`]
+    pub fn from_tag_str(value: &str) -> Self {
+        let parsed = match value {
+            "?" => Some(Self::Unknown),
+            "-" => Some(Self::NotApplicable),
+            _ => match value.split_once('.') {
+                Some((round, board)) => round
+                    .parse()
+                    .ok()
+                    .zip(board.parse().ok())
+                    .map(|(r, b)| Self::Board(r, b)),
+                None => value.parse().ok().map(Self::Number),
+            },
+        };
+        parsed.unwrap_or_else(|| Self::Other(value.to_string()))
+    }
+
+    /// Convert back to the value of a `Round` tag
+    pub fn as_tag_str(&self) -> String {
+        match self {
+            Self::Unknown => "?".to_string(),
+            Self::NotApplicable => "-".to_string(),
+            Self::Number(round) => round.to_string(),
+            Self::Board(round, board) => format!("{round}.{board}"),
+            Self::Other(value) => value.clone(),
+        }
+    }
+}
+
+/// Value of the `Termination` tag, the reason the game ended
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TerminationReason {
+    /// Game ended by checkmate, resignation, agreement or another normal means
+    Normal,
+    /// A player ran out of time
+    TimeForfeit,
+    /// A player left the game
+    Abandoned,
+    /// A player was penalised for breaking the rules
+    RulesInfraction,
+    /// Result decided by a third party, such as an arbiter or engine
+    Adjudication,
+    /// Game not yet finished
+    Unterminated,
+    /// Any other value, kept as written
+    Other(String),
+}
+
+impl TerminationReason {
+    /// Parse the value of a `Termination` tag ignoring case, keeping unrecognised values as
+    /// [`TerminationReason::Other`]
+    pub fn from_tag_str(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "normal" => Self::Normal,
+            "time forfeit" => Self::TimeForfeit,
+            "abandoned" => Self::Abandoned,
+            "rules infraction" => Self::RulesInfraction,
+            "adjudication" => Self::Adjudication,
+            "unterminated" => Self::Unterminated,
+            _ => Self::Other(value.to_string()),
+        }
+    }
+
+    /// Convert back to the value of a `Termination` tag
+    pub fn as_tag_str(&self) -> String {
+        match self {
+            Self::Normal => "Normal".to_string(),
+            Self::TimeForfeit => "Time forfeit".to_string(),
+            Self::Abandoned => "Abandoned".to_string(),
+            Self::RulesInfraction => "Rules infraction".to_string(),
+            Self::Adjudication => "Adjudication".to_string(),
+            Self::Unterminated => "Unterminated".to_string(),
+            Self::Other(value) => value.clone(),
+        }
+    }
+}
+
+/// Move played in a game along with its annotations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameMove {
+    chess_move: AmbiguousMove,
+    comment: Option<String>,
+    nags: Vec<u8>,
+    variations: Vec<Vec<GameMove>>,
+}
+
+impl GameMove {
+    /// Unannotated game move
+    pub fn new(chess_move: AmbiguousMove) -> Self {
+        Self {
+            chess_move,
+            comment: None,
+            nags: vec![],
+            variations: vec![],
+        }
+    }
+
+    /// The move played
+    pub fn chess_move(&self) -> AmbiguousMove {
+        self.chess_move
+    }
+
+    /// Comment following the move
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Commands such as `[%clk 0:03:00]` embedded in the comment
+    pub fn commands(&self) -> CommentCommands {
+        self.comment
+            .as_deref()
+            .map(CommentCommands::from_comment)
+            .unwrap_or_default()
+    }
+
+    /// Numeric annotation glyphs attached to the move, in order
+    pub fn nags(&self) -> &[u8] {
+        &self.nags
+    }
+
+    /// Replace the comment following the move
+    pub fn set_comment(&mut self, comment: String) {
+        self.comment = Some(comment);
+    }
+
+    /// Add to the comment following the move, separated from any existing comment by a space
+    pub fn append_comment(&mut self, comment: &str) {
+        match &mut self.comment {
+            Some(existing) => {
+                existing.push(' ');
+                existing.push_str(comment);
+            }
+            None => self.comment = Some(comment.to_string()),
+        }
+    }
+
+    /// Attach numeric annotation glyph `nag` to the move
+    pub fn add_nag(&mut self, nag: u8) {
+        self.nags.push(nag);
+    }
+
+    /// Alternative lines played instead of this move
+    pub fn variations(&self) -> &[Vec<GameMove>] {
+        &self.variations
+    }
+
+    /// Add alternative line `variation`, starting with a move played instead of this one
+    pub fn add_variation(&mut self, variation: Vec<GameMove>) {
+        self.variations.push(variation);
+    }
+}
+
+/// Token of exported movetext, as yielded by [`Game::san_tokens`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanToken {
+    /// Move number indicator before a move, written `1.` before white's move and `1...` before
+    /// black's
+    MoveNumber {
+        /// Fullmove number
+        number: usize,
+        /// Side the following move is played by
+        colour: PieceColour,
+    },
+    /// Move in SAN
+    Move(String),
+    /// Numeric annotation glyph of the move before
+    Nag(u8),
+    /// Comment on the move before
+    Comment(String),
+    /// Start of a variation replacing the move before
+    VariationStart,
+    /// End of the innermost variation
+    VariationEnd,
+    /// Game termination marker
+    Termination(String),
+}
+
+impl SanToken {
+    /// Convert to string as written in PGN movetext
+    pub fn as_pgn_str(&self) -> String {
+        match self {
+            Self::MoveNumber {
+                number,
+                colour: PieceColour::White,
+            } => format!("{number}."),
+            Self::MoveNumber {
+                number,
+                colour: PieceColour::Black,
+            } => format!("{number}..."),
+            Self::Move(san) | Self::Termination(san) => san.clone(),
+            Self::Nag(nag) => format!("${nag}"),
+            Self::Comment(comment) => notation::comment_to_pgn(comment),
+            Self::VariationStart => "(".to_string(),
+            Self::VariationEnd => ")".to_string(),
+        }
+    }
+}
+
+/// Chess game
+///
+/// Plies are indexed from zero, so ply 0 is white's first move.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    moves: Vec<GameMove>,
+    result: Option<GameResult>,
+}
+
+impl Game {
+    /// Create game from PGN string
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidPGN`] if `pgn` is invalid or contains trailing text
+    ///   that can't be parsed as movetext
+    /// - [`crate::error::ChessError::VariationTooDeep`] if variations are nested more than 256 deep
+    /// - [`crate::error::ChessError::InvalidTag`] if a tag pair is malformed
+    /// - [`crate::error::ChessError::UnterminatedComment`] if a `{` comment is never closed
+    /// - [`crate::error::ChessError::UnexpectedMoveNumber`] if a move number doesn't match the
+    ///   move it is followed by
+    pub fn from_pgn_str(pgn: &str) -> Result<Self, ChessError> {
+        Self::from_pgn_str_with(pgn, ParseOptions::default())
+    }
+
+    /// Create game from PGN string using parsing `options`
+    ///
+    /// A leading byte order mark is skipped and CRLF line endings are read as LF, including in
+    /// comments and tag values.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidPGN`] if `pgn` is invalid or contains trailing text
+    ///   that can't be parsed as movetext
+    /// - [`crate::error::ChessError::VariationTooDeep`] if variations are nested deeper than
+    ///   allowed by `options`
+    /// - [`crate::error::ChessError::InvalidTag`] if a tag pair is malformed
+    /// - [`crate::error::ChessError::UnterminatedComment`] if a `{` comment is never closed
+    /// - [`crate::error::ChessError::UnexpectedMoveNumber`] if a move number doesn't match the
+    ///   move it is followed by
+    pub fn from_pgn_str_with(pgn: &str, options: ParseOptions) -> Result<Self, ChessError> {
+        let normalised = parser::pgn::normalise(pgn);
+        match parser::pgn::game(&normalised, options.max_variation_depth) {
+            Ok((rest, (tags, moves, result))) if rest.trim().is_empty() => Ok(Self {
+                tags: tags
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+                moves,
+                result,
+            }),
+            Err(Err::Failure(e)) => {
+                let first_line = e.input.lines().next().unwrap_or_default().to_string();
+                match e.code {
+                    ErrorKind::TooLarge => Err(ChessError::VariationTooDeep(options.max_variation_depth)),
+                    ErrorKind::Tag => Err(ChessError::InvalidTag(first_line)),
+                    ErrorKind::TakeUntil => Err(ChessError::UnterminatedComment(first_line)),
+                    ErrorKind::Verify => Err(ChessError::UnexpectedMoveNumber(
+                        e.input.split_whitespace().next().unwrap_or_default().to_string(),
+                    )),
+                    _ => Err(ChessError::InvalidPGN(pgn.to_string())),
+                }
+            }
+            _ => Err(ChessError::InvalidPGN(pgn.to_string())),
+        }
+    }
+
+    /// Result of the game
+    ///
+    /// Taken from the termination marker after the movetext, falling back to the `Result` tag and
+    /// then [`GameResult::Ongoing`] if neither is present or valid.
+    pub fn result(&self) -> GameResult {
+        self.result
+            .or_else(|| self.tag("Result").and_then(|value| GameResult::try_from(value).ok()))
+            .unwrap_or(GameResult::Ongoing)
+    }
+
+    /// Tag pairs in the order they appear
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Value of tag `key` if present
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Round the game was played in, from the `Round` tag
+    pub fn round(&self) -> Option<Round> {
+        self.tag("Round").map(Round::from_tag_str)
+    }
+
+    /// Rules the game was played under, from the `Variant` tag
+    ///
+    /// `Chess960` and its other names such as `Fischerandom` are recognised, anything else is
+    /// treated as standard chess.
+    pub fn variant(&self) -> Variant {
+        let name: String = self
+            .tag("Variant")
+            .unwrap_or_default()
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "chess960" | "960" | "fischerandom" | "fischerrandom" | "fischerrandomchess" => Variant::Chess960,
+            _ => Variant::Standard,
+        }
+    }
+
+    /// Reason the game ended, from the `Termination` tag
+    pub fn termination(&self) -> Option<TerminationReason> {
+        self.tag("Termination").map(TerminationReason::from_tag_str)
+    }
+
+    /// Clock time `colour` started with, from the `WhiteClock` or `BlackClock` tag
+    ///
+    /// `None` if the tag is missing. Times are written `h:mm:ss` with optional fractional seconds.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidPGN`] if the tag value isn't a valid clock time
+    pub fn clock_tag(&self, colour: PieceColour) -> Result<Option<Duration>, ChessError> {
+        let key = match colour {
+            PieceColour::White => "WhiteClock",
+            PieceColour::Black => "BlackClock",
+        };
+        self.tag(key)
+            .map(|value| {
+                all_consuming(parser::pgn::clock)
+                    .parse(value)
+                    .map(|(_, clock)| clock)
+                    .or(Err(ChessError::InvalidPGN(value.to_string())))
+            })
+            .transpose()
+    }
+
+    /// Set tag `key` to `value`, replacing the existing value if present
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        if let Some((_, existing)) = self.tags.iter_mut().find(|(k, _)| k == key) {
+            *existing = value.to_string();
+        } else {
+            self.tags.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    /// Moves of the game in order
+    pub fn moves(&self) -> &[GameMove] {
+        &self.moves
+    }
+
+    /// Call `f` on every move in the game, mainline and variations, depth first in PGN order
+    ///
+    /// Each move is visited before its variations. The path locates the move: a mainline move is
+    /// `[ply]`, and a move inside a variation extends the path of the move the variation replaces
+    /// with the variation's index and the move's index within it, so `[1, 0, 2]` is the third move
+    /// of the first variation on ply 1.
+    pub fn walk<F: FnMut(&[usize], &AmbiguousMove)>(&self, mut f: F) {
+        walk_moves(&self.moves, &mut vec![], &mut f);
+    }
+
+    /// Add move to the end of the game
+    pub fn push_move(&mut self, chess_move: AmbiguousMove) {
+        self.moves.push(GameMove::new(chess_move));
+    }
+
+    /// Replace the comment following the move at `ply`
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::PlyOutOfRange`] if there is no move at `ply`
+    pub fn set_comment(&mut self, ply: usize, comment: String) -> Result<(), ChessError> {
+        self.move_mut(ply)?.set_comment(comment);
+        Ok(())
+    }
+
+    /// Attach numeric annotation glyph `nag` to the move at `ply`
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::PlyOutOfRange`] if there is no move at `ply`
+    pub fn add_nag(&mut self, ply: usize, nag: u8) -> Result<(), ChessError> {
+        self.move_mut(ply)?.add_nag(nag);
+        Ok(())
+    }
+
+    /// Convert to string according to pgn standard
+    ///
+    /// The termination marker is the one the game was parsed with, otherwise it is taken from the
+    /// `Result` tag, or `*` if that is missing too.
+    pub fn as_pgn_str(&self) -> String {
+        self.as_pgn_str_with(WriteOptions::default())
+    }
+
+    /// Convert to string according to pgn standard using writing `options`
+    ///
+    /// The termination marker is chosen as in [`Game::as_pgn_str`].
+    pub fn as_pgn_str_with(&self, options: WriteOptions) -> String {
+        let mut tags: Vec<(&str, String)> = vec![];
+        if options.roster_first {
+            for key in SEVEN_TAG_ROSTER {
+                let value = match (key, self.tag(key)) {
+                    ("Result", _) => self.result().as_pgn_str(),
+                    (_, Some(value)) => value,
+                    ("Date", None) => "????.??.??",
+                    (_, None) => "?",
+                };
+                tags.push((key, value.to_string()));
+            }
+        }
+        for (key, value) in &self.tags {
+            let in_roster = SEVEN_TAG_ROSTER.contains(&key.as_str());
+            if (in_roster && options.roster_first) || (!in_roster && !options.other_tags) {
+                continue;
+            }
+            tags.push((key, value.clone()));
+        }
+        let mut s = String::new();
+        for (key, value) in &tags {
+            writeln!(s, "[{key} \"{value}\"]").unwrap();
+        }
+        if !tags.is_empty() {
+            s.push('\n');
+        }
+        let mut tokens: Vec<String> = vec![];
+        let mut in_new_variation = false;
+        for token in self.san_tokens() {
+            match (token, tokens.last_mut()) {
+                (SanToken::VariationStart, Some(last)) if in_new_variation => {
+                    last.push('(');
+                    continue;
+                }
+                (SanToken::VariationStart, _) => {
+                    tokens.push(String::from("("));
+                    in_new_variation = true;
+                    continue;
+                }
+                (SanToken::VariationEnd, Some(last)) => last.push(')'),
+                (token, Some(last)) if in_new_variation => last.push_str(&token.as_pgn_str()),
+                (token, _) => tokens.push(token.as_pgn_str()),
+            }
+            in_new_variation = false;
+        }
+        let Some(line_width) = options.line_width else {
+            s.push_str(&tokens.join(" "));
+            return s;
+        };
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 {
+                if line_len + 1 + token.len() > line_width {
+                    s.push('\n');
+                    line_len = 0;
+                } else {
+                    s.push(' ');
+                    line_len += 1;
+                }
+            }
+            line_len += token.len();
+            s.push_str(&token);
+        }
+        s
+    }
+
+    /// Movetext tokens in the order they are written, ending with the termination marker
+    ///
+    /// Tokens for each mainline move, including its variations, are only produced once the
+    /// iterator reaches it, so a scoresheet can be rendered as it streams in. The termination
+    /// marker is chosen as in [`Game::as_pgn_str`].
+    pub fn san_tokens(&self) -> impl Iterator<Item = SanToken> + '_ {
+        let result = match self.result {
+            Some(result) => result.as_pgn_str(),
+            None => self.tag("Result").unwrap_or("*"),
+        };
+        // Number moves on from the FEN position, an invalid tag is reported when the moves are played
+        let first_ply = self.starting_board().map_or(0, |board| board.ply());
+        line_tokens(&self.moves, first_ply).chain(iter::once(SanToken::Termination(result.to_string())))
+    }
+
+    /// Board before the first move
+    ///
+    /// Set up from the `FEN` tag if present, otherwise the standard starting position, and played
+    /// under the rules of [`Self::variant`].
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    pub fn starting_board(&self) -> Result<PieceListBoard, ChessError> {
+        let mut board = match self.tag("FEN") {
+            Some(fen) => PieceListBoard::from_fen(fen)?,
+            None => PieceListBoard::starting_board(),
+        };
+        if self.variant() == Variant::Chess960 {
+            board.set_variant(Variant::Chess960);
+        }
+        Ok(board)
+    }
+
+    /// Boards after each mainline move, starting with the position before the first move
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn positions(&self) -> Result<Vec<PieceListBoard>, ChessError> {
+        self.replay(self.moves.len())
+    }
+
+    /// Key for the opening played, hashing the positions after each of the first `plies` plies
+    ///
+    /// Games sharing the same first `plies` plies share a key, so it can be used to group games by
+    /// opening. Games shorter than `plies` hash every position they have.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn opening_key(&self, plies: usize) -> Result<u64, ChessError> {
+        Ok(self.replay(plies)?.iter().skip(1).fold(FNV_OFFSET, |key, board| {
+            (key ^ board.zobrist_hash()).wrapping_mul(FNV_PRIME)
+        }))
+    }
+
+    /// Pairs of mainline plies reaching the same position, as `(earlier, later)`
+    ///
+    /// Plies are indices into [`Game::positions`], so 0 is the position before the first move.
+    /// Positions are compared by hash, and pairs are ordered by the later ply, then the earlier.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn transposition_plies(&self) -> Result<Vec<(usize, usize)>, ChessError> {
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut pairs = vec![];
+        for (ply, board) in self.positions()?.iter().enumerate() {
+            let earlier = seen.entry(board.zobrist_hash()).or_default();
+            pairs.extend(earlier.iter().map(|&earlier| (earlier, ply)));
+            earlier.push(ply);
+        }
+        Ok(pairs)
+    }
+
+    /// Stable hash of the tags and mainline moves, for storing games
+    ///
+    /// Tags are hashed in order of key, so the order they were written in doesn't matter, and
+    /// moves are hashed as canonical SAN, so the same game written with different disambiguation
+    /// hashes the same. Comments, NAGs and variations are ignored.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn content_hash(&self) -> Result<u64, ChessError> {
+        let mut tags: Vec<&(String, String)> = self.tags.iter().collect();
+        tags.sort();
+        let mut fields: Vec<String> = tags
+            .into_iter()
+            .flat_map(|(key, value)| [key.clone(), value.clone()])
+            .collect();
+        let mut board = self.starting_board()?;
+        for game_move in &self.moves {
+            fields.push(play_move(&mut board, game_move.chess_move)?.as_pgn_str());
+        }
+        // Each field ends in a byte that never appears in UTF-8 so fields can't run together
+        Ok(fields.iter().fold(FNV_OFFSET, |hash, field| {
+            field
+                .bytes()
+                .chain([0xFF])
+                .fold(hash, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+        }))
+    }
+
+    /// Source and destination squares of the last mainline move, for highlighting it
+    ///
+    /// Castling gives the king's source and destination. `None` if no moves have been played or
+    /// the last move was a null move.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn last_move(&self) -> Result<Option<(SimpleSquare, SimpleSquare)>, ChessError> {
+        let Some(last) = self.moves.last().filter(|last| last.chess_move != AmbiguousMove::Null) else {
+            return Ok(None);
+        };
+        let positions = self.replay(self.moves.len() - 1)?;
+        let previous = positions
+            .last()
+            .ok_or(ChessError::PlyOutOfRange(self.moves.len() - 1))?;
+        let chess_move = previous.disambiguate_move(last.chess_move)?;
+        Ok(Some((chess_move.src(), chess_move.dest())))
+    }
+
+    /// Mainline plies whose SAN differs from the canonical SAN, as `(ply, stored, canonical)`
+    ///
+    /// Moves are stored as written, so this flags over or under disambiguated moves along with
+    /// missing or wrong check and checkmate markers, for auditing game databases.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn san_deviations(&self) -> Result<Vec<(usize, String, String)>, ChessError> {
+        let mut board = self.starting_board()?;
+        let mut deviations = vec![];
+        for (ply, game_move) in self.moves.iter().enumerate() {
+            let stored = game_move.chess_move.as_pgn_str();
+            let canonical = play_move(&mut board, game_move.chess_move)?.as_pgn_str();
+            if stored != canonical {
+                deviations.push((ply, stored, canonical));
+            }
+        }
+        Ok(deviations)
+    }
+
+    /// Mainline moves in canonical SAN, replayed from the starting position
+    ///
+    /// Unlike [`Game::moves`], which keeps moves as written, disambiguation and check markers are
+    /// worked out from the position each move is played in.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn to_san_list(&self) -> Result<Vec<String>, ChessError> {
+        let mut board = self.starting_board()?;
+        let mut sans = vec![];
+        for game_move in &self.moves {
+            sans.push(play_move(&mut board, game_move.chess_move)?.as_pgn_str());
+        }
+        Ok(sans)
+    }
+
+    /// Mainline moves as source square, destination square and promotion, for minimal UIs
+    ///
+    /// Castling gives the king's squares.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played, including null
+    ///   moves which have no squares
+    pub fn coord_moves(&self) -> Result<Vec<(SimpleSquare, SimpleSquare, Option<PieceKind>)>, ChessError> {
+        let mut board = self.starting_board()?;
+        let mut moves = vec![];
+        for game_move in &self.moves {
+            let chess_move = board.disambiguate_move(game_move.chess_move)?;
+            moves.push((chess_move.src(), chess_move.dest(), chess_move.promote_to()));
+            board.move_piece(chess_move)?;
+        }
+        Ok(moves)
+    }
+
+    /// Heuristically decide whether the game has settled into a fortress
+    ///
+    /// True if over the last `window` plies no material changed hands and at least one position
+    /// repeated, suggesting neither side can make progress and the game could be adjudicated as a
+    /// draw. Always false if the game is shorter than `window` plies or `window` is 0.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn is_likely_fortress(&self, window: usize) -> Result<bool, ChessError> {
+        if window == 0 || window > self.moves.len() {
+            return Ok(false);
+        }
+        let positions = self.positions()?;
+        let recent = &positions[positions.len() - window - 1..];
+        let start_material = material(&recent[0]);
+        if recent.iter().any(|board| material(board) != start_material) {
+            return Ok(false);
+        }
+        let hashes: HashSet<u64> = recent.iter().map(PieceListBoard::zobrist_hash).collect();
+        Ok(hashes.len() < recent.len())
+    }
+
+    /// Heuristically decide whether one side is giving perpetual check
+    ///
+    /// True if over the last `window` plies every move by one side gave check and some position
+    /// occurred three times, so the game could be adjudicated as a draw. Always false if the game
+    /// is shorter than `window` plies or `window` is 0.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn has_perpetual_check(&self, window: usize) -> Result<bool, ChessError> {
+        if window == 0 || window > self.moves.len() {
+            return Ok(false);
+        }
+        let positions = self.positions()?;
+        let recent = &positions[positions.len() - window - 1..];
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for board in recent {
+            *counts.entry(board.zobrist_hash()).or_default() += 1;
+        }
+        if counts.values().all(|&count| count < 3) {
+            return Ok(false);
+        }
+        let checks = recent[1..]
+            .iter()
+            .map(PieceListBoard::in_check)
+            .collect::<Result<Vec<bool>, ChessError>>()?;
+        let checking = |first: usize| checks.iter().skip(first).step_by(2).all(|&check| check);
+        Ok(checking(0) || checking(1))
+    }
+
+    /// Boards after each of the first `plies` plies, starting with the position before the first
+    fn replay(&self, plies: usize) -> Result<Vec<PieceListBoard>, ChessError> {
+        let mut board = self.starting_board()?;
+        let mut positions = vec![board.clone()];
+        for game_move in self.moves.iter().take(plies) {
+            apply_move(&mut board, game_move.chess_move)?;
+            positions.push(board.clone());
+        }
+        Ok(positions)
+    }
+
+    fn move_mut(&mut self, ply: usize) -> Result<&mut GameMove, ChessError> {
+        self.moves.get_mut(ply).ok_or(ChessError::PlyOutOfRange(ply))
+    }
+}
+
+/// Serialized as PGN, as written by [`Game::as_pgn_str`]
+#[cfg(feature = "serde")]
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_pgn_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::from_pgn_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Game being explored move by move, with undo, redo and variations
+///
+/// Wraps a [`Game`] along with the boards leading to the current move. Moves are located by paths
+/// as in [`Game::walk`], with the empty path standing for the position before the first move.
+/// Stepping back is free and stepping forward plays a single move, while jumping to another path
+/// replays the game from its starting board.
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    game: Game,
+    path: Vec<usize>,
+    boards: Vec<PieceListBoard>,
+    redo: Vec<Vec<usize>>,
+}
+
+impl GameTree {
+    /// Empty game starting from `board`
+    ///
+    /// The `SetUp` and `FEN` tags are set unless `board` is the standard starting position, and the
+    /// `Variant` tag is set when playing Chess960.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if `board` can't be written as FEN
+    pub fn new(board: PieceListBoard) -> Result<Self, ChessError> {
+        let mut game = Game::default();
+        let fen = board.as_fen_str()?;
+        if fen != PieceListBoard::starting_board().as_fen_str()? {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+        if board.variant() == Variant::Chess960 {
+            game.set_tag("Variant", "Chess960");
+        }
+        Ok(Self {
+            game,
+            path: vec![],
+            boards: vec![board],
+            redo: vec![],
+        })
+    }
+
+    /// Explore `game`, starting before its first move
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidFEN`] if the `FEN` tag is invalid
+    pub fn from_game(game: Game) -> Result<Self, ChessError> {
+        Ok(Self {
+            boards: vec![game.starting_board()?],
+            game,
+            path: vec![],
+            redo: vec![],
+        })
+    }
+
+    /// Game with every move played so far, mainline and variations
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Take the game with every move played so far
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+
+    /// Board after the current move
+    pub fn board(&self) -> &PieceListBoard {
+        &self.boards[self.ply()]
+    }
+
+    /// Path of the current move, empty before the first move
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// Number of plies played to reach the current move, counting moves in variations
+    pub fn ply(&self) -> usize {
+        self.boards.len() - 1
+    }
+
+    /// Move last played to reach the current position, `None` before the first move
+    pub fn current_move(&self) -> Option<&GameMove> {
+        self.node(&self.path)
+    }
+
+    /// Move last played to reach the current position, for annotating it
+    pub fn current_move_mut(&mut self) -> Option<&mut GameMove> {
+        let (&index, _) = self.path.split_last()?;
+        line_mut(&mut self.game.moves, &self.path)?.get_mut(index)
+    }
+
+    /// Move at `path`, `None` if there is none
+    pub fn node(&self, path: &[usize]) -> Option<&GameMove> {
+        let (&index, _) = path.split_last()?;
+        line(&self.game.moves, path)?.get(index)
+    }
+
+    /// Paths of the moves that can follow the move at `path`
+    ///
+    /// The continuation of the line comes first, followed by the first move of each of its
+    /// variations.
+    pub fn children(&self, path: &[usize]) -> Vec<Vec<usize>> {
+        let next = next_in_line(path);
+        let Some(game_move) = self.node(&next) else {
+            return vec![];
+        };
+        iter::once(next.clone())
+            .chain((0..game_move.variations.len()).map(|variation| [next.as_slice(), &[variation, 0]].concat()))
+            .filter(|child| self.node(child).is_some())
+            .collect()
+    }
+
+    /// Board after the move at `path`, without moving to it
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::PathNotFound`] if there is no move at `path`
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move leading to it can't be played
+    pub fn board_at(&self, path: &[usize]) -> Result<PieceListBoard, ChessError> {
+        self.replay_to(path)?
+            .pop()
+            .ok_or_else(|| ChessError::PathNotFound(path.to_vec()))
+    }
+
+    /// Play `chess_move` after the current move
+    ///
+    /// If the move already follows the current move, either as the continuation or as the start of
+    /// a variation, that line is entered. Otherwise the move continues the line when played at its
+    /// end, and starts a new variation when it isn't. Any moves that could be redone are forgotten.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if `chess_move` can't be played
+    pub fn play(&mut self, chess_move: AmbiguousMove) -> Result<(), ChessError> {
+        let mut board = self.board().clone();
+        let canonical = play_move(&mut board, chess_move)?;
+        let existing = self.children(&self.path).into_iter().find(|child| {
+            self.node(child)
+                .and_then(|game_move| play_move(&mut self.board().clone(), game_move.chess_move).ok())
+                == Some(canonical)
+        });
+        self.path = match existing {
+            Some(path) => path,
+            None => self.insert(GameMove::new(canonical)),
+        };
+        self.boards.push(board);
+        self.redo.clear();
+        Ok(())
+    }
+
+    /// Step back to the move before the current one, `false` if already before the first move
+    pub fn undo(&mut self) -> bool {
+        if self.path.is_empty() {
+            return false;
+        }
+        self.boards.pop();
+        let parent = parent(&self.path);
+        self.redo.push(mem::replace(&mut self.path, parent));
+        true
+    }
+
+    /// Step forward to the move last undone, or the continuation of the line if nothing was undone
+    ///
+    /// Returns `false` if there is no move to step forward to.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if the move can't be played
+    pub fn redo(&mut self) -> Result<bool, ChessError> {
+        let next = self.redo.last().cloned().unwrap_or_else(|| next_in_line(&self.path));
+        let Some(game_move) = self.node(&next) else {
+            return Ok(false);
+        };
+        let mut board = self.board().clone();
+        apply_move(&mut board, game_move.chess_move)?;
+        self.redo.pop();
+        self.boards.push(board);
+        self.path = next;
+        Ok(true)
+    }
+
+    /// Move to `ply` by undoing or redoing moves along the current line
+    ///
+    /// The cursor is left where it was if `ply` can't be reached.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::PlyOutOfRange`] if the line ends before `ply`
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move can't be played
+    pub fn goto_ply(&mut self, ply: usize) -> Result<(), ChessError> {
+        while self.ply() > ply {
+            self.undo();
+        }
+        let (start, path, redo) = (self.ply(), self.path.clone(), self.redo.clone());
+        let result = (start..ply).try_for_each(|_| match self.redo() {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ChessError::PlyOutOfRange(ply)),
+            Err(error) => Err(error),
+        });
+        if result.is_err() {
+            self.boards.truncate(start + 1);
+            self.path = path;
+            self.redo = redo;
+        }
+        result
+    }
+
+    /// Move to the move at `path`, forgetting any moves that could be redone
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::PathNotFound`] if there is no move at `path`
+    /// - [`crate::error::ChessError::ImpossibleMove`] or
+    ///   [`crate::error::ChessError::AmbiguousMove`] if a move leading to it can't be played
+    pub fn goto(&mut self, path: &[usize]) -> Result<(), ChessError> {
+        self.boards = self.replay_to(path)?;
+        self.path = path.to_vec();
+        self.redo.clear();
+        Ok(())
+    }
+
+    /// Boards from the starting board to the one after the move at `path`
+    fn replay_to(&self, path: &[usize]) -> Result<Vec<PieceListBoard>, ChessError> {
+        if !path.is_empty() && self.node(path).is_none() {
+            return Err(ChessError::PathNotFound(path.to_vec()));
+        }
+        let mut nodes = vec![];
+        let mut node = path.to_vec();
+        while !node.is_empty() {
+            let parent = parent(&node);
+            nodes.push(mem::replace(&mut node, parent));
+        }
+        let mut board = self.boards[0].clone();
+        let mut boards = vec![board.clone()];
+        for node in nodes.iter().rev() {
+            let game_move = self.node(node).unwrap();
+            apply_move(&mut board, game_move.chess_move)?;
+            boards.push(board.clone());
+        }
+        Ok(boards)
+    }
+
+    /// Add `game_move` after the current move, returning its path
+    fn insert(&mut self, game_move: GameMove) -> Vec<usize> {
+        let next = next_in_line(&self.path);
+        let index = next[next.len() - 1];
+        let line = line_mut(&mut self.game.moves, &next).unwrap();
+        if let Some(replaced) = line.get_mut(index) {
+            replaced.add_variation(vec![game_move]);
+            [next.as_slice(), &[replaced.variations.len() - 1, 0]].concat()
+        } else {
+            line.push(game_move);
+            next
+        }
+    }
+}
+
+/// Read the games of a multi-game PGN stream one at a time
+///
+/// Only one game is held in memory at once, so arbitrarily large files can be read. A game ends
+/// where a tag pair follows its movetext. Games that fail to parse give an error and reading carries
+/// on with the next game, but reading stops after an error from `reader`.
+pub fn read_games<R: Read>(reader: R) -> impl Iterator<Item = Result<Game, ChessError>> {
+    let mut lines = BufReader::new(reader).lines();
+    let mut next_game = String::new();
+    let mut failed = false;
+    iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let mut pgn = mem::take(&mut next_game);
+        let (mut in_movetext, mut in_comment) = (false, false);
+        for line in lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    failed = true;
+                    return Some(Err(error.into()));
+                }
+            };
+            let line = line.strip_prefix('\u{feff}').unwrap_or(&line);
+            let is_tag = !in_comment && line.starts_with('[');
+            if is_tag && in_movetext {
+                next_game = format!("{line}\n");
+                break;
+            }
+            if !is_tag {
+                in_movetext |= !line.trim().is_empty();
+                for c in line.chars() {
+                    match (in_comment, c) {
+                        (true, '}') => in_comment = false,
+                        (false, '{') => in_comment = true,
+                        (false, ';') => break,
+                        _ => (),
+                    }
+                }
+            }
+            pgn.push_str(line);
+            pgn.push('\n');
+        }
+        (!pgn.trim().is_empty()).then(|| Game::from_pgn_str(&pgn))
+    })
+}
+
+/// FEN of every position after each mainline move, across every game in a PGN stream
+///
+/// Games are read with [`read_games`], so memory use is bounded by the longest game. A game that
+/// fails to parse or replay gives a single error in place of its positions.
+pub fn extract_fens<R: Read>(reader: R) -> impl Iterator<Item = Result<String, ChessError>> {
+    read_games(reader).flat_map(|game| match game.and_then(|game| game.positions()) {
+        Ok(positions) => positions.iter().skip(1).map(PieceListBoard::as_fen_str).collect(),
+        Err(error) => vec![Err(error)],
+    })
+}
+
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01B3;
+
+/// Play `chess_move` on `board`, returning it as canonical SAN would write it
+///
+/// Null moves pass the turn with [`PieceListBoard::apply_null`].
+fn play_move(board: &mut PieceListBoard, chess_move: AmbiguousMove) -> Result<AmbiguousMove, ChessError> {
+    if chess_move == AmbiguousMove::Null {
+        board.apply_null();
+        return Ok(chess_move);
+    }
+    let chess_move = board.disambiguate_move(chess_move)?;
+    let canonical = board.ambiguous_move(chess_move)?;
+    board.move_piece(chess_move)?;
+    Ok(canonical)
+}
+
+/// Play `chess_move` on `board` without working out its SAN, for replaying moves already read
+///
+/// Null moves pass the turn with [`PieceListBoard::apply_null`].
+fn apply_move(board: &mut PieceListBoard, chess_move: AmbiguousMove) -> Result<(), ChessError> {
+    if chess_move == AmbiguousMove::Null {
+        board.apply_null();
+        return Ok(());
+    }
+    board.move_piece(board.disambiguate_move(chess_move)?)
+}
+
+/// Pieces on `board` ignoring where they stand
+fn material(board: &PieceListBoard) -> Vec<(PieceColour, PieceKind)> {
+    let mut material: Vec<_> = board
+        .all_pieces()
+        .into_iter()
+        .map(|piece| (piece.colour(), piece.kind()))
+        .collect();
+    material.sort_unstable();
+    material
+}
+
+/// Visit `moves` and their variations depth first, extending `path` with each move's index
+fn walk_moves<F: FnMut(&[usize], &AmbiguousMove)>(moves: &[GameMove], path: &mut Vec<usize>, f: &mut F) {
+    for (i, game_move) in moves.iter().enumerate() {
+        path.push(i);
+        f(path, &game_move.chess_move);
+        for (v, variation) in game_move.variations.iter().enumerate() {
+            path.push(v);
+            walk_moves(variation, path, f);
+            path.pop();
+        }
+        path.pop();
+    }
+}
+
+/// Line of moves holding the move at non-empty `path`
+fn line<'a>(moves: &'a [GameMove], path: &[usize]) -> Option<&'a [GameMove]> {
+    path[..path.len() - 1]
+        .chunks(2)
+        .try_fold(moves, |line, step| match *step {
+            [ply, variation] => line.get(ply)?.variations.get(variation).map(Vec::as_slice),
+            _ => None,
+        })
+}
+
+/// Line of moves holding the move at non-empty `path`, for adding to it
+fn line_mut<'a>(moves: &'a mut Vec<GameMove>, path: &[usize]) -> Option<&'a mut Vec<GameMove>> {
+    path[..path.len() - 1]
+        .chunks(2)
+        .try_fold(moves, |line, step| match *step {
+            [ply, variation] => line.get_mut(ply)?.variations.get_mut(variation),
+            _ => None,
+        })
+}
+
+/// Path of the move played before the move at `path`
+///
+/// The first move of a variation follows the move before the one it replaces.
+fn parent(path: &[usize]) -> Vec<usize> {
+    match path {
+        [] | [0] => vec![],
+        [.., 0] => parent(&path[..path.len() - 2]),
+        [rest @ .., last] => [rest, &[last - 1]].concat(),
+    }
+}
+
+/// Path of the move continuing the line of the move at `path`
+fn next_in_line(path: &[usize]) -> Vec<usize> {
+    match path {
+        [] => vec![0],
+        [rest @ .., last] => [rest, &[last + 1]].concat(),
+    }
+}
+
+/// Movetext tokens for the line of `moves` starting at `first_ply`, produced a move at a time
+fn line_tokens(moves: &[GameMove], first_ply: usize) -> impl Iterator<Item = SanToken> + '_ {
+    moves.iter().enumerate().flat_map(move |(i, game_move)| {
+        // Black's move number is repeated when something comes between it and white's move
+        let show_number = i == 0 || moves[i - 1].comment.is_some() || !moves[i - 1].variations.is_empty();
+        move_tokens(game_move, first_ply + i, show_number)
+    })
+}
+
+/// Movetext tokens for `game_move` played at `ply`, including its annotations and variations
+fn move_tokens(game_move: &GameMove, ply: usize, show_number: bool) -> Vec<SanToken> {
+    let mut tokens = vec![];
+    let number = ply / 2 + 1;
+    match (ply % 2, show_number) {
+        (0, _) => tokens.push(SanToken::MoveNumber {
+            number,
+            colour: PieceColour::White,
+        }),
+        (_, true) => tokens.push(SanToken::MoveNumber {
+            number,
+            colour: PieceColour::Black,
+        }),
+        _ => (),
+    }
+    tokens.push(SanToken::Move(game_move.chess_move.as_pgn_str()));
+    tokens.extend(game_move.nags.iter().map(|&nag| SanToken::Nag(nag)));
+    if let Some(comment) = &game_move.comment {
+        tokens.push(SanToken::Comment(comment.clone()));
+    }
+    for variation in &game_move.variations {
+        tokens.push(SanToken::VariationStart);
+        tokens.extend(line_tokens(variation, ply));
+        tokens.push(SanToken::VariationEnd);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::piece_list::tests::square;
+    use crate::comment::CommentCommand;
+
+    #[test]
+    fn parse_annotations() {
+        let game = Game::from_pgn_str("[Result \"1-0\"]\n\n1. e4 $1 {Best by test} e5 2. Nf3 1-0").unwrap();
+        assert_eq!(game.tag("Result"), Some("1-0"));
+        assert_eq!(game.moves().len(), 3);
+        assert_eq!(game.moves()[0].comment(), Some("Best by test"));
+        assert_eq!(game.moves()[0].nags(), [1]);
+        assert_eq!(game.moves()[1].comment(), None);
+    }
+
+    #[test]
+    fn parse_comment_commands() {
+        let game = Game::from_pgn_str("1. e4 {[%eval 0.17] [%foo bar]} e5 *").unwrap();
+        let commands = game.moves()[0].commands();
+        assert_eq!(commands.known.len(), 1);
+        assert_eq!(commands.unknown, [("foo".to_owned(), "bar".to_owned())]);
+        assert_eq!(game.moves()[1].commands(), CommentCommands::default());
+        assert!(game.as_pgn_str().contains("{[%eval 0.17] [%foo bar]}"));
+    }
+
+    #[test]
+    fn parse_round() {
+        for (value, round) in [
+            ("?", Round::Unknown),
+            ("-", Round::NotApplicable),
+            ("1", Round::Number(1)),
+            ("3.2", Round::Board(3, 2)),
+            ("playoff", Round::Other("playoff".to_string())),
+            ("1.2.3", Round::Other("1.2.3".to_string())),
+        ] {
+            let game = Game::from_pgn_str(&format!("[Round \"{value}\"]\n\n1. e4 *")).unwrap();
+            assert_eq!(game.round(), Some(round.clone()));
+            assert_eq!(round.as_tag_str(), value);
+        }
+        assert_eq!(Game::from_pgn_str("1. e4 *").unwrap().round(), None);
+    }
+
+    #[test]
+    fn parse_termination() {
+        for (value, termination) in [
+            ("Time forfeit", TerminationReason::TimeForfeit),
+            ("abandoned", TerminationReason::Abandoned),
+            ("Normal", TerminationReason::Normal),
+            ("Stalemate", TerminationReason::Other("Stalemate".to_string())),
+        ] {
+            let game = Game::from_pgn_str(&format!("[Termination \"{value}\"]\n\n1. e4 *")).unwrap();
+            assert_eq!(game.termination(), Some(termination));
+        }
+        assert_eq!(TerminationReason::RulesInfraction.as_tag_str(), "Rules infraction");
+        assert_eq!(Game::from_pgn_str("1. e4 *").unwrap().termination(), None);
+    }
+
+    #[test]
+    fn nag_before_result() {
+        let game = Game::from_pgn_str("1. e4 e5 $10 1/2-1/2").unwrap();
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(game.moves()[1].nags(), [10]);
+        assert!(game.as_pgn_str().contains("e5 $10"));
+    }
+
+    #[test]
+    fn annotate_and_export() {
+        let mut game = Game::from_pgn_str("[Result \"*\"]\n\n1. e4 e5 2. Nf3 Nc6 *").unwrap();
+        game.set_comment(1, "Symmetrical reply".to_string()).unwrap();
+        game.add_nag(2, 1).unwrap();
+        assert_eq!(
+            game.as_pgn_str(),
+            "[Result \"*\"]\n\n1. e4 e5 {Symmetrical reply} 2. Nf3 $1 Nc6 *"
+        );
+    }
+
+    #[test]
+    fn annotate_out_of_range() {
+        let mut game = Game::from_pgn_str("1. e4 e5 *").unwrap();
+        match game.add_nag(2, 1).unwrap_err() {
+            ChessError::PlyOutOfRange(2) => (),
+            e => panic!("Wrong error type {e}"),
+        }
+        match game.set_comment(5, String::new()).unwrap_err() {
+            ChessError::PlyOutOfRange(5) => (),
+            e => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn parse_variations() {
+        let game = Game::from_pgn_str("1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 *").unwrap();
+        assert_eq!(game.moves().len(), 3);
+        let variation = &game.moves()[1].variations()[0];
+        assert_eq!(variation.len(), 3);
+        assert_eq!(variation[1].variations()[0].len(), 1);
+        assert_eq!(game.as_pgn_str(), "1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 *");
+    }
+
+    #[test]
+    fn walk_variations() {
+        let game = Game::from_pgn_str("1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 (2. d4) *").unwrap();
+        let mut visited = vec![];
+        game.walk(|path, chess_move| visited.push((path.to_vec(), chess_move.as_pgn_str())));
+        assert_eq!(visited.len(), 8);
+        assert_eq!(visited[1], (vec![1], "e5".to_string()));
+        assert_eq!(visited[2], (vec![1, 0, 0], "c5".to_string()));
+        assert_eq!(visited[4], (vec![1, 0, 1, 0, 0], "c3".to_string()));
+        assert_eq!(visited[5], (vec![1, 0, 2], "d6".to_string()));
+        assert_eq!(visited[7], (vec![2, 0, 0], "d4".to_string()));
+    }
+
+    #[test]
+    fn unterminated_variation() {
+        match Game::from_pgn_str("1. e4 e5 (1... c5 2. Nf3").unwrap_err() {
+            ChessError::InvalidPGN(_) => (),
+            e => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn variation_too_deep() {
+        let pgn = format!("1. e4 {}{} *", "(1. d4 ".repeat(300), ")".repeat(300));
+        match Game::from_pgn_str(&pgn).unwrap_err() {
+            ChessError::VariationTooDeep(256) => (),
+            e => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn configured_variation_depth() {
+        let options = ParseOptions::default().max_variation_depth(2);
+        Game::from_pgn_str_with("1. e4 (1. d4 (1. c4)) *", options).unwrap();
+        match Game::from_pgn_str_with("1. e4 (1. d4 (1. c4 (1. Nf3))) *", options).unwrap_err() {
+            ChessError::VariationTooDeep(2) => (),
+            e => panic!("Wrong error type {e}"),
+        }
+    }
+
+    #[test]
+    fn shared_opening_key() {
+        let ruy_lopez = Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 *").unwrap();
+        let exchange = Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. Bxc6 dxc6 *").unwrap();
+        let transposed = Game::from_pgn_str("1. Nf3 Nc6 2. e4 e5 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 *").unwrap();
+        assert_eq!(ruy_lopez.opening_key(8).unwrap(), exchange.opening_key(8).unwrap());
+        assert_ne!(ruy_lopez.opening_key(10).unwrap(), exchange.opening_key(10).unwrap());
+        assert_ne!(ruy_lopez.opening_key(8).unwrap(), transposed.opening_key(8).unwrap());
+    }
+
+    #[test]
+    fn last_move() {
+        let mut game = Game::default();
+        assert_eq!(game.last_move().unwrap(), None);
+        game.push_move(notation::pgn_to_moves("1. e4").unwrap()[0]);
+        assert_eq!(game.last_move().unwrap(), Some((square("e2"), square("e4"))));
+        let castled = Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O *").unwrap();
+        assert_eq!(castled.last_move().unwrap(), Some((square("e1"), square("g1"))));
+    }
+
+    #[test]
+    fn fortress_shuffling() {
+        let shuffle = "[FEN \"8/8/4k3/8/2b5/4K3/3B4/8 w - - 0 1\"]\n\n1. Kf3 Kf6 2. Ke3 Ke6 3. Kf3 Kf6 4. Ke3 Ke6 *";
+        let game = Game::from_pgn_str(shuffle).unwrap();
+        assert!(game.is_likely_fortress(8).unwrap());
+        assert!(game.is_likely_fortress(4).unwrap());
+        assert!(!game.is_likely_fortress(3).unwrap());
+        assert!(!game.is_likely_fortress(9).unwrap());
+        assert!(!game.is_likely_fortress(0).unwrap());
+    }
+
+    #[test]
+    fn fortress_broken_by_capture() {
+        let capture = "[FEN \"8/8/4k2p/8/2b2B2/4K3/8/8 w - - 0 1\"]\n\n1. Kf3 Kf6 2. Ke3 Ke6 3. Bxh6 Kf5 *";
+        let game = Game::from_pgn_str(capture).unwrap();
+        assert!(!game.is_likely_fortress(6).unwrap());
+        assert!(!game.is_likely_fortress(2).unwrap());
+    }
+
+    #[test]
+    fn positions_from_fen_tag() {
+        let game = Game::from_pgn_str("[FEN \"4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 *").unwrap();
+        let positions = game.positions().unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1].as_fen_str().unwrap(), "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let pgn = "[Event \"Casual\"]\n[Result \"0-1\"]\n\n1. e4 {King's pawn} 1... e5 $2 2. Qh5 Nc6 0-1";
+        assert_eq!(Game::from_pgn_str(pgn).unwrap().as_pgn_str(), pgn);
+    }
+
+    #[test]
+    fn san_deviations() {
+        let game = Game::from_pgn_str("1. e4 e5 2. Ngf3 Nc6 3. Bb5 a6 4. Bxc6 dxc6 *").unwrap();
+        assert_eq!(
+            game.san_deviations().unwrap(),
+            [(2, "Ngf3".to_string(), "Nf3".to_string())]
+        );
+        let game = Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 *").unwrap();
+        assert!(game.san_deviations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bom_and_crlf() {
+        let lf = "[Event \"Casual\"]\n\n1. e4 {King's pawn\nopening} e5 ; rest of line\n2. Nf3 *";
+        let crlf = format!("\u{feff}{}", lf.replace('\n', "\r\n"));
+        let game = Game::from_pgn_str(&crlf).unwrap();
+        assert_eq!(game, Game::from_pgn_str(lf).unwrap());
+        assert_eq!(game.tag("Event"), Some("Casual"));
+        assert_eq!(game.moves()[0].comment(), Some("King's pawn\nopening"));
+        assert_eq!(game.moves().len(), 3);
+    }
+
+    #[test]
+    fn clock_tags() {
+        let game = Game::from_pgn_str("[WhiteClock \"0:03:00\"]\n[BlackClock \"3 minutes\"]\n\n1. e4 *").unwrap();
+        assert_eq!(
+            game.clock_tag(PieceColour::White).unwrap(),
+            Some(Duration::from_secs(180))
+        );
+        assert!(matches!(
+            game.clock_tag(PieceColour::Black),
+            Err(ChessError::InvalidPGN(_))
+        ));
+        assert_eq!(
+            Game::from_pgn_str("1. e4 *")
+                .unwrap()
+                .clock_tag(PieceColour::White)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn content_hash() {
+        let pgn = "[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+        let game = Game::from_pgn_str(pgn).unwrap();
+        let reordered =
+            Game::from_pgn_str("[Black \"B\"]\n[White \"A\"]\n\n1. e4 e5 2. Ngf3 {Main line} Nc6 *").unwrap();
+        assert_eq!(game.content_hash().unwrap(), reordered.content_hash().unwrap());
+        let mut renamed = game.clone();
+        renamed.set_tag("White", "C");
+        assert_ne!(game.content_hash().unwrap(), renamed.content_hash().unwrap());
+        let other_moves = Game::from_pgn_str("[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 2. Nc3 Nc6 *").unwrap();
+        assert_ne!(game.content_hash().unwrap(), other_moves.content_hash().unwrap());
+    }
+
+    #[test]
+    fn lichess_games() {
+        let pgn = "[Event \"Rated Blitz game\"]\n[Site \"https://lichess.org/abcdefgh\"]\n[White \"alice\"]\n\
+                   [Black \"bob\"]\n[Result \"1-0\"]\n\n1. e4 { [%clk 0:03:00] } 1... e5 { [%clk 0:03:00] } \
+                   2. Bc4 Nc6 3. Qh5 Nf6?? { Blunders into mate. } 4. Qxf7# { White wins by checkmate. } 1-0\n";
+        let game = Game::from_pgn_str(pgn).unwrap();
+        assert_eq!(game.tag("White"), Some("alice"));
+        assert_eq!(game.result(), GameResult::WhiteWin);
+        assert_eq!(game.moves().len(), 7);
+        assert_eq!(game.moves()[5].nags(), [4]);
+        assert_eq!(game.moves()[6].comment(), Some("White wins by checkmate."));
+
+        let pgn = "[Event \"Casual\"]\n[Result \"1/2-1/2\"]\n\n1. e4 e5 2. Nf3 Nf6 3. Ng1 Ng8 4. Nf3 Nf6 \
+                   5. Ng1 Ng8 1/2-1/2";
+        let game = Game::from_pgn_str(pgn).unwrap();
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.moves().len(), 10);
+        assert_eq!(Game::from_pgn_str("1. e4 e5").unwrap().result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn descriptive_errors() {
+        assert!(matches!(
+            Game::from_pgn_str("[Event \"Casual\"]\n[White alice]\n\n1. e4 *"),
+            Err(ChessError::InvalidTag(tag)) if tag == "[White alice]"
+        ));
+        assert!(matches!(
+            Game::from_pgn_str("1. e4 {Opens the game 1... e5 *"),
+            Err(ChessError::UnterminatedComment(comment)) if comment == "{Opens the game 1... e5 *"
+        ));
+        assert!(matches!(
+            Game::from_pgn_str("1. e4 e5 3. Nf3 *"),
+            Err(ChessError::UnexpectedMoveNumber(number)) if number == "3."
+        ));
+        assert!(matches!(
+            Game::from_pgn_str("1. e4 e5 2... Nf3 *"),
+            Err(ChessError::UnexpectedMoveNumber(number)) if number == "2..."
+        ));
+        let game = Game::from_pgn_str("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *").unwrap();
+        assert_eq!(game.moves()[1].variations().len(), 1);
+        assert!(matches!(
+            Game::from_pgn_str("1. e4 e5 (2. c5) *"),
+            Err(ChessError::UnexpectedMoveNumber(_))
+        ));
+    }
+
+    #[test]
+    fn san_tokens() {
+        let game = Game::from_pgn_str("1. e4 $1 {Best by test} 1... c5 (1... e5 2. Nf3) 2. Nf3 1-0").unwrap();
+        let white = |number| SanToken::MoveNumber {
+            number,
+            colour: PieceColour::White,
+        };
+        let black = |number| SanToken::MoveNumber {
+            number,
+            colour: PieceColour::Black,
+        };
+        let san = |s: &str| SanToken::Move(s.to_string());
+        assert_eq!(
+            game.san_tokens().collect::<Vec<_>>(),
+            [
+                white(1),
+                san("e4"),
+                SanToken::Nag(1),
+                SanToken::Comment("Best by test".to_string()),
+                black(1),
+                san("c5"),
+                SanToken::VariationStart,
+                black(1),
+                san("e5"),
+                white(2),
+                san("Nf3"),
+                SanToken::VariationEnd,
+                white(2),
+                san("Nf3"),
+                SanToken::Termination("1-0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn black_variation() {
+        let game = Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 (2... Nf6 3. Bc4) 3. Bb5 *").unwrap();
+        let variation = &game.moves()[3].variations()[0];
+        // The variation replaces black's second move, so is replayed from the board before it
+        let mut board = game.positions().unwrap()[3].clone();
+        for game_move in variation {
+            let chess_move = board.disambiguate_move(game_move.chess_move()).unwrap();
+            board.move_piece(chess_move).unwrap();
+        }
+        assert_eq!(
+            board.as_fen_str().unwrap(),
+            "rnbqkb1r/pppp1ppp/5n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+        );
+        assert_eq!(game.as_pgn_str(), "1. e4 e5 2. Nf3 Nc6 (2... Nf6 3. Bc4) 3. Bb5 *");
+        assert!(matches!(
+            Game::from_pgn_str("1. e4 e5 2. Nf3 Nc6 (2. Nf6 3. Bc4) 3. Bb5 *"),
+            Err(ChessError::UnexpectedMoveNumber(number)) if number == "2."
+        ));
+    }
+
+    #[test]
+    fn from_fen_position() {
+        let pgn = "[FEN \"r3k3/8/8/8/8/8/8/4K2R b Kq - 3 20\"]\n\n20... O-O-O 21. Rh8 Rxh8 *";
+        let game = Game::from_pgn_str(pgn).unwrap();
+        assert_eq!(game.to_san_list().unwrap(), ["O-O-O", "Rh8", "Rxh8"]);
+        assert!(game.as_pgn_str().ends_with("20... O-O-O 21. Rh8 Rxh8 *"));
+    }
+
+    #[test]
+    fn transposition_plies() {
+        let game = Game::from_pgn_str("1. Nf3 Nf6 2. Nc3 Nc6 3. Nb1 Nb8 4. Ng1 Ng8 *").unwrap();
+        assert_eq!(game.transposition_plies().unwrap(), [(2, 6), (0, 8)]);
+        let game = Game::from_pgn_str("1. e4 e5 2. Nf3 *").unwrap();
+        assert!(game.transposition_plies().unwrap().is_empty());
+    }
+
+    #[test]
+    fn null_moves() {
+        let game = Game::from_pgn_str("1. e4 e5 2. Nf3 (2. -- Nc6 3. Z0 Nf6) 2... Nc6 3. -- *").unwrap();
+        let variation = &game.moves()[2].variations()[0];
+        assert_eq!(variation[0].chess_move(), AmbiguousMove::Null);
+        assert_eq!(variation[2].chess_move(), AmbiguousMove::Null);
+        assert_eq!(game.to_san_list().unwrap(), ["e4", "e5", "Nf3", "Nc6", "--"]);
+        assert_eq!(game.positions().unwrap()[5].ply(), 5);
+        assert_eq!(game.last_move().unwrap(), None);
+        assert!(game.as_pgn_str().ends_with("(2. -- Nc6 3. -- Nf6) 2... Nc6 3. -- *"));
+    }
+
+    #[test]
+    fn write_options() {
+        let game =
+            Game::from_pgn_str("[White \"Tal\"]\n[ECO \"C20\"]\n[Event \"Riga\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0").unwrap();
+        assert_eq!(
+            game.as_pgn_str_with(WriteOptions::default()),
+            "[White \"Tal\"]\n[ECO \"C20\"]\n[Event \"Riga\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0"
+        );
+        let options = WriteOptions::default()
+            .roster_first(true)
+            .other_tags(false)
+            .line_width(Some(12));
+        assert_eq!(
+            game.as_pgn_str_with(options),
+            "[Event \"Riga\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Tal\"]\n[Black \"?\"]\n\
+             [Result \"1-0\"]\n\n1. e4 e5 2.\nNf3 Nc6 1-0"
+        );
+    }
+
+    #[test]
+    fn extract_fens() {
+        let pgn = "[Event \"One\"]\n\n1. e4 {[Event in a comment]\n[not a tag]} e5 2. Nf3 *\n\n\
+                   [Event \"Two\"]\n[Black \"?\"]\n\n1. d4 d5 1/2-1/2\n";
+        let games: Vec<Game> = read_games(pgn.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            games.iter().map(|game| game.tag("Event").unwrap()).collect::<Vec<_>>(),
+            ["One", "Two"]
+        );
+        let fens: Vec<String> = super::extract_fens(pgn.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(fens.len(), 5);
+        assert_eq!(fens[0], "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(fens[4], "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 2");
+        let results: Vec<_> = super::extract_fens("1. e4 Ke7 *\n[Event \"Two\"]\n1. e4 *".as_bytes()).collect();
+        assert!(matches!(results[..], [Err(ChessError::ImpossibleMove(_)), Ok(_)]));
+    }
+
+    #[test]
+    fn coord_moves() {
+        let game =
+            Game::from_pgn_str("[FEN \"4k3/1P6/8/8/8/8/4P3/4K2R w K - 0 1\"]\n\n1. e4 Kd7 2. O-O Kc7 3. b8=Q+ *")
+                .unwrap();
+        let moves = game.coord_moves().unwrap();
+        assert_eq!(moves[0], (square("e2"), square("e4"), None));
+        assert_eq!(moves[2], (square("e1"), square("g1"), None));
+        assert_eq!(moves[4], (square("b7"), square("b8"), Some(PieceKind::Queen)));
+        Game::from_pgn_str("1. e4 -- *").unwrap().coord_moves().unwrap_err();
+    }
+
+    #[test]
+    fn has_perpetual_check() {
+        let pgn = "[FEN \"7k/6p1/7p/8/8/8/8/K4Q2 w - - 0 1\"]\n\n\
+                   1. Qf8+ Kh7 2. Qf5+ Kh8 3. Qf8+ Kh7 4. Qf5+ Kh8 5. Qf8+ *";
+        let game = Game::from_pgn_str(pgn).unwrap();
+        assert!(game.has_perpetual_check(8).unwrap());
+        assert!(!game.has_perpetual_check(6).unwrap());
+        assert!(!game.has_perpetual_check(10).unwrap());
+        let game = Game::from_pgn_str("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 *").unwrap();
+        assert!(!game.has_perpetual_check(8).unwrap());
+    }
+
+    #[test]
+    fn lichess_export() {
+        let pgn = "[Event \"Rated Blitz game\"]\n[Site \"https://lichess.org/abcdefgh\"]\n[Result \"1-0\"]\n\n\
+                   1. e4 { [%clk 0:03:00] } 1... e5?! $6 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Qh5 Nc6 3. Bc4 Nf6?? \
+                   4. Qxf7# { Checkmate } 1-0\n\n\
+                   [Event \"Rated Blitz game\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n";
+        let games: Vec<Game> = read_games(pgn.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(games.len(), 2);
+        let moves = games[0].moves();
+        assert_eq!(moves.len(), 7);
+        assert_eq!(games[0].tag("Site"), Some("https://lichess.org/abcdefgh"));
+        assert_eq!(
+            moves[0].commands().known,
+            [CommentCommand::Clock(Duration::from_secs(180))]
+        );
+        assert_eq!(moves[1].nags(), [6, 6]);
+        assert_eq!(moves[1].variations()[0][1].variations()[0].len(), 1);
+        assert_eq!(moves[5].nags(), [4]);
+        assert_eq!(moves[6].comment(), Some("Checkmate"));
+        assert_eq!(games[0].result(), GameResult::WhiteWin);
+        assert_eq!(games[1].result(), GameResult::BlackWin);
+        assert_eq!(games[1].to_san_list().unwrap(), ["f3", "e5", "g4", "Qh4#"]);
+    }
+
+    #[test]
+    fn chess960_variant_tag() {
+        let moves = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. O-O Bc5 5. d3 O-O *";
+        let standard = Game::from_pgn_str(moves).unwrap();
+        let chess960 = Game::from_pgn_str(&format!("[Variant \"Chess960\"]\n\n{moves}")).unwrap();
+        assert_eq!(standard.variant(), Variant::Standard);
+        assert_eq!(chess960.variant(), Variant::Chess960);
+        assert_eq!(
+            chess960.positions().unwrap().last().unwrap().as_fen_str().unwrap(),
+            standard.positions().unwrap().last().unwrap().as_fen_str().unwrap()
+        );
+        assert_eq!(chess960.coord_moves().unwrap()[6], (square("e1"), square("h1"), None));
+        assert_eq!(standard.coord_moves().unwrap()[6], (square("e1"), square("g1"), None));
+        assert_eq!(chess960.to_san_list().unwrap()[6], "O-O");
+    }
+
+    #[test]
+    fn game_tree_undo_redo() {
+        let mut tree = GameTree::new(PieceListBoard::starting_board()).unwrap();
+        for san in ["e4", "e5", "Nf3"] {
+            tree.play(AmbiguousMove::try_from(san).unwrap()).unwrap();
+        }
+        assert_eq!(tree.path(), [2]);
+        assert!(tree.undo());
+        assert!(tree.undo());
+        assert_eq!(tree.ply(), 1);
+        assert!(tree.redo().unwrap());
+        assert_eq!(tree.current_move().unwrap().chess_move().as_pgn_str(), "e5");
+        tree.goto_ply(0).unwrap();
+        assert!(!tree.undo());
+        assert!(matches!(tree.goto_ply(4), Err(ChessError::PlyOutOfRange(4))));
+        assert_eq!(tree.ply(), 0);
+        tree.goto_ply(3).unwrap();
+        assert_eq!(tree.board(), &tree.game().positions().unwrap()[3]);
+        assert!(!tree.redo().unwrap());
+        assert_eq!(tree.game().as_pgn_str(), "1. e4 e5 2. Nf3 *");
+    }
+
+    #[test]
+    fn game_tree_variations() {
+        let game = Game::from_pgn_str("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *").unwrap();
+        let mut tree = GameTree::from_game(game).unwrap();
+        assert_eq!(tree.children(&[0]), [vec![1], vec![1, 0, 0]]);
+        tree.goto(&[1, 0, 1]).unwrap();
+        assert_eq!(tree.ply(), 3);
+        assert_eq!(tree.board(), &tree.board_at(&[1, 0, 1]).unwrap());
+        tree.play(AmbiguousMove::try_from("d6").unwrap()).unwrap();
+        assert_eq!(tree.path(), [1, 0, 2]);
+        tree.goto_ply(1).unwrap();
+        assert_eq!(tree.path(), [0]);
+        tree.play(AmbiguousMove::try_from("c5").unwrap()).unwrap();
+        assert_eq!(tree.path(), [1, 0, 0]);
+        tree.undo();
+        tree.play(AmbiguousMove::try_from("e6").unwrap()).unwrap();
+        assert_eq!(tree.path(), [1, 1, 0]);
+        assert!(tree.undo());
+        assert!(tree.redo().unwrap());
+        assert_eq!(tree.path(), [1, 1, 0]);
+        tree.current_move_mut().unwrap().set_comment("French".to_string());
+        tree.goto(&[]).unwrap();
+        tree.goto_ply(2).unwrap();
+        assert_eq!(tree.path(), [1]);
+        assert!(matches!(tree.goto(&[1, 2, 0]), Err(ChessError::PathNotFound(path)) if path == [1, 2, 0]));
+        assert_eq!(
+            tree.into_game().as_pgn_str(),
+            "1. e4 e5 (1... c5 2. Nf3 d6) (1... e6 {French}) 2. Nf3 *"
+        );
+    }
+
+    #[test]
+    fn game_tree_from_position() {
+        let board = PieceListBoard::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mut tree = GameTree::new(board.clone()).unwrap();
+        tree.play(AmbiguousMove::try_from("O-O").unwrap()).unwrap();
+        let game = tree.game().clone();
+        assert_eq!(game.tag("FEN"), Some("4k3/8/8/8/8/8/8/4K2R w K - 0 1"));
+        assert_eq!(
+            game.starting_board().unwrap().as_fen_str().unwrap(),
+            board.as_fen_str().unwrap()
+        );
+        tree.play(AmbiguousMove::try_from("Ke1").unwrap()).unwrap_err();
+        assert_eq!(tree.ply(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::board::bitboard::BitBoard;
+
+        let game = Game::from_pgn_str("[Event \"Casual\"]\n\n1. e4 {Best by test} e5 (1... c5) 1-0").unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(json, serde_json::to_string(&game.as_pgn_str()).unwrap());
+        assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+        assert_eq!(serde_json::to_string(&game.result()).unwrap(), "\"1-0\"");
+        let fen = "r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1";
+        let board = PieceListBoard::from_fen(fen).unwrap();
+        let json = format!("{{\"fen\":\"{fen}\",\"variant\":\"Standard\"}}");
+        assert_eq!(serde_json::to_string(&board).unwrap(), json);
+        let bitboard: BitBoard = serde_json::from_str(&json).unwrap();
+        assert_eq!(bitboard.as_fen_str(), fen);
+        serde_json::from_str::<PieceListBoard>("{\"fen\":\"8/8 w - - 0 1\",\"variant\":\"Standard\"}").unwrap_err();
+        // Chess960 number 518 has the standard FEN, so only the variant tells them apart
+        let chess960 = PieceListBoard::chess960_starting_board(518).unwrap();
+        let json = serde_json::to_string(&chess960).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PieceListBoard>(&json).unwrap().variant(),
+            Variant::Chess960
+        );
+        let chess960 = BitBoard::chess960_starting_board(518).unwrap();
+        let json = serde_json::to_string(&chess960).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BitBoard>(&json).unwrap().variant(),
+            Variant::Chess960
+        );
+    }
+}
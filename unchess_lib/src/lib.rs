@@ -1,12 +1,23 @@
 //! Chess backend and engine library
 
 pub mod board;
+pub mod book;
+pub mod comment;
+pub mod diagram;
 pub mod enums;
 pub mod error;
+pub mod eval;
+pub mod game;
 pub mod notation;
-mod parser;
+pub mod parser;
 pub mod simple_types;
+pub mod square_set;
+pub mod svg;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod traits;
+pub mod uci;
+mod zobrist;
 
 #[cfg(doctest)]
 #[doc = include_str!("../../Readme.md")]
@@ -74,12 +74,20 @@ pub fn char_to_rank(rank: char) -> Result<u8, ChessError> {
     }
 }
 
+/// Wrap `comment` in braces as a PGN comment
+///
+/// PGN comments can't be nested or escaped, so any `}` in `comment` is stripped to stop the comment
+/// from ending early.
+pub fn comment_to_pgn(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ""))
+}
+
 /// Convert pgn file to vector of moves
 ///
 /// # Errors
 /// - [`crate::error::ChessError::InvalidPGN`] if PGN can't be parsed
 pub fn pgn_to_moves(input: &str) -> Result<Vec<AmbiguousMove>, ChessError> {
-    if let Ok((_, (_, moves))) = parser::pgn::pgn(input) {
+    if let Ok((_, (_, moves))) = parser::pgn::pgn(&parser::pgn::normalise(input)) {
         Ok(moves)
     } else {
         Err(ChessError::InvalidPGN(input.to_string()))
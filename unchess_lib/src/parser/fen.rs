@@ -1,22 +1,29 @@
+//! Reading and writing positions in Forsyth-Edwards Notation
+
 use itertools::Itertools as _;
 use nom::{
     Err, IResult, Parser as _,
     branch::alt,
     bytes::complete::tag,
-    character::complete::{char, multispace0, one_of, u32, usize},
-    combinator::{map_res, opt, value},
+    character::complete::{char, multispace0, multispace1, one_of, u32, usize},
+    combinator::{map_res, opt, recognize, value},
     error,
-    multi::{many1, separated_list1},
+    multi::{many0, many1, separated_list1},
 };
 #[cfg(test)]
 use proptest::prelude::Strategy;
 
-use std::fmt::Write as _;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+};
 
 use crate::{
     enums::{CastlingSide, PieceColour, PieceKind},
+    error::ChessError,
     parser::pgn::square,
     simple_types::{SimplePiece, SimpleSquare},
+    traits::ChessSquare as _,
 };
 
 fn white_piece(input: &str) -> IResult<&str, SimplePiece> {
@@ -38,6 +45,7 @@ fn turn(input: &str) -> IResult<&str, PieceColour> {
     alt((value(PieceColour::White, tag("w")), value(PieceColour::Black, tag("b")))).parse(input)
 }
 
+/// Parse rank, failing if the pieces and runs of empty squares don't add up to exactly 8 squares
 fn rank(mut input: &str) -> IResult<&str, [Option<SimplePiece>; 8]> {
     let mut i = 0;
     let mut out = [None; 8];
@@ -47,9 +55,12 @@ fn rank(mut input: &str) -> IResult<&str, [Option<SimplePiece>; 8]> {
             i += 1;
             input
         } else {
-            let (input, empty_squares) = usize(input)?;
+            let (rest, empty_squares) = usize(input)?;
+            if empty_squares == 0 || i + empty_squares > out.len() {
+                return Err(Err::Error(error::Error::new(input, error::ErrorKind::TooLarge)));
+            }
             i += empty_squares;
-            input
+            rest
         };
     }
     Ok((input, out))
@@ -67,39 +78,89 @@ fn board_layout(input: &str) -> IResult<&str, Box<[[Option<SimplePiece>; 8]; 8]>
     }
 }
 
-fn castling_rights(input: &str) -> IResult<&str, [bool; 4]> {
-    let (input, castles) = many1(alt((
-        |i| {
-            let (i, _) = char('K')(i)?;
-            Ok((i, Some((CastlingSide::KingSide, PieceColour::White))))
-        },
-        |i| {
-            let (i, _) = char('Q')(i)?;
-            Ok((i, Some((CastlingSide::QueenSide, PieceColour::White))))
-        },
-        |i| {
-            let (i, _) = char('k')(i)?;
-            Ok((i, Some((CastlingSide::KingSide, PieceColour::Black))))
-        },
-        |i| {
-            let (i, _) = char('q')(i)?;
-            Ok((i, Some((CastlingSide::QueenSide, PieceColour::Black))))
-        },
-        |i| {
-            let (i, _) = char('-')(i)?;
-            Ok((i, None))
-        },
-    )))
-    .parse(input)?;
-    Ok((
-        input,
-        [
-            castles.contains(&Some((CastlingSide::KingSide, PieceColour::White))),
-            castles.contains(&Some((CastlingSide::QueenSide, PieceColour::White))),
-            castles.contains(&Some((CastlingSide::KingSide, PieceColour::Black))),
-            castles.contains(&Some((CastlingSide::QueenSide, PieceColour::Black))),
-        ],
-    ))
+/// File of the rook each castling right castles with in standard chess, in the order of
+/// [`Fen::castling_rights`]
+pub const STANDARD_CASTLING_FILES: [u8; 4] = [7, 0, 7, 0];
+
+/// Castling right as written in the FEN castling field
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CastlingLetter {
+    /// `KQkq`, castling with the outermost rook on that side of the king
+    Side(CastlingSide),
+    /// Shredder-FEN `HAha`, castling with the rook on that file
+    File(u8),
+}
+
+fn castling_letter(input: &str) -> IResult<&str, Option<(PieceColour, CastlingLetter)>> {
+    let (input, letter) = one_of("KQkqABCDEFGHabcdefgh-").parse(input)?;
+    let colour = if letter.is_ascii_uppercase() {
+        PieceColour::White
+    } else {
+        PieceColour::Black
+    };
+    let letter = match letter.to_ascii_lowercase() {
+        '-' => return Ok((input, None)),
+        'k' => CastlingLetter::Side(CastlingSide::KingSide),
+        'q' => CastlingLetter::Side(CastlingSide::QueenSide),
+        file => CastlingLetter::File(file as u8 - b'a'),
+    };
+    Ok((input, Some((colour, letter))))
+}
+
+fn castling_rights(input: &str) -> IResult<&str, Vec<(PieceColour, CastlingLetter)>> {
+    let (input, letters) = many1(castling_letter).parse(input)?;
+    Ok((input, letters.into_iter().flatten().collect()))
+}
+
+/// Castling rights and the file of the rook each castles with, in the order of
+/// [`Fen::castling_rights`]
+///
+/// `KQkq` castle with the outermost rook on that side of the king, or the standard corner if there
+/// is none, so positions with rooks in the corners always castle with them.
+fn resolve_castling(
+    layout: &[[Option<SimplePiece>; 8]; 8],
+    letters: &[(PieceColour, CastlingLetter)],
+) -> ([bool; 4], [u8; 4]) {
+    let mut rights = [false; 4];
+    let mut files = STANDARD_CASTLING_FILES;
+    for &(colour, letter) in letters {
+        let back_rank = match colour {
+            PieceColour::White => &layout[7],
+            PieceColour::Black => &layout[0],
+        };
+        let files_of = |kind| {
+            back_rank
+                .iter()
+                .positions(move |&piece| piece == Some(SimplePiece::new(kind, colour)))
+                .map(|file| file as u8)
+        };
+        let king_file = files_of(PieceKind::King).next().unwrap_or(4);
+        let (side, file) = match letter {
+            CastlingLetter::Side(side) => {
+                let outermost = match side {
+                    CastlingSide::KingSide => files_of(PieceKind::Rook).filter(|&file| file > king_file).max(),
+                    CastlingSide::QueenSide => files_of(PieceKind::Rook).filter(|&file| file < king_file).min(),
+                };
+                (side, outermost.unwrap_or_else(|| side.standard_rook_file()))
+            }
+            CastlingLetter::File(file) if file > king_file || (file == king_file && file >= 4) => {
+                (CastlingSide::KingSide, file)
+            }
+            CastlingLetter::File(file) => (CastlingSide::QueenSide, file),
+        };
+        let index = castling_index(colour, side);
+        rights[index] = true;
+        files[index] = file;
+    }
+    (rights, files)
+}
+
+/// Index of `colour`'s right to castle on `side` in [`Fen::castling_rights`]
+fn castling_index(colour: PieceColour, side: CastlingSide) -> usize {
+    Fen::CASTLING_SIDES
+        .iter()
+        .position(|&right| right == (colour, side))
+        .unwrap_or_default()
 }
 
 fn en_passant(input: &str) -> IResult<&str, Option<SimpleSquare>> {
@@ -116,18 +177,78 @@ fn en_passant(input: &str) -> IResult<&str, Option<SimpleSquare>> {
     .parse(input)
 }
 
+/// Parse FEN with fields separated by whitespace
+///
+/// A missing halfmove clock or fullmove number is read as 0.
+///
+/// # Errors
+/// - [`nom::Err::Error`] if `input` doesn't start with a valid FEN
 pub fn fen(input: &str) -> IResult<&str, Fen> {
+    fen_separated_by(input, multispace0)
+}
+
+/// Parse FEN with fields separated by whitespace or underscores
+///
+/// URLs, such as Lichess analysis links, often replace the spaces in FENs with underscores.
+///
+/// # Errors
+/// - [`nom::Err::Error`] if `input` doesn't start with a valid FEN
+pub fn board_lenient(input: &str) -> IResult<&str, Fen> {
+    fen_separated_by(input, |input| {
+        recognize(many0(alt((multispace1, tag("_"))))).parse(input)
+    })
+}
+
+/// Parse just the piece placement field of a FEN
+///
+/// White is to move with no castling rights or en passant square, a halfmove clock of 0 and
+/// fullmove number of 1.
+fn placement(input: &str) -> IResult<&str, Fen> {
     let (input, _) = multispace0(input)?;
     let (input, layout) = board_layout(input)?;
     let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        Fen {
+            layout,
+            turn: PieceColour::White,
+            castling_rights: [false; 4],
+            castling_files: STANDARD_CASTLING_FILES,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        },
+    ))
+}
+
+/// Board from just the piece placement field of a FEN
+///
+/// White is to move with no castling rights or en passant square, a halfmove clock of 0 and
+/// fullmove number of 1.
+///
+/// # Errors
+/// [`crate::error::ChessError::InvalidFEN`] If the placement isn't valid syntax or is followed
+/// by other fields
+pub fn placement_only<B: From<Fen>>(input: &str) -> Result<B, ChessError> {
+    match placement(input) {
+        Ok(("", fen)) => Ok(B::from(fen)),
+        _ => Err(ChessError::InvalidFEN(input.to_string())),
+    }
+}
+
+fn fen_separated_by<'a>(input: &'a str, separator: fn(&'a str) -> IResult<&'a str, &'a str>) -> IResult<&'a str, Fen> {
+    let (input, _) = separator(input)?;
+    let (input, layout) = board_layout(input)?;
+    let (input, _) = separator(input)?;
     let (input, turn) = turn(input)?;
-    let (input, _) = multispace0(input)?;
-    let (input, castling_rights) = castling_rights(input)?;
-    let (input, _) = multispace0(input)?;
+    let (input, _) = separator(input)?;
+    let (input, castling_letters) = castling_rights(input)?;
+    let (castling_rights, castling_files) = resolve_castling(&layout, &castling_letters);
+    let (input, _) = separator(input)?;
     let (input, en_passant) = en_passant(input)?;
-    let (input, _) = multispace0(input)?;
+    let (input, _) = separator(input)?;
     let (input, halfmove_clock) = opt(u32).parse(input)?;
-    let (input, _) = multispace0(input)?;
+    let (input, _) = separator(input)?;
     let (input, fullmove_number) = opt(u32).parse(input)?;
     Ok((
         input,
@@ -135,6 +256,7 @@ pub fn fen(input: &str) -> IResult<&str, Fen> {
             layout,
             turn,
             castling_rights,
+            castling_files,
             en_passant,
             halfmove_clock: halfmove_clock.unwrap_or(0),
             fullmove_number: fullmove_number.unwrap_or(0),
@@ -142,17 +264,36 @@ pub fn fen(input: &str) -> IResult<&str, Fen> {
     ))
 }
 
+/// Fields of a FEN, which boards are built from
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Fen {
+    /// Pieces in the order written, rank 8 first and the a file first within each rank
     pub layout: Box<[[Option<SimplePiece>; 8]; 8]>,
+    /// Side to move
     pub turn: PieceColour,
+    /// Castling rights in the order of [`Self::CASTLING_SIDES`]
     pub castling_rights: [bool; 4],
+    /// File of the rook each castling right castles with, always that of the corner rook in
+    /// standard chess
+    pub castling_files: [u8; 4],
+    /// Square a pawn skipped over with a double push on the last move
     pub en_passant: Option<SimpleSquare>,
+    /// Halfmoves since the last capture or pawn move
     pub halfmove_clock: u32,
+    /// Number of the move being played, starting at 1 and going up after each black move
     pub fullmove_number: u32,
 }
 
 impl Fen {
+    /// Colour and side of each castling right, in the order of [`Self::castling_rights`]
+    pub const CASTLING_SIDES: [(PieceColour, CastlingSide); 4] = [
+        (PieceColour::White, CastlingSide::KingSide),
+        (PieceColour::White, CastlingSide::QueenSide),
+        (PieceColour::Black, CastlingSide::KingSide),
+        (PieceColour::Black, CastlingSide::QueenSide),
+    ];
+
+    /// Write out as a FEN string
     pub fn to_str(&self) -> String {
         const CASTLING_LETTERS: [char; 4] = ['K', 'Q', 'k', 'q'];
         let mut s = String::new();
@@ -163,10 +304,18 @@ impl Fen {
             PieceColour::White => s.push('w'),
         }
         s.push(' ');
-        for (i, castling_right) in self.castling_rights.iter().enumerate() {
-            if *castling_right {
-                s.push(CASTLING_LETTERS[i]);
+        // Chess960 castling is written in Shredder-FEN, naming the file of each rook
+        let shredder = self.needs_chess960();
+        for (i, (colour, _)) in Self::CASTLING_SIDES.into_iter().enumerate() {
+            if !self.castling_rights[i] {
+                continue;
             }
+            let letter = char::from(b'a' + self.castling_files[i]);
+            s.push(match colour {
+                _ if !shredder => CASTLING_LETTERS[i],
+                PieceColour::White => letter.to_ascii_uppercase(),
+                PieceColour::Black => letter,
+            });
         }
         if self.castling_rights.iter().all(|b| !b) {
             s.push('-');
@@ -182,8 +331,80 @@ impl Fen {
         s
     }
 
+    /// Check if castling needs Chess960 rules, because a rook with castling rights isn't in the
+    /// corner
+    ///
+    /// Kings away from the e file with rooks in the corners could be either variant, so are left
+    /// as standard chess.
+    pub fn needs_chess960(&self) -> bool {
+        (0..4).any(|i| self.castling_rights[i] && self.castling_files[i] != STANDARD_CASTLING_FILES[i])
+    }
+
+    /// Starting position of Chess960 numbered `number` in Scharnagl's numbering
+    ///
+    /// Standard chess is number 518. Every position has all four castling rights.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidChess960Position`] if `number` is over 959
+    pub fn chess960(number: u16) -> Result<Self, ChessError> {
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        if number >= 960 {
+            return Err(ChessError::InvalidChess960Position(number));
+        }
+        let mut back_rank = [None; 8];
+        let mut n = usize::from(number);
+        back_rank[n % 4 * 2 + 1] = Some(PieceKind::Bishop);
+        n /= 4;
+        back_rank[n % 4 * 2] = Some(PieceKind::Bishop);
+        n /= 4;
+        let empty = |back_rank: &[Option<PieceKind>; 8]| back_rank.iter().positions(Option::is_none).collect_vec();
+        back_rank[empty(&back_rank)[n % 6]] = Some(PieceKind::Queen);
+        n /= 6;
+        let (first, second) = KNIGHTS[n];
+        let remaining = empty(&back_rank);
+        back_rank[remaining[first]] = Some(PieceKind::Knight);
+        back_rank[remaining[second]] = Some(PieceKind::Knight);
+        let remaining = empty(&back_rank);
+        for (file, kind) in remaining
+            .iter()
+            .zip([PieceKind::Rook, PieceKind::King, PieceKind::Rook])
+        {
+            back_rank[*file] = Some(kind);
+        }
+        let rank =
+            |colour, kinds: [Option<PieceKind>; 8]| kinds.map(|kind| kind.map(|kind| SimplePiece::new(kind, colour)));
+        let pawns = [Some(PieceKind::Pawn); 8];
+        let mut layout = Box::new([[None; 8]; 8]);
+        layout[0] = rank(PieceColour::Black, back_rank);
+        layout[1] = rank(PieceColour::Black, pawns);
+        layout[6] = rank(PieceColour::White, pawns);
+        layout[7] = rank(PieceColour::White, back_rank);
+        let rooks = [remaining[2], remaining[0]].map(|file| file as u8);
+        Ok(Self {
+            layout,
+            turn: PieceColour::White,
+            castling_rights: [true; 4],
+            castling_files: [rooks[0], rooks[1], rooks[0], rooks[1]],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        })
+    }
+
+    /// Strategy for FENs with any layout, not necessarily a legal position
     #[cfg(test)]
-    pub fn strategy() -> impl Strategy<Value = Self> {
+    pub(crate) fn strategy() -> impl Strategy<Value = Self> {
         use proptest::{array::uniform4, collection::vec, option::of, prelude::any};
 
         let layout = vec(vec(of(SimplePiece::strategy()), 8), 8);
@@ -209,10 +430,19 @@ impl Fen {
                             .collect_array()
                             .unwrap(),
                     );
+                    // Rooks found by the letters `to_str` writes, so the FEN survives a round trip
+                    let letters: Vec<(PieceColour, CastlingLetter)> = castling_rights
+                        .iter()
+                        .zip(Self::CASTLING_SIDES)
+                        .filter(|(right, _)| **right)
+                        .map(|(_, (colour, side))| (colour, CastlingLetter::Side(side)))
+                        .collect();
+                    let (castling_rights, castling_files) = resolve_castling(&layout, &letters);
                     Self {
                         layout,
                         turn,
                         castling_rights,
+                        castling_files,
                         en_passant,
                         halfmove_clock,
                         fullmove_number,
@@ -222,6 +452,118 @@ impl Fen {
     }
 }
 
+/// Position read from a FEN, independent of any board representation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// Piece on each occupied square
+    pub pieces: BTreeMap<SimpleSquare, SimplePiece>,
+    /// Side to move
+    pub turn: PieceColour,
+    /// Sides each colour may still castle on
+    pub castling: BTreeSet<(PieceColour, CastlingSide)>,
+    /// Square a pawn skipped over with a double push on the last move
+    pub en_passant: Option<SimpleSquare>,
+    /// Halfmoves since the last capture or pawn move
+    pub halfmove_clock: u32,
+    /// Number of the move being played, starting at 1 and going up after each black move
+    pub fullmove_number: u32,
+}
+
+impl Position {
+    /// Write out as a FEN string
+    ///
+    /// Castling is always written as `KQkq`, so parsing the result gives back the same position.
+    pub fn to_fen(&self) -> String {
+        let mut layout = Box::new([[None; 8]; 8]);
+        for (square, piece) in &self.pieces {
+            layout[usize::from(7 - square.rank())][usize::from(square.file())] = Some(*piece);
+        }
+        Fen {
+            layout,
+            turn: self.turn,
+            castling_rights: Fen::CASTLING_SIDES.map(|right| self.castling.contains(&right)),
+            castling_files: STANDARD_CASTLING_FILES,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+        .to_str()
+    }
+
+    /// Strategy for positions with any pieces, not necessarily legal
+    #[cfg(test)]
+    pub(crate) fn strategy() -> impl Strategy<Value = Self> {
+        use proptest::{array::uniform4, collection::vec, option::of, prelude::any};
+
+        (
+            vec(of(SimplePiece::strategy()), 64),
+            PieceColour::strategy(),
+            uniform4(any::<bool>()),
+            of(SimpleSquare::strategy()),
+            any::<u32>(),
+            any::<u32>(),
+        )
+            .prop_map(|(squares, turn, rights, en_passant, halfmove_clock, fullmove_number)| {
+                let pieces = (0..64)
+                    .zip(squares)
+                    .filter_map(|(index, piece)| Some((SimpleSquare::from_index(index), piece?)))
+                    .collect();
+                let castling = Fen::CASTLING_SIDES
+                    .into_iter()
+                    .zip(rights)
+                    .filter_map(|(right, has)| has.then_some(right))
+                    .collect();
+                Self {
+                    pieces,
+                    turn,
+                    castling,
+                    en_passant,
+                    halfmove_clock,
+                    fullmove_number,
+                }
+            })
+    }
+}
+
+impl From<Fen> for Position {
+    fn from(value: Fen) -> Self {
+        let mut pieces = BTreeMap::new();
+        for (i, rank) in value.layout.iter().enumerate() {
+            for (j, piece) in rank.iter().enumerate() {
+                if let Some(piece) = piece {
+                    pieces.insert(SimpleSquare::new(j as u8, 7 - i as u8), *piece);
+                }
+            }
+        }
+        Self {
+            pieces,
+            turn: value.turn,
+            castling: Fen::CASTLING_SIDES
+                .into_iter()
+                .zip(value.castling_rights)
+                .filter_map(|(right, has)| has.then_some(right))
+                .collect(),
+            en_passant: value.en_passant,
+            halfmove_clock: value.halfmove_clock,
+            fullmove_number: value.fullmove_number,
+        }
+    }
+}
+
+/// Parse a whole FEN into a [`Position`]
+///
+/// Shredder-FEN castling letters are read as the side of the king their rook is on.
+///
+/// # Errors
+/// - [`crate::error::ChessError::InvalidFEN`] if `input` isn't a valid FEN, including ranks that
+///   don't add up to 8 squares, or has anything but whitespace after it
+pub fn parse_position(input: &str) -> Result<Position, ChessError> {
+    match fen(input) {
+        Ok((rest, fen)) if rest.trim().is_empty() => Ok(Position::from(fen)),
+        _ => Err(ChessError::InvalidFEN(input.to_string())),
+    }
+}
+
 fn rank_to_str(rank: &[Option<SimplePiece>; 8]) -> String {
     let mut s = String::new();
     let mut empty_squares = 0usize;
@@ -255,12 +597,137 @@ fn layout_to_str(layout: &[[Option<SimplePiece>; 8]; 8]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::board::piece_list::PieceListBoard;
     use crate::simple_types::SimplePiece;
-    use crate::traits::ChessPiece as _;
+    use crate::traits::{ChessMove as _, ChessPiece as _, LegalMoveGenerator as _};
     use proptest::array::uniform8;
     use proptest::collection::vec;
     use proptest::option::of;
     use proptest::proptest;
+    use std::collections::HashSet;
+
+    #[test]
+    fn underscore_separated() {
+        let spaced = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(
+            board_lenient(&spaced.replace(' ', "_")).unwrap(),
+            ("", fen(spaced).unwrap().1)
+        );
+        assert_eq!(board_lenient(spaced).unwrap(), ("", fen(spaced).unwrap().1));
+        assert_eq!(
+            board_lenient("8/8/8/8/8/8/8/8_w_-_-").unwrap().1.turn,
+            PieceColour::White
+        );
+    }
+
+    #[test]
+    fn rank_lengths() {
+        rank("p8").unwrap_err();
+        rank("44p").unwrap_err();
+        rank("7").unwrap_err();
+        rank("0p7").unwrap_err();
+        assert_eq!(rank("3p4").unwrap().1.iter().flatten().count(), 1);
+        fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap_err();
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2k5/8/8/8/8/5K2/8 b - - 57 90",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+        ] {
+            let (rest, parsed) = fen(s).unwrap();
+            assert_eq!(rest, "");
+            assert_eq!(parsed.to_str(), s);
+        }
+        let (_, parsed) = fen("8/2k5/8/8/8/8/5K2/8 b - - 57 90").unwrap();
+        assert_eq!(parsed.en_passant, None);
+        assert_eq!(parsed.castling_rights, [false; 4]);
+    }
+
+    #[test]
+    fn position_round_trip() {
+        for s in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r2q1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2Q1RK1 b - - 3 10",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+        ] {
+            assert_eq!(parse_position(s).unwrap().to_fen(), s);
+        }
+        let start = parse_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(start.pieces.len(), 32);
+        assert_eq!(
+            start.pieces[&SimpleSquare::new(4, 0)],
+            SimplePiece::new(PieceKind::King, PieceColour::White)
+        );
+        assert_eq!(start.castling.len(), 4);
+        let en_passant = parse_position("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        assert_eq!(en_passant.en_passant, Some(SimpleSquare::new(5, 5)));
+        let bare = parse_position("8/2k5/8/8/8/8/5K2/8 b - - 57 90").unwrap();
+        assert_eq!((bare.turn, bare.en_passant), (PieceColour::Black, None));
+        assert!(bare.castling.is_empty());
+        assert_eq!((bare.halfmove_clock, bare.fullmove_number), (57, 90));
+        for invalid in [
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 e4",
+        ] {
+            assert!(matches!(parse_position(invalid), Err(ChessError::InvalidFEN(_))));
+        }
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let (_, parsed) = fen("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1").unwrap();
+        assert_eq!(parsed.castling_rights, [true; 4]);
+        assert_eq!(parsed.castling_files, [6, 1, 6, 1]);
+        assert!(parsed.needs_chess960());
+        assert_eq!(
+            parsed.to_str(),
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1"
+        );
+        // X-FEN letters castle with the outermost rook
+        let (_, parsed) = fen("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w KQq - 0 1").unwrap();
+        assert_eq!(parsed.castling_rights, [true, true, false, true]);
+        assert_eq!(parsed.castling_files, [6, 1, 7, 1]);
+        assert_eq!(parsed.to_str(), "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBb - 0 1");
+        // Corner rooks are written as in standard chess whatever the letters
+        let (_, parsed) = fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1").unwrap();
+        assert_eq!(
+            parsed,
+            fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .unwrap()
+                .1
+        );
+        assert!(!parsed.needs_chess960());
+    }
+
+    #[test]
+    fn chess960_starting_positions() {
+        assert_eq!(
+            Fen::chess960(518).unwrap().to_str(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            Fen::chess960(0).unwrap().to_str(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+        assert_eq!(
+            Fen::chess960(959).unwrap().to_str(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1"
+        );
+        assert!(matches!(
+            Fen::chess960(960),
+            Err(ChessError::InvalidChess960Position(960))
+        ));
+        let back_ranks: HashSet<String> = (0..960).map(|number| Fen::chess960(number).unwrap().to_str()).collect();
+        assert_eq!(back_ranks.len(), 960);
+    }
 
     proptest! {
         #[test]
@@ -294,5 +761,43 @@ mod tests {
         fn fens(f in Fen::strategy()) {
             assert_eq!(fen(&f.to_str()).unwrap(), ("", f));
         }
+
+        #[test]
+        fn positions(p in Position::strategy()) {
+            let s = p.to_fen();
+            assert_eq!(parse_position(&s).unwrap(), p);
+            assert_eq!(parse_position(&s).unwrap().to_fen(), s);
+        }
+    }
+
+    #[test]
+    fn placement_only_defaults() {
+        let (rest, parsed) = placement("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR\n").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(parsed.turn, PieceColour::White);
+        assert_eq!(parsed.castling_rights, [false; 4]);
+        assert_eq!(
+            (parsed.en_passant, parsed.halfmove_clock, parsed.fullmove_number),
+            (None, 0, 1)
+        );
+        assert_eq!(
+            parsed.layout,
+            fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap()
+                .1
+                .layout
+        );
+        let parsed: Fen = placement_only("8/8/8/8/8/8/8/K6k").unwrap();
+        assert_eq!(parsed.turn, PieceColour::White);
+        placement_only::<Fen>("8/8/8/8/8/8/8/K6k w - - 0 1").unwrap_err();
+        let board: PieceListBoard = placement_only("8/8/8/8/8/8/8/K6k").unwrap();
+        assert_eq!(board.as_fen_str().unwrap(), "8/8/8/8/8/8/8/K6k w - - 0 1");
+        assert!(
+            board
+                .all_legal_moves()
+                .unwrap()
+                .into_iter()
+                .all(|chess_move| chess_move.src() == SimpleSquare::new(0, 0))
+        );
     }
 }
@@ -1,2 +1,5 @@
+//! Parsers for the notations boards, games and engines are read from
+
 pub mod fen;
-pub mod pgn;
+pub(crate) mod pgn;
+pub mod uci;
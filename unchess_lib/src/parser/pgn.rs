@@ -1,22 +1,35 @@
 //! Parsing for PGN notation
 #![allow(clippy::type_complexity)]
 
+use std::{iter, time::Duration};
+
 use nom::{
-    IResult, Parser as _,
+    Err, IResult, Parser as _,
     branch::alt,
-    bytes::complete::{is_not, tag, take_until},
-    character::complete::{char, digit1, multispace0, multispace1, one_of},
-    combinator::{map_res, opt, value},
-    multi::{many0, many1},
-    sequence::{delimited, pair, separated_pair},
+    bytes::complete::{is_not, tag, take_till, take_until},
+    character::complete::{anychar, char, digit1, i32, multispace0, multispace1, one_of, u8, u32, u64},
+    combinator::{map, map_res, opt, value},
+    error,
+    multi::{many0, many1, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair},
 };
 
 use crate::{
-    enums::{AmbiguousMove, CastlingSide, MoveAction, PieceKind},
+    comment::{Arrow, Evaluation, Highlight, MarkColour},
+    enums::{AmbiguousMove, CastlingSide, GameResult, MoveAction, PieceKind},
+    game::GameMove,
     notation::{char_to_file, char_to_rank},
     simple_types::{SimpleMove, SimpleSquare},
 };
 
+/// Strip a leading UTF-8 byte order mark and convert CRLF line endings to LF
+///
+/// Applied before parsing so comments and tag values read the same whichever line endings a file
+/// was saved with.
+pub fn normalise(input: &str) -> String {
+    input.strip_prefix('\u{feff}').unwrap_or(input).replace("\r\n", "\n")
+}
+
 fn rank(input: &str) -> IResult<&str, u8> {
     map_res(one_of("12345678"), char_to_rank).parse(input)
 }
@@ -77,11 +90,18 @@ fn action(input: &str) -> IResult<&str, MoveAction> {
     .parse(input)
 }
 
+/// Explicit en passant suffix written by some older software, e.g. `exd6 e.p.`
+fn en_passant_suffix(input: &str) -> IResult<&str, ()> {
+    value((), preceded(multispace0, tag("e.p."))).parse(input)
+}
+
 fn normal_move(input: &str) -> IResult<&str, AmbiguousMove> {
     let (input, piece_kind) = piece(input)?;
     let (input, (src_file, src_rank, takes, dest)) = disambiguated_move(input)?;
     let (input, promote_to) = opt(promotion).parse(input)?;
     let (input, action) = opt(action).parse(input)?;
+    // Ignored since en passant is unambiguous from the board
+    let (input, _) = opt(en_passant_suffix).parse(input)?;
     Ok((
         input,
         AmbiguousMove::Normal {
@@ -96,24 +116,23 @@ fn normal_move(input: &str) -> IResult<&str, AmbiguousMove> {
     ))
 }
 
+fn castle(input: &str) -> IResult<&str, AmbiguousMove> {
+    let (input, side) = alt((
+        value(CastlingSide::QueenSide, tag("O-O-O")),
+        value(CastlingSide::KingSide, tag("O-O")),
+    ))
+    .parse(input)?;
+    let (input, action) = opt(action).parse(input)?;
+    Ok((input, AmbiguousMove::Castle { side, action }))
+}
+
+fn null_move(input: &str) -> IResult<&str, AmbiguousMove> {
+    value(AmbiguousMove::Null, alt((tag("--"), tag("Z0")))).parse(input)
+}
+
 /// Parse PGN standard chess move
 pub fn chess_move(input: &str) -> IResult<&str, AmbiguousMove> {
-    alt((
-        normal_move,
-        value(
-            AmbiguousMove::Castle {
-                side: CastlingSide::QueenSide,
-            },
-            tag("O-O-O"),
-        ),
-        value(
-            AmbiguousMove::Castle {
-                side: CastlingSide::KingSide,
-            },
-            tag("O-O"),
-        ),
-    ))
-    .parse(input)
+    alt((normal_move, castle, null_move)).parse(input)
 }
 
 fn eol_comment(input: &str) -> IResult<&str, ()> {
@@ -124,6 +143,10 @@ fn eol_comment(input: &str) -> IResult<&str, ()> {
     .parse(input)
 }
 
+/// Brace comment, PGN doesn't allow nesting so the comment ends at the first `}`
+///
+/// Any text following that `}` is parsed as movetext, even if the comment was clearly intended to
+/// continue (e.g. `{ text with } weirdness}`).
 fn enclosed_comment(input: &str) -> IResult<&str, ()> {
     value(
         (), // Output is thrown away.
@@ -132,9 +155,106 @@ fn enclosed_comment(input: &str) -> IResult<&str, ()> {
     .parse(input)
 }
 
+/// Comment command such as `[%clk 0:03:00]`, returned as `(name, args)`
+fn comment_command(input: &str) -> IResult<&str, (&str, &str)> {
+    delimited(
+        tag("[%"),
+        separated_pair(
+            take_till(|c: char| c.is_whitespace() || c == ']'),
+            multispace0,
+            map(opt(is_not("]")), |args: Option<&str>| {
+                args.unwrap_or_default().trim_end()
+            }),
+        ),
+        char(']'),
+    )
+    .parse(input)
+}
+
+/// All comment commands in a comment's text as `(name, args)`, skipping any other text
+pub fn comment_commands(comment: &str) -> Vec<(&str, &str)> {
+    many0(alt((map(comment_command, Some), value(None, anychar))))
+        .parse(comment)
+        .map(|(_, commands)| commands.into_iter().flatten().collect())
+        .unwrap_or_default()
+}
+
+/// Digits after a decimal point, scaled so there are exactly `places` of them
+fn fraction(input: &str, places: usize) -> IResult<&str, u32> {
+    let (input, digits) = preceded(char('.'), digit1).parse(input)?;
+    let scaled: String = digits.chars().chain(iter::repeat('0')).take(places).collect();
+    Ok((input, scaled.parse().unwrap_or_default()))
+}
+
+/// Clock time of the form `h:mm:ss` with optional fractional seconds
+pub fn clock(input: &str) -> IResult<&str, Duration> {
+    let (input, (hours, _, minutes, _, seconds)) = (u64, char(':'), u64, char(':'), u64).parse(input)?;
+    let (input, nanos) = opt(|s| fraction(s, 9)).parse(input)?;
+    let seconds = hours
+        .saturating_mul(3600)
+        .saturating_add(minutes.saturating_mul(60))
+        .saturating_add(seconds);
+    Ok((input, Duration::new(seconds, nanos.unwrap_or_default())))
+}
+
+/// Evaluation in pawns like `-0.35` or mate like `#3`, optionally followed by `,depth`
+pub fn evaluation(input: &str) -> IResult<&str, Evaluation> {
+    let pawns = map_res(
+        (opt(one_of("+-")), u32, opt(|s| fraction(s, 2))),
+        |(sign, pawns, centipawns)| {
+            let centipawns = i64::from(pawns) * 100 + i64::from(centipawns.unwrap_or_default());
+            i32::try_from(if sign == Some('-') { -centipawns } else { centipawns })
+        },
+    );
+    let (input, evaluation) = alt((
+        map(preceded(char('#'), i32), Evaluation::Mate),
+        map(pawns, Evaluation::Centipawns),
+    ))
+    .parse(input)?;
+    let (input, _) = opt(preceded(char(','), digit1)).parse(input)?;
+    Ok((input, evaluation))
+}
+
+fn mark_colour(input: &str) -> IResult<&str, MarkColour> {
+    alt((
+        value(MarkColour::Red, char('R')),
+        value(MarkColour::Green, char('G')),
+        value(MarkColour::Blue, char('B')),
+        value(MarkColour::Yellow, char('Y')),
+    ))
+    .parse(input)
+}
+
+/// Comma separated arrows like `Ge2e4,Rd1d8`
+pub fn arrows(input: &str) -> IResult<&str, Vec<Arrow>> {
+    separated_list1(
+        (multispace0, char(','), multispace0),
+        map((mark_colour, square, square), |(colour, from, to)| Arrow {
+            colour,
+            from,
+            to,
+        }),
+    )
+    .parse(input)
+}
+
+/// Comma separated highlighted squares like `Rd4,Ge5`
+pub fn highlights(input: &str) -> IResult<&str, Vec<Highlight>> {
+    separated_list1(
+        (multispace0, char(','), multispace0),
+        map((mark_colour, square), |(colour, square)| Highlight { colour, square }),
+    )
+    .parse(input)
+}
+
 fn tag_pair(input: &str) -> IResult<&str, (&str, &str)> {
     let (input, pair) = delimited(char('['), is_not("]"), char(']')).parse(input)?;
-    let (_, (key, value)) = separated_pair(is_not(" "), multispace0, is_not("]")).parse(pair)?;
+    let (_, (key, value)) = separated_pair(
+        is_not(" "),
+        multispace0,
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+    )
+    .parse(pair)?;
     Ok((input, (key, value)))
 }
 
@@ -144,17 +264,159 @@ fn move_number(input: &str) -> IResult<&str, ()> {
     Ok((input, ()))
 }
 
+/// Move number indicator like `12.` or `12...`, as the ply of the move it is followed by
+fn numbered_ply(input: &str) -> IResult<&str, usize> {
+    let (input, number) = u32(input)?;
+    let (input, dots) = many1(char('.')).parse(input)?;
+    let ply = 2 * (number as usize).saturating_sub(1) + usize::from(dots.len() >= 3);
+    Ok((input, ply))
+}
+
 fn move_without_comments(input: &str) -> IResult<&str, AmbiguousMove> {
     let (input, _) = many0(alt((
         enclosed_comment,
         eol_comment,
         |s| Ok((multispace1(s)?.0, ())),
         move_number,
+        value((), nag),
+        value((), suffix_annotation),
     )))
     .parse(input)?;
     chess_move(input)
 }
 
+fn comment_text(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(char('{'), take_until("}"), char('}')),
+        preceded(char(';'), take_till(|c| c == '\n' || c == '\r')),
+    ))
+    .parse(input)
+}
+
+fn nag(input: &str) -> IResult<&str, u8> {
+    preceded(char('$'), u8).parse(input)
+}
+
+/// Move suffix annotation like `!?`, as the NAG it stands for
+fn suffix_annotation(input: &str) -> IResult<&str, u8> {
+    alt((
+        value(3, tag("!!")),
+        value(4, tag("??")),
+        value(5, tag("!?")),
+        value(6, tag("?!")),
+        value(1, tag("!")),
+        value(2, tag("?")),
+    ))
+    .parse(input)
+}
+
+fn result(input: &str) -> IResult<&str, GameResult> {
+    alt((
+        value(GameResult::WhiteWin, tag("1-0")),
+        value(GameResult::BlackWin, tag("0-1")),
+        value(GameResult::Draw, tag("1/2-1/2")),
+        value(GameResult::Ongoing, tag("*")),
+    ))
+    .parse(input)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MovetextToken<'a> {
+    Move(AmbiguousMove),
+    Comment(&'a str),
+    Nag(u8),
+    Variation(Vec<GameMove>),
+    MoveNumber(usize),
+}
+
+fn movetext_token(
+    input: &str,
+    depth: usize,
+    max_depth: usize,
+    variation_ply: Option<usize>,
+) -> IResult<&str, MovetextToken<'_>> {
+    let (input, _) = multispace0(input)?;
+    if input.starts_with('{') && !input.contains('}') {
+        return Err(Err::Failure(error::Error::new(input, error::ErrorKind::TakeUntil)));
+    }
+    if let Ok((input, _)) = char::<&str, error::Error<&str>>('(')(input) {
+        if depth >= max_depth {
+            return Err(Err::Failure(error::Error::new(input, error::ErrorKind::TooLarge)));
+        }
+        let (input, moves) = movetext(input, depth + 1, max_depth, variation_ply)?;
+        let (input, _) = preceded(multispace0, char(')')).parse(input)?;
+        return Ok((input, MovetextToken::Variation(moves)));
+    }
+    alt((
+        map(chess_move, MovetextToken::Move),
+        map(comment_text, MovetextToken::Comment),
+        map(alt((nag, suffix_annotation)), MovetextToken::Nag),
+        map(numbered_ply, MovetextToken::MoveNumber),
+    ))
+    .parse(input)
+}
+
+/// Parse moves along with their annotations and variations at nesting `depth`
+///
+/// `first_ply` is the ply of the first move if known, otherwise it is taken from the first move
+/// number. Fails with [`error::ErrorKind::TooLarge`] if variations are nested deeper than
+/// `max_depth`, with [`error::ErrorKind::TakeUntil`] if a comment is never closed and with
+/// [`error::ErrorKind::Verify`] if a move number doesn't match the move it is followed by.
+fn movetext(mut input: &str, depth: usize, max_depth: usize, first_ply: Option<usize>) -> IResult<&str, Vec<GameMove>> {
+    let mut moves: Vec<GameMove> = vec![];
+    let mut ply = first_ply;
+    loop {
+        let variation_ply = ply.and_then(|ply| ply.checked_sub(1));
+        let token = match movetext_token(input, depth, max_depth, variation_ply) {
+            Ok((rest, token)) => {
+                if let MovetextToken::MoveNumber(number_ply) = token
+                    && ply.is_some_and(|ply| ply != number_ply)
+                {
+                    return Err(Err::Failure(error::Error::new(
+                        input.trim_start(),
+                        error::ErrorKind::Verify,
+                    )));
+                }
+                input = rest;
+                token
+            }
+            Err(Err::Error(_)) => return Ok((input, moves)),
+            Err(e) => return Err(e),
+        };
+        match (token, moves.last_mut()) {
+            (MovetextToken::MoveNumber(number_ply), _) => ply = Some(number_ply),
+            (MovetextToken::Move(chess_move), _) => {
+                moves.push(GameMove::new(chess_move));
+                ply = ply.map(|ply| ply + 1);
+            }
+            (MovetextToken::Comment(comment), Some(last)) => last.append_comment(comment.trim()),
+            (MovetextToken::Nag(nag), Some(last)) => last.add_nag(nag),
+            (MovetextToken::Variation(variation), Some(last)) => last.add_variation(variation),
+            (MovetextToken::Comment(_) | MovetextToken::Nag(_) | MovetextToken::Variation(_), None) => (),
+        }
+    }
+}
+
+/// Parse PGN game keeping comments, NAGs and variations attached to the moves they follow
+///
+/// Comments before the first move are discarded. Variations nested deeper than
+/// `max_variation_depth` fail with [`error::ErrorKind::TooLarge`], a malformed tag pair fails
+/// with [`error::ErrorKind::Tag`] and badly formed movetext fails as described in [`movetext`].
+pub fn game(
+    input: &str,
+    max_variation_depth: usize,
+) -> IResult<&str, (Vec<(&str, &str)>, Vec<GameMove>, Option<GameResult>)> {
+    let (input, tag_pairs) = many0(preceded(multispace0, tag_pair)).parse(input)?;
+    let (input, _) = multispace0(input)?;
+    if input.starts_with('[') {
+        return Err(Err::Failure(error::Error::new(input, error::ErrorKind::Tag)));
+    }
+    let (input, moves) = movetext(input, 0, max_variation_depth, None)?;
+    let (input, _) = multispace0(input)?;
+    let (input, result) = opt(result).parse(input)?;
+    Ok((input, (tag_pairs, moves, result)))
+}
+
 #[allow(clippy::type_complexity)]
 pub fn pgn(input: &str) -> IResult<&str, (Vec<(&str, &str)>, Vec<AmbiguousMove>)> {
     let (input, tag_pairs) = many0(|s| {
@@ -174,9 +436,91 @@ mod tests {
     };
 
     use super::*;
+    use crate::notation::comment_to_pgn;
     use proptest::prelude::*;
     use std::fmt::Write as _;
 
+    #[test]
+    fn comment_ends_at_first_brace() {
+        let (_, (_, moves)) = pgn("1. e4 { text with } e5 2. Nf3 {more} Nc6").unwrap();
+        assert_eq!(
+            moves,
+            ["e4", "e5", "Nf3", "Nc6"].map(|s| chess_move(s).unwrap().1).to_vec()
+        );
+    }
+
+    #[test]
+    fn nag_before_result() {
+        let (_, (_, moves)) = pgn("1. e4 $1 e5 $10 1/2-1/2").unwrap();
+        assert_eq!(moves, ["e4", "e5"].map(|s| chess_move(s).unwrap().1).to_vec());
+        let (rest, (_, moves, result)) = game("1. e4 e5 $10 1/2-1/2", 1).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(result, Some(GameResult::Draw));
+        assert_eq!(moves[1].nags(), [10]);
+    }
+
+    #[test]
+    fn result_glued_to_move() {
+        let (rest, (_, moves)) = pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7#1-0").unwrap();
+        assert_eq!(moves.last(), Some(&chess_move("Qxf7#").unwrap().1));
+        assert_eq!(result(rest), Ok(("", GameResult::WhiteWin)));
+        let (rest, (_, moves, result)) = game("1. f3 e5 2. g4 Qh4#0-1", 1).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(result, Some(GameResult::BlackWin));
+        assert_eq!(moves.last().unwrap().chess_move(), chess_move("Qh4#").unwrap().1);
+    }
+
+    #[test]
+    fn castle_with_action() {
+        assert_eq!(
+            chess_move("O-O#"),
+            Ok((
+                "",
+                AmbiguousMove::Castle {
+                    side: CastlingSide::KingSide,
+                    action: Some(MoveAction::Checkmate),
+                }
+            ))
+        );
+        assert_eq!(chess_move("O-O-O+").unwrap().1.as_pgn_str(), "O-O-O+");
+    }
+
+    #[test]
+    fn annotated_castles() {
+        let (rest, (_, moves, _)) =
+            game("1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. O-O! $1 {Castles} Be7 5. d4?! O-O", 1).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(moves[6].nags(), [1, 1]);
+        assert_eq!(moves[6].comment(), Some("Castles"));
+        assert_eq!(moves[8].nags(), [6]);
+        let (_, (_, moves, _)) = game("20. O-O-O#!! 1-0", 1).unwrap();
+        assert_eq!(
+            moves[0].chess_move(),
+            AmbiguousMove::Castle {
+                side: CastlingSide::QueenSide,
+                action: Some(MoveAction::Checkmate),
+            }
+        );
+        assert_eq!(moves[0].nags(), [3]);
+        let (_, (_, moves)) = pgn("1. e4! e5?? 2. O-O+ ?").unwrap();
+        assert_eq!(moves.len(), 3);
+    }
+
+    #[test]
+    fn en_passant_suffix_ignored() {
+        assert_eq!(chess_move("exd6 e.p."), Ok(("", chess_move("exd6").unwrap().1)));
+        assert_eq!(chess_move("exd6+ e.p."), Ok(("", chess_move("exd6+").unwrap().1)));
+        let (_, (_, moves)) = pgn("1. e4 a6 2. e5 d5 3. exd6 e.p. e6").unwrap();
+        assert_eq!(moves.len(), 6);
+    }
+
+    #[test]
+    fn written_comment_never_closes_early() {
+        let s = format!("1. e4 {} e5", comment_to_pgn("text with } weirdness"));
+        assert_eq!(s, "1. e4 {text with  weirdness} e5");
+        assert_eq!(pgn(&s).unwrap().1.1.len(), 2);
+    }
+
     proptest! {
         #[test]
         fn good_squares(file in 0..=7u8, rank in 0..=7u8) {
@@ -221,6 +565,7 @@ mod tests {
         #[test]
         fn all_ambiguous_moves(amb_move in AmbiguousMove::strategy()) {
             assert_eq!(chess_move(&amb_move.as_pgn_str()).unwrap(), ("", amb_move));
+            assert_eq!(AmbiguousMove::try_from(amb_move.as_pgn_str().as_str()).unwrap(), amb_move);
         }
 
         #[test]
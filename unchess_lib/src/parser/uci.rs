@@ -0,0 +1,192 @@
+//! Parsing for UCI long algebraic notation and engine output
+
+use std::time::Duration;
+
+use nom::{
+    IResult, Parser as _,
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::{i32, one_of, space1, u32, u64},
+    combinator::{all_consuming, map, map_res, opt, rest, value, verify},
+    multi::{many0, many1},
+    sequence::preceded,
+};
+
+use crate::{
+    comment::Evaluation, enums::PieceKind, error::ChessError, parser::pgn::square, simple_types::SimpleMove, uci::Info,
+};
+
+fn promotion(input: &str) -> IResult<&str, PieceKind> {
+    map_res(one_of("qrbn"), |c| PieceKind::try_from(c.to_ascii_uppercase())).parse(input)
+}
+
+/// Move written as source and destination squares with an optional lowercase promotion, e.g.
+/// `e7e8q`
+fn long_algebraic(input: &str) -> IResult<&str, SimpleMove> {
+    let (input, (src, dest)) = verify((square, square), |(src, dest)| src != dest).parse(input)?;
+    let (input, promote_to) = opt(promotion).parse(input)?;
+    Ok((input, SimpleMove::new(src, dest, promote_to)))
+}
+
+/// Parse a move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`
+///
+/// Castling is written as the king's move, e.g. `e1g1`.
+///
+/// # Errors
+/// - [`crate::error::ChessError::InvalidUCI`] if `input` isn't two different squares, optionally
+///   followed by a promotion to a queen, rook, bishop or knight
+pub fn uci_move(input: &str) -> Result<SimpleMove, ChessError> {
+    match all_consuming(long_algebraic).parse(input) {
+        Ok((_, chess_move)) => Ok(chess_move),
+        Err(_) => Err(ChessError::InvalidUCI(input.to_string())),
+    }
+}
+
+/// Field of an engine `info` line
+#[derive(Clone)]
+enum InfoField<'a> {
+    Depth(u32),
+    MultiPv(u32),
+    Score(Evaluation),
+    Nodes(u64),
+    Time(Duration),
+    Pv(Vec<SimpleMove>),
+    String(&'a str),
+    Unknown,
+}
+
+/// Score as `cp` in centipawns or `mate` in moves, ignoring whether it is only a bound
+fn score(input: &str) -> IResult<&str, Evaluation> {
+    let (input, evaluation) = alt((
+        map(preceded((tag("cp"), space1), i32), Evaluation::Centipawns),
+        map(preceded((tag("mate"), space1), i32), Evaluation::Mate),
+    ))
+    .parse(input)?;
+    let (input, _) = opt(preceded(space1, alt((tag("lowerbound"), tag("upperbound"))))).parse(input)?;
+    Ok((input, evaluation))
+}
+
+/// Field of an `info` line, or a single unrecognised token
+fn info_field(input: &str) -> IResult<&str, InfoField<'_>> {
+    alt((
+        map(preceded((tag("depth"), space1), u32), InfoField::Depth),
+        map(preceded((tag("multipv"), space1), u32), InfoField::MultiPv),
+        map(preceded((tag("score"), space1), score), InfoField::Score),
+        map(preceded((tag("nodes"), space1), u64), InfoField::Nodes),
+        map(preceded((tag("time"), space1), u64), |millis| {
+            InfoField::Time(Duration::from_millis(millis))
+        }),
+        map(
+            preceded(tag("pv"), many1(preceded(space1, long_algebraic))),
+            InfoField::Pv,
+        ),
+        map(preceded((tag("string"), space1), rest), InfoField::String),
+        value(InfoField::Unknown, take_till1(|c: char| c.is_ascii_whitespace())),
+    ))
+    .parse(input)
+}
+
+/// Engine `info` line, e.g. `info depth 12 score cp 31 nodes 48211 pv e2e4 e7e5`
+pub(crate) fn info(input: &str) -> IResult<&str, Info> {
+    let (input, fields) = preceded(tag("info"), many0(preceded(space1, info_field))).parse(input)?;
+    let mut info = Info::default();
+    for field in fields {
+        match field {
+            InfoField::Depth(depth) => info.depth = Some(depth),
+            InfoField::MultiPv(multipv) => info.multipv = Some(multipv),
+            InfoField::Score(score) => info.score = Some(score),
+            InfoField::Nodes(nodes) => info.nodes = Some(nodes),
+            InfoField::Time(time) => info.time = Some(time),
+            InfoField::Pv(pv) => info.pv = pv,
+            InfoField::String(string) => info.string = Some(string.to_owned()),
+            InfoField::Unknown => (),
+        }
+    }
+    Ok((input, info))
+}
+
+/// Engine `bestmove` line, returning the best move and the move to ponder on
+///
+/// The best move is none if the engine had no legal move to play, written as `(none)` or `0000`.
+pub(crate) fn best_move(input: &str) -> IResult<&str, (Option<SimpleMove>, Option<SimpleMove>)> {
+    let (input, best) = preceded(
+        (tag("bestmove"), space1),
+        alt((
+            map(long_algebraic, Some),
+            value(None, alt((tag("(none)"), tag("0000")))),
+        )),
+    )
+    .parse(input)?;
+    let (input, ponder) = opt(preceded((space1, tag("ponder"), space1), long_algebraic)).parse(input)?;
+    Ok((input, (best, ponder)))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::proptest;
+
+    use super::*;
+    use crate::simple_types::SimpleSquare;
+    use crate::traits::ChessMove as _;
+
+    #[test]
+    fn promotions() {
+        let chess_move = uci_move("e7e8q").unwrap();
+        assert_eq!(chess_move.promote_to(), Some(PieceKind::Queen));
+        assert_eq!(chess_move.src(), SimpleSquare::new(4, 6));
+        assert_eq!(chess_move.dest(), SimpleSquare::new(4, 7));
+        assert_eq!(uci_move("g1f3").unwrap().promote_to(), None);
+        assert_eq!(long_algebraic("e7e8k").unwrap().0, "k");
+    }
+
+    #[test]
+    fn invalid_moves() {
+        for invalid in [
+            "e2", "e2e4q5", "e2e9", "i2e4", "e2e2", "E2E4", "e7e8k", "e7e8p", "e7e8Q",
+        ] {
+            assert!(matches!(uci_move(invalid), Err(ChessError::InvalidUCI(s)) if s == invalid));
+        }
+    }
+
+    #[test]
+    fn info_lines() {
+        let (_, line) = info(
+            "info depth 12 seldepth 18 multipv 1 score cp -31 upperbound nodes 48211 nps 960000 time 50 pv e2e4 e7e5",
+        )
+        .unwrap();
+        assert_eq!(line.depth, Some(12));
+        assert_eq!(line.multipv, Some(1));
+        assert_eq!(line.score, Some(Evaluation::Centipawns(-31)));
+        assert_eq!(line.nodes, Some(48211));
+        assert_eq!(line.time, Some(Duration::from_millis(50)));
+        assert_eq!(line.pv, [uci_move("e2e4").unwrap(), uci_move("e7e5").unwrap()]);
+        let (_, line) = info("info depth 3 currmove g1f3 currmovenumber 2 score mate -2").unwrap();
+        assert_eq!(line.score, Some(Evaluation::Mate(-2)));
+        assert!(line.pv.is_empty());
+        assert_eq!(
+            info("info string NNUE enabled").unwrap().1.string.as_deref(),
+            Some("NNUE enabled")
+        );
+        info("bestmove e2e4").unwrap_err();
+    }
+
+    #[test]
+    fn best_moves() {
+        let e2e4 = uci_move("e2e4").unwrap();
+        let e7e5 = uci_move("e7e5").unwrap();
+        assert_eq!(
+            best_move("bestmove e2e4 ponder e7e5").unwrap().1,
+            (Some(e2e4), Some(e7e5))
+        );
+        assert_eq!(best_move("bestmove e2e4").unwrap().1, (Some(e2e4), None));
+        assert_eq!(best_move("bestmove (none)").unwrap().1, (None, None));
+        assert_eq!(best_move("bestmove 0000").unwrap().1, (None, None));
+    }
+
+    proptest! {
+        #[test]
+        fn all_moves(chess_move in SimpleMove::strategy()) {
+            assert_eq!(uci_move(&chess_move.to_uci_str()).unwrap(), chess_move);
+        }
+    }
+}
@@ -4,13 +4,17 @@
 //! bittwiddling versions, so they are used for error types and such.
 use core::fmt;
 
+#[cfg(feature = "serde")]
+use nom::{Parser as _, combinator::all_consuming};
 #[cfg(test)]
 use proptest::prelude::Strategy;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
 
 use crate::enums::PieceColour;
 use crate::enums::PieceKind;
 use crate::error::ChessError;
-use crate::parser::pgn;
+use crate::parser::{pgn, uci};
 use crate::traits::ChessMove;
 use crate::traits::ChessPiece;
 use crate::traits::ChessSquare;
@@ -41,6 +45,38 @@ impl fmt::Display for SimpleSquare {
     }
 }
 
+/// Serialized in algebraic notation, e.g. `"e4"`
+#[cfg(feature = "serde")]
+impl Serialize for SimpleSquare {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SimpleSquare {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match all_consuming(pgn::square).parse(&s) {
+            Ok((_, square)) => Ok(square),
+            Err(_) => Err(D::Error::custom(format!("invalid square {s}"))),
+        }
+    }
+}
+
+impl TryFrom<u8> for SimpleSquare {
+    type Error = ChessError;
+
+    /// Square at `index`, counting from 0 for a1 to 63 for h8 as in [`SimpleSquare::to_index`]
+    fn try_from(index: u8) -> Result<Self, ChessError> {
+        if index < 64 {
+            Ok(Self::from_index(index))
+        } else {
+            Err(ChessError::OutOfBounds(index))
+        }
+    }
+}
+
 impl SimpleSquare {
     /// Chess square at `file` and `rank`
     ///
@@ -64,6 +100,29 @@ impl SimpleSquare {
         }
     }
 
+    /// Index of the square from 0 for a1 to 63 for h8, counting along each rank in turn
+    pub fn to_index(&self) -> u8 {
+        self.rank * 8 + self.file
+    }
+
+    /// Square at `index`, the inverse of [`SimpleSquare::to_index`]
+    ///
+    /// # Panics
+    /// Panics if index is not between 0-63 inclusive
+    pub fn from_index(index: u8) -> Self {
+        assert!(index < 64, "Index must be between 0-63 inclusive, {index} > 63");
+        Self::new(index % 8, index / 8)
+    }
+
+    /// Colour of the square on the board, a1 being dark
+    pub fn colour(&self) -> PieceColour {
+        if (self.file + self.rank).is_multiple_of(2) {
+            PieceColour::Black
+        } else {
+            PieceColour::White
+        }
+    }
+
     /// Check if square is starting rank for pawns of `colour`
     pub fn is_starting_rank(&self, colour: PieceColour) -> bool {
         match colour {
@@ -115,6 +174,22 @@ impl fmt::Display for SimpleMove {
     }
 }
 
+/// Serialized in UCI long algebraic notation, e.g. `"e7e8q"`
+#[cfg(feature = "serde")]
+impl Serialize for SimpleMove {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_uci_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SimpleMove {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::from_uci_str(&s).map_err(D::Error::custom)
+    }
+}
+
 impl SimpleMove {
     /// Chess move from `src` to `dest`
     ///
@@ -137,6 +212,16 @@ impl SimpleMove {
         }
     }
 
+    /// Create move from UCI long algebraic notation, e.g. `e2e4` or `e7e8q`
+    ///
+    /// Castling is written as the king's move, e.g. `e1g1`.
+    ///
+    /// # Errors
+    /// [crate::error::ChessError::InvalidUCI] if `uci` is invalid
+    pub fn from_uci_str(uci: &str) -> Result<Self, ChessError> {
+        uci::uci_move(uci)
+    }
+
     /// Strategy for property testing moves
     ///
     /// NOTE: to avoid generating invalid moves to and from the same square, if they are generated
@@ -152,7 +237,7 @@ impl SimpleMove {
             if src != dest {
                 Self::new(src, dest, promote_to)
             } else {
-                Self::from_pgn_str("a1h8").unwrap()
+                Self::new(SimpleSquare::new(0, 0), SimpleSquare::new(7, 7), promote_to)
             }
         })
     }
@@ -201,3 +286,92 @@ impl From<SimplePiece> for char {
         }
     }
 }
+
+/// Serialized as its FEN char, e.g. `"n"` for a black knight
+#[cfg(feature = "serde")]
+impl Serialize for SimplePiece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(char::from(*self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SimplePiece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let c = char::deserialize(deserializer)?;
+        let colour = if c.is_ascii_uppercase() {
+            PieceColour::White
+        } else {
+            PieceColour::Black
+        };
+        let kind = PieceKind::try_from(c.to_ascii_uppercase()).map_err(D::Error::custom)?;
+        Ok(Self::new(kind, colour))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_try_from_index() {
+        assert_eq!(
+            SimpleSquare::try_from(63).unwrap(),
+            SimpleSquare::from_pgn_str("h8").unwrap()
+        );
+        assert_eq!(SimpleSquare::try_from(0).unwrap(), SimpleSquare::new(0, 0));
+        assert!(matches!(SimpleSquare::try_from(64), Err(ChessError::OutOfBounds(64))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::enums::{AmbiguousMove, CastlingSide, MoveAction, PieceKind};
+
+        let square = SimpleSquare::from_pgn_str("e4").unwrap();
+        assert_eq!(serde_json::to_string(&square).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::from_str::<SimpleSquare>("\"e4\"").unwrap(), square);
+        serde_json::from_str::<SimpleSquare>("\"e9\"").unwrap_err();
+        for chess_move in [
+            AmbiguousMove::Castle {
+                side: CastlingSide::QueenSide,
+                action: Some(MoveAction::Check),
+            },
+            AmbiguousMove::Normal {
+                piece_kind: PieceKind::Knight,
+                src_file: Some(1),
+                src_rank: None,
+                takes: true,
+                dest: square,
+                promote_to: None,
+                action: None,
+            },
+        ] {
+            let json = serde_json::to_string(&chess_move).unwrap();
+            assert_eq!(json, format!("\"{chess_move}\""));
+            assert_eq!(serde_json::from_str::<AmbiguousMove>(&json).unwrap(), chess_move);
+        }
+        let promotion = SimpleMove::from_uci_str("e7e8q").unwrap();
+        assert_eq!(serde_json::to_string(&promotion).unwrap(), "\"e7e8q\"");
+        assert_eq!(serde_json::from_str::<SimpleMove>("\"e7e8q\"").unwrap(), promotion);
+        let knight = SimplePiece::new(PieceKind::Knight, PieceColour::Black);
+        assert_eq!(serde_json::to_string(&knight).unwrap(), "\"n\"");
+        assert_eq!(serde_json::from_str::<SimplePiece>("\"n\"").unwrap(), knight);
+        serde_json::from_str::<SimplePiece>("\"x\"").unwrap_err();
+    }
+
+    #[test]
+    fn uci_moves() {
+        let chess_move = SimpleMove::from_uci_str("e7e8n").unwrap();
+        assert_eq!(chess_move, SimpleMove::from_pgn_str("e7e8=N").unwrap());
+        assert_eq!(chess_move.to_uci_str(), "e7e8n");
+        assert!(matches!(
+            SimpleMove::from_uci_str("e7e8k"),
+            Err(ChessError::InvalidUCI(_))
+        ));
+        assert!(matches!(
+            SimpleMove::from_uci_str("e1g1 "),
+            Err(ChessError::InvalidUCI(_))
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+//! Sets of squares
+//!
+//! A [`SquareSet`] is a u64 with one bit per square, as used by bitboards, so unions and
+//! intersections of sets are single instructions.
+
+use std::ops::{BitAnd, BitOr, Not};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::simple_types::SimpleSquare;
+
+/// Set of squares on the chess board
+///
+/// Bit [`SimpleSquare::to_index`] is set for each square in the set, so bit 0 is a1 and bit 63 is
+/// h8. Serialized as that u64.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SquareSet(u64);
+
+impl SquareSet {
+    /// Set containing no squares
+    pub const EMPTY: Self = Self(0);
+
+    /// Set containing every square
+    pub const FULL: Self = Self(u64::MAX);
+
+    /// Set from its bitboard representation
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Bitboard representation of the set
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Return true if `square` is in the set
+    pub fn contains(self, square: SimpleSquare) -> bool {
+        self.0 & (1 << square.to_index()) != 0
+    }
+
+    /// Add `square` to the set
+    pub fn insert(&mut self, square: SimpleSquare) {
+        self.0 |= 1 << square.to_index();
+    }
+
+    /// Remove `square` from the set
+    pub fn remove(&mut self, square: SimpleSquare) {
+        self.0 &= !(1 << square.to_index());
+    }
+
+    /// Number of squares in the set
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Return true if the set contains no squares
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the squares in the set from a1 to h8
+    pub fn iter(self) -> impl Iterator<Item = SimpleSquare> {
+        (0..64)
+            .filter(move |index| self.0 & (1 << index) != 0)
+            .map(SimpleSquare::from_index)
+    }
+}
+
+/// Knight moves as `(file, rank)` offsets
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+
+/// Bitboards of the squares a knight attacks from each square, built at compile time
+const fn knight_attacks() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        let (file, rank) = ((index % 8) as i8, (index / 8) as i8);
+        let mut i = 0;
+        while i < KNIGHT_OFFSETS.len() {
+            let (file, rank) = (file + KNIGHT_OFFSETS[i].0, rank + KNIGHT_OFFSETS[i].1);
+            if 0 <= file && file < 8 && 0 <= rank && rank < 8 {
+                table[index] |= 1 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = knight_attacks();
+
+/// Squares a knight attacks from each square as bitboards, indexed by [`SimpleSquare::to_index`]
+///
+/// For reusing the precomputed table in your own bitboard code, with bits laid out as in
+/// [`SquareSet`].
+pub fn knight_attacks_table() -> &'static [u64; 64] {
+    &KNIGHT_ATTACKS
+}
+
+impl FromIterator<SimpleSquare> for SquareSet {
+    fn from_iter<T: IntoIterator<Item = SimpleSquare>>(iter: T) -> Self {
+        let mut set = Self::EMPTY;
+        for square in iter {
+            set.insert(square);
+        }
+        set
+    }
+}
+
+impl BitOr for SquareSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for SquareSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for SquareSet {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::ChessSquare as _;
+
+    #[test]
+    fn index_round_trip() {
+        for index in 0..64 {
+            assert_eq!(SimpleSquare::from_index(index).to_index(), index);
+        }
+        let h8 = SimpleSquare::from_index(63);
+        assert_eq!((h8.file(), h8.rank()), (7, 7));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a1 = SimpleSquare::new(0, 0);
+        let e4 = SimpleSquare::new(4, 3);
+        let mut set: SquareSet = [a1, e4].into_iter().collect();
+        assert_eq!(set.bits(), 1 | (1 << 28));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(e4));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [a1, e4]);
+        set.remove(a1);
+        assert_eq!(set & SquareSet::from_bits(1), SquareSet::EMPTY);
+        assert_eq!(set | !set, SquareSet::FULL);
+        assert!(!SquareSet::EMPTY.contains(a1));
+        assert!(SquareSet::EMPTY.is_empty());
+    }
+
+    #[test]
+    fn knight_attacks_table() {
+        let table = super::knight_attacks_table();
+        let d4 = SimpleSquare::new(3, 3);
+        assert_eq!(table[d4.to_index() as usize].count_ones(), 8);
+        let a1 = SquareSet::from_bits(table[0]);
+        assert_eq!(
+            a1.iter().collect::<Vec<_>>(),
+            [SimpleSquare::new(2, 1), SimpleSquare::new(1, 2)]
+        );
+    }
+}
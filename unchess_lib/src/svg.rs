@@ -0,0 +1,216 @@
+//! SVG diagrams of boards
+//!
+//! Produces self contained SVG documents with pieces drawn as Unicode chess glyphs, so no external
+//! images or fonts beyond one containing the chess symbols are needed.
+
+use std::fmt::Write as _;
+
+use crate::{
+    board::piece_list::PieceListBoard,
+    enums::{AmbiguousMove, PieceColour, PieceKind},
+    error::ChessError,
+    simple_types::SimpleSquare,
+    traits::{ChessBoard as _, ChessMove as _, ChessPiece as _, ChessSquare as _, LegalMoveGenerator as _},
+};
+
+/// Options for drawing SVG diagrams
+///
+/// By default squares are 45 pixels wide, white is at the bottom, and arrows are drawn in a
+/// translucent green.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgOptions {
+    square_size: u32,
+    flipped: bool,
+    light_colour: String,
+    dark_colour: String,
+    arrow_colour: String,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            square_size: 45,
+            flipped: false,
+            light_colour: "#f0d9b5".to_string(),
+            dark_colour: "#b58863".to_string(),
+            arrow_colour: "#15781bcc".to_string(),
+        }
+    }
+}
+
+impl SvgOptions {
+    /// Set the width of each square in pixels
+    pub fn square_size(mut self, square_size: u32) -> Self {
+        self.square_size = square_size;
+        self
+    }
+
+    /// Draw the board from black's side
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Set the fill colours of the light and dark squares, any SVG colour is accepted
+    pub fn square_colours(mut self, light: &str, dark: &str) -> Self {
+        self.light_colour = light.to_string();
+        self.dark_colour = dark.to_string();
+        self
+    }
+
+    /// Set the colour arrows are drawn in, any SVG colour is accepted
+    pub fn arrow_colour(mut self, colour: &str) -> Self {
+        self.arrow_colour = colour.to_string();
+        self
+    }
+
+    /// Top left corner of `square` in pixels
+    fn corner(&self, square: SimpleSquare) -> (u32, u32) {
+        let (column, row) = if self.flipped {
+            (7 - square.file(), square.rank())
+        } else {
+            (square.file(), 7 - square.rank())
+        };
+        (u32::from(column) * self.square_size, u32::from(row) * self.square_size)
+    }
+
+    /// Centre of `square` in pixels
+    fn centre(&self, square: SimpleSquare) -> (u32, u32) {
+        let (x, y) = self.corner(square);
+        (x + self.square_size / 2, y + self.square_size / 2)
+    }
+}
+
+/// Draw `board` as an SVG document
+pub fn to_svg(board: &PieceListBoard, options: &SvgOptions) -> String {
+    let mut svg = String::new();
+    write_board(&mut svg, board, options);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Draw `board` as an SVG document with an arrow showing `chess_move`
+///
+/// The move is resolved against `board`, so it must be legal there.
+///
+/// # Errors
+/// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state
+/// - [`crate::error::ChessError::ImpossibleMove`] if no legal moves match `chess_move`
+/// - [`crate::error::ChessError::AmbiguousMove`] if multiple legal moves match `chess_move`
+pub fn to_svg_with_move(
+    board: &PieceListBoard,
+    chess_move: AmbiguousMove,
+    options: &SvgOptions,
+) -> Result<String, ChessError> {
+    let chess_move = board.disambiguate_move(chess_move)?;
+    let mut svg = String::new();
+    write_board(&mut svg, board, options);
+    let (x1, y1) = options.centre(chess_move.src());
+    let (x2, y2) = options.centre(chess_move.dest());
+    let width = options.square_size / 5;
+    // Stop short of the centre so the arrowhead, 2 stroke widths long, ends on it
+    let length = f64::from(x1.abs_diff(x2)).hypot(f64::from(y1.abs_diff(y2)));
+    let shorten = 2.0 * f64::from(width) / length;
+    let (x2, y2) = (
+        f64::from(x2) - (f64::from(x2) - f64::from(x1)) * shorten,
+        f64::from(y2) - (f64::from(y2) - f64::from(y1)) * shorten,
+    );
+    write!(
+        svg,
+        r##"<defs><marker id="arrowhead" markerWidth="2" markerHeight="2" refX="0" refY="1" orient="auto"><path d="M0,0 L2,1 L0,2 z" fill="{colour}"/></marker></defs><line x1="{x1}" y1="{y1}" x2="{x2:.1}" y2="{y2:.1}" stroke="{colour}" stroke-width="{width}" marker-end="url(#arrowhead)"/>"##,
+        colour = options.arrow_colour,
+    )
+    .unwrap();
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// Write the opening tag, squares and pieces, leaving the document open for overlays
+fn write_board(svg: &mut String, board: &PieceListBoard, options: &SvgOptions) {
+    let size = options.square_size * 8;
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}" width="{size}" height="{size}">"#
+    )
+    .unwrap();
+    for rank in 0..8 {
+        for file in 0..8 {
+            let (x, y) = options.corner(SimpleSquare::new(file, rank));
+            let fill = match (file + rank) % 2 {
+                0 => &options.dark_colour,
+                _ => &options.light_colour,
+            };
+            write!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{square}" height="{square}" fill="{fill}"/>"#,
+                square = options.square_size
+            )
+            .unwrap();
+        }
+    }
+    for piece in board.all_pieces() {
+        let (x, y) = options.centre(piece.square());
+        write!(
+            svg,
+            r#"<text x="{x}" y="{y}" font-size="{font}" text-anchor="middle" dominant-baseline="central">{glyph}</text>"#,
+            font = options.square_size * 4 / 5,
+            glyph = glyph(piece.colour(), piece.kind()),
+        )
+        .unwrap();
+    }
+}
+
+/// Unicode chess symbol for a piece
+pub(crate) fn glyph(colour: PieceColour, kind: PieceKind) -> char {
+    match (colour, kind) {
+        (PieceColour::White, PieceKind::King) => '♔',
+        (PieceColour::White, PieceKind::Queen) => '♕',
+        (PieceColour::White, PieceKind::Rook) => '♖',
+        (PieceColour::White, PieceKind::Bishop) => '♗',
+        (PieceColour::White, PieceKind::Knight) => '♘',
+        (PieceColour::White, PieceKind::Pawn) => '♙',
+        (PieceColour::Black, PieceKind::King) => '♚',
+        (PieceColour::Black, PieceKind::Queen) => '♛',
+        (PieceColour::Black, PieceKind::Rook) => '♜',
+        (PieceColour::Black, PieceKind::Bishop) => '♝',
+        (PieceColour::Black, PieceKind::Knight) => '♞',
+        (PieceColour::Black, PieceKind::Pawn) => '♟',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+
+    #[test]
+    fn starting_board_svg() {
+        let svg = to_svg(&PieceListBoard::starting_board(), &SvgOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+        assert_eq!(svg.matches("<text").count(), 32);
+        assert!(!svg.contains("<line"));
+    }
+
+    #[test]
+    fn move_arrow() {
+        let board = PieceListBoard::starting_board();
+        let chess_move = notation::pgn_to_moves("1. Nf3").unwrap()[0];
+        let svg = to_svg_with_move(&board, chess_move, &SvgOptions::default()).unwrap();
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains(r#"<line x1="292" y1="337""#));
+        let flipped = to_svg_with_move(&board, chess_move, &SvgOptions::default().flipped(true)).unwrap();
+        assert!(flipped.contains(r#"<line x1="67" y1="22""#));
+    }
+
+    #[test]
+    fn impossible_move_arrow() {
+        let board = PieceListBoard::starting_board();
+        let chess_move = notation::pgn_to_moves("1. Nd4").unwrap()[0];
+        assert!(matches!(
+            to_svg_with_move(&board, chess_move, &SvgOptions::default()),
+            Err(ChessError::ImpossibleMove(_))
+        ));
+    }
+}
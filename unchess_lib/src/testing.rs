@@ -0,0 +1,115 @@
+//! Helpers for checking this crate against external references
+//!
+//! Only available with the `testing` feature, since they shell out to other programs.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, BufRead as _, BufReader, Write as _},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    board::piece_list::PieceListBoard,
+    traits::{ChessMove, LegalMoveGenerator as _},
+};
+
+/// Environment variable naming the UCI engine to compare against
+pub const ENGINE_VAR: &str = "UNCHESS_UCI_ENGINE";
+
+/// Move where this crate's perft divide disagrees with the engine's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftDifference {
+    /// Move in UCI long algebraic notation, e.g. `e7e8q`
+    pub chess_move: String,
+    /// Nodes counted by the engine, `None` if the engine didn't generate the move
+    pub engine: Option<u64>,
+    /// Nodes counted by this crate, `None` if this crate didn't generate the move
+    pub unchess: Option<u64>,
+}
+
+/// Find a UCI engine to compare against
+///
+/// Uses the path in [`ENGINE_VAR`] if set, otherwise looks for `stockfish` on the `PATH`.
+pub fn find_engine() -> Option<PathBuf> {
+    if let Some(engine) = env::var_os(ENGINE_VAR) {
+        return Some(PathBuf::from(engine));
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join("stockfish"))
+        .find(|path| path.is_file())
+}
+
+/// Move in UCI long algebraic notation
+pub fn uci_move_str(chess_move: &impl ChessMove) -> String {
+    chess_move.to_uci_str()
+}
+
+/// Run `go perft depth` on `engine` from `fen`, returning its node count for each move
+///
+/// # Errors
+/// - [`io::Error`] if the engine can't be run or exits before finishing the perft
+pub fn engine_perft_divide(engine: &PathBuf, fen: &str, depth: u32) -> io::Result<BTreeMap<String, u64>> {
+    let mut child = Command::new(engine)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("engine has no stdin"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("engine has no stdout"))?;
+    writeln!(stdin, "uci\nposition fen {fen}\ngo perft {depth}")?;
+    let mut divide = BTreeMap::new();
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if line.starts_with("Nodes searched") {
+            writeln!(stdin, "quit")?;
+            child.wait()?;
+            return Ok(divide);
+        }
+        if let Some((chess_move, nodes)) = line.split_once(": ")
+            && let Ok(nodes) = nodes.trim().parse()
+        {
+            divide.insert(chess_move.to_owned(), nodes);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "engine exited before finishing perft",
+    ))
+}
+
+/// Compare this crate's perft divide for `board` with `engine`'s
+///
+/// Returns every move where the node counts differ, so an empty result means move generation
+/// agrees with the engine to `depth` plies.
+///
+/// # Errors
+/// - [`io::Error`] if the engine can't be run, or this crate fails to generate moves for `board`
+pub fn compare_perft(engine: &PathBuf, board: &PieceListBoard, depth: u32) -> io::Result<Vec<PerftDifference>> {
+    let fen = board.as_fen_str().map_err(io::Error::other)?;
+    let mut engine = engine_perft_divide(engine, &fen, depth)?;
+    let mut differences = vec![];
+    for (chess_move, nodes) in board.perft_divide(depth).map_err(io::Error::other)? {
+        let chess_move = uci_move_str(&chess_move);
+        let engine_nodes = engine.remove(&chess_move);
+        if engine_nodes != Some(nodes) {
+            differences.push(PerftDifference {
+                chess_move,
+                engine: engine_nodes,
+                unchess: Some(nodes),
+            });
+        }
+    }
+    differences.extend(engine.into_iter().map(|(chess_move, nodes)| PerftDifference {
+        chess_move,
+        engine: Some(nodes),
+        unchess: None,
+    }));
+    Ok(differences)
+}
@@ -6,13 +6,15 @@
 //! assumed that its internal representation was two integers, this would create massive overhead
 //! when interacting with bitboards.
 
+use std::cmp::Reverse;
 use std::fmt::Write as _;
 
-use crate::enums::{AmbiguousMove, BoardState, PieceColour, PieceKind};
+use crate::enums::{AmbiguousMove, BoardState, CastlingSide, DrawReason, MoveAction, PieceColour, PieceKind};
 use crate::error::ChessError;
 use crate::notation;
-use crate::parser::fen::{Fen, fen as fen_parser};
+use crate::parser::fen::{Fen, board_lenient, fen as fen_parser, placement_only};
 use crate::parser::pgn::chess_move as chess_move_parser;
+use crate::simple_types::{SimpleMove, SimpleSquare};
 
 /// Generic chess square
 ///
@@ -67,6 +69,15 @@ pub trait ChessMove {
         }
         s
     }
+
+    /// Returns move in UCI long algebraic notation, e.g. `e7e8q`
+    fn to_uci_str(&self) -> String {
+        let mut s = format!("{}{}", self.src().as_str(), self.dest().as_str());
+        if let Some(piece) = self.promote_to() {
+            s.push(char::from(piece).to_ascii_lowercase());
+        }
+        s
+    }
 }
 
 /// Generic piece
@@ -126,8 +137,12 @@ pub trait ChessBoard: From<Fen> {
 
     /// Generate board from FEN standard string
     ///
+    /// Boards may also reject positions that can't arise in a game, such as an en passant square
+    /// no pawn could have just skipped.
+    ///
     /// # Errors
-    /// [`crate::error::ChessError::InvalidFEN`] If FEN isn't valid syntax
+    /// [`crate::error::ChessError::InvalidFEN`] If FEN isn't valid syntax or describes a position
+    /// the board rejects
     fn from_fen(fen: &str) -> Result<Self, ChessError>
     where
         Self: Sized,
@@ -139,6 +154,45 @@ pub trait ChessBoard: From<Fen> {
         }
     }
 
+    /// Generate board from FEN string with fields separated by spaces or underscores
+    ///
+    /// Accepts FENs taken from URLs, where spaces are often replaced with underscores, e.g.
+    /// `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR_b_KQkq_e3_0_1`. Boards may correct positions
+    /// [`ChessBoard::from_fen`] would reject rather than failing.
+    ///
+    /// # Errors
+    /// [`crate::error::ChessError::InvalidFEN`] If FEN isn't valid syntax
+    fn from_fen_lenient(fen: &str) -> Result<Self, ChessError>
+    where
+        Self: Sized,
+    {
+        if let Ok(fen) = board_lenient(fen) {
+            Ok(Self::from(fen.1))
+        } else {
+            Err(ChessError::InvalidFEN(fen.to_string()))
+        }
+    }
+
+    /// Generate board from just the piece placement field of a FEN, e.g. from a diagram
+    ///
+    /// White is to move with no castling rights or en passant square.
+    ///
+    /// # Errors
+    /// [`crate::error::ChessError::InvalidFEN`] If the placement isn't valid syntax or is followed
+    /// by other fields
+    fn from_placement(placement: &str) -> Result<Self, ChessError>
+    where
+        Self: Sized,
+    {
+        placement_only(placement)
+    }
+
+    /// Write board out as a FEN string
+    ///
+    /// # Errors
+    /// [`crate::error::ChessError::InvalidBoard`] If board in invalid state
+    fn to_fen(&self) -> Result<String, ChessError>;
+
     /// Return piece at `square`
     ///
     /// Returns none if no piece present.
@@ -240,6 +294,17 @@ pub trait LegalMoveGenerator: PLegalMoveGenerator {
     /// - [`crate::error::ChessError::PieceNotFound`] if no piece present at `square`
     fn piece_legal_moves(&self, square: Self::Square) -> Result<impl IntoIterator<Item = Self::Move>, ChessError>;
 
+    /// Return all legal moves as if it were `colour`'s turn
+    ///
+    /// Leaves the board unchanged, useful for finding what the side not to move is threatening. If
+    /// `colour` isn't the side to move there can be no en passant captures.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of `colour` or there is not one king of each colour on the
+    ///   board.
+    fn legal_moves_for(&self, colour: PieceColour) -> Result<impl IntoIterator<Item = Self::Move>, ChessError>;
+
     /// Return true if move `chess_move` is legal
     ///
     /// # Errors
@@ -249,6 +314,25 @@ pub trait LegalMoveGenerator: PLegalMoveGenerator {
     /// - [`crate::error::ChessError::PieceNotFound`] if no piece present at `chess_move.src()`
     fn is_move_legal(&self, chess_move: Self::Move) -> Result<bool, ChessError>;
 
+    /// Return true if `chess_move` would leave the opponent's king in check
+    ///
+    /// Does not check that `chess_move` is legal.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    /// - [`crate::error::ChessError::PieceNotFound`] if no piece present at `chess_move.src()`
+    fn gives_check(&self, chess_move: Self::Move) -> Result<bool, ChessError>;
+
+    /// Return all legal moves that give check
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn checking_moves(&self) -> Result<impl IntoIterator<Item = Self::Move>, ChessError>;
+
     /// Move piece if move is legal, otherwise error
     ///
     /// # Errors
@@ -261,12 +345,260 @@ pub trait LegalMoveGenerator: PLegalMoveGenerator {
 
     /// Get current board state
     ///
+    /// Checkmate and stalemate take precedence over draws, since they end the game at once.
+    ///
     /// # Errors
     /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
     ///   example if there are no pieces of the colour of the current turn or there is not one king
     ///   of each colour on the board.
     fn state(&self) -> Result<BoardState, ChessError>;
 
+    /// Why the game is drawn in the current position, if it is
+    ///
+    /// Covers threefold and fivefold repetition, the fifty and seventy-five move rules and
+    /// insufficient material. Threefold repetition and the fifty move rule would have to be claimed
+    /// over the board, but are still reported.
+    fn draw_reason(&self) -> Option<DrawReason>;
+
+    /// Count the leaf nodes of the legal move tree `depth` plies deep
+    ///
+    /// The standard check of move generator correctness, since counts for many positions are well
+    /// known. A `depth` of 0 counts the current position as a single node.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn perft(&self, depth: u32) -> Result<u64, ChessError>
+    where
+        Self: Clone,
+        Self::Move: Copy,
+    {
+        match depth {
+            0 => Ok(1),
+            _ => Ok(self.perft_divide(depth)?.into_iter().map(|(_, nodes)| nodes).sum()),
+        }
+    }
+
+    /// Count the leaf nodes `depth` plies deep under each legal move
+    ///
+    /// Comparing against another generator's divide narrows down which move a difference in
+    /// [`LegalMoveGenerator::perft`] comes from. Empty if `depth` is 0.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn perft_divide(&self, depth: u32) -> Result<Vec<(Self::Move, u64)>, ChessError>
+    where
+        Self: Clone,
+        Self::Move: Copy,
+    {
+        let Some(depth) = depth.checked_sub(1) else {
+            return Ok(vec![]);
+        };
+        self.all_legal_moves()?
+            .into_iter()
+            .map(|chess_move| {
+                let mut board = self.clone();
+                board.move_piece(chess_move)?;
+                Ok((chess_move, board.perft(depth)?))
+            })
+            .collect()
+    }
+
+    /// Count the nodes of the legal move tree at each depth from 1 to `depth` plies
+    ///
+    /// Element `i` is [`LegalMoveGenerator::perft`] at depth `i + 1`, counted in a single walk of
+    /// the tree, showing how quickly it branches. Empty if `depth` is 0.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn branching_stats(&self, depth: u32) -> Result<Vec<u64>, ChessError>
+    where
+        Self: Clone,
+    {
+        let mut counts = vec![0; depth as usize];
+        count_branches(self, &mut counts)?;
+        Ok(counts)
+    }
+
+    /// Hash of every line of legal moves `depth` plies deep, for regression testing move generation
+    ///
+    /// Each line is hashed from the squares and promotions of its moves, and the hashes of all lines
+    /// are summed, so the result doesn't depend on the order moves are generated in but does change
+    /// if moves are swapped for others when [`LegalMoveGenerator::perft`] counts happen to match.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn perft_hash(&self, depth: u32) -> Result<u64, ChessError>
+    where
+        Self: Clone,
+    {
+        hash_lines(self, depth, 0)
+    }
+
+    /// Convert `chess_move` into the shortest [`AmbiguousMove`] identifying it, as written in SAN
+    ///
+    /// Only includes as much of the source square as is needed to tell it apart from other legal
+    /// moves, along with whether the move is a capture and whether it gives check or checkmate.
+    /// The inverse of [`LegalMoveGenerator::disambiguate_move`].
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    /// - [`crate::error::ChessError::PieceNotFound`] if no piece present at `chess_move.src()`
+    /// - [`crate::error::ChessError::IllegalMove`] if `chess_move` isn't legal
+    fn ambiguous_move(&self, chess_move: Self::Move) -> Result<AmbiguousMove, ChessError>
+    where
+        Self: Clone,
+        Self::Move: Copy + ChessMove<Square = Self::Square>,
+    {
+        let legal_moves: Vec<Self::Move> = self.all_legal_moves()?.into_iter().collect();
+        let (src, dest) = (chess_move.src(), chess_move.dest());
+        let is_legal = legal_moves.iter().any(|legal| {
+            (
+                legal.src().file(),
+                legal.src().rank(),
+                legal.dest().file(),
+                legal.dest().rank(),
+                legal.promote_to(),
+            ) == (
+                src.file(),
+                src.rank(),
+                dest.file(),
+                dest.rank(),
+                chess_move.promote_to(),
+            )
+        });
+        if !is_legal {
+            self.get_piece(chess_move.src())?;
+            return Err(ChessError::IllegalMove(SimpleMove::new(
+                SimpleSquare::new(src.file(), src.rank()),
+                SimpleSquare::new(dest.file(), dest.rank()),
+                chess_move.promote_to(),
+            )));
+        }
+        let mut board = self.clone();
+        board.move_piece(chess_move)?;
+        ambiguous_move(self, &legal_moves, &chess_move, &board)
+    }
+
+    /// Return all legal moves ordered captures first, then promotions, then quiet moves
+    ///
+    /// Captures are sorted most valuable victim first, breaking ties by least valuable attacker
+    /// (MVV-LVA), and promotions by the value of the piece promoted to. A reasonable default move
+    /// ordering for searches.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn ordered_moves(&self) -> Result<Vec<Self::Move>, ChessError>
+    where
+        Self::Move: ChessMove<Square = Self::Square>,
+    {
+        let mut moves = vec![];
+        for chess_move in self.all_legal_moves()? {
+            let (src, dest) = (chess_move.src(), chess_move.dest());
+            let attacker = self.get_piece(chess_move.src())?.kind();
+            let victim = match self.get_piece(chess_move.dest()) {
+                Ok(piece) => Some(piece.kind()),
+                // En passant
+                Err(_) if attacker == PieceKind::Pawn && src.file() != dest.file() => Some(PieceKind::Pawn),
+                Err(_) => None,
+            };
+            let key = match (victim, chess_move.promote_to()) {
+                (Some(victim), _) => (0, Reverse(victim.value_ord_key()), attacker.value_ord_key()),
+                (None, Some(promote_to)) => (1, Reverse(promote_to.value_ord_key()), 0),
+                (None, None) => (2, Reverse(0), 0),
+            };
+            moves.push((key, chess_move));
+        }
+        moves.sort_by_key(|&(key, _)| key);
+        Ok(moves.into_iter().map(|(_, chess_move)| chess_move).collect())
+    }
+
+    /// Return all legal moves in SAN, each with the board after it is played
+    ///
+    /// Saves replaying each move to show where it leads, for example when building a search tree.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn legal_moves_with_boards(&self) -> Result<Vec<(String, Self)>, ChessError>
+    where
+        Self: Clone,
+        Self::Move: Copy + ChessMove<Square = Self::Square>,
+    {
+        let legal_moves: Vec<Self::Move> = self.all_legal_moves()?.into_iter().collect();
+        legal_moves
+            .iter()
+            .map(|&chess_move| {
+                let mut board = self.clone();
+                board.move_piece(chess_move)?;
+                let san = ambiguous_move(self, &legal_moves, &chess_move, &board)?.as_pgn_str();
+                Ok((san, board))
+            })
+            .collect()
+    }
+
+    /// Return the SAN of every legal move in the order chess GUIs list them
+    ///
+    /// Sorted by the kind of piece moving, king first through to pawns last, then by destination
+    /// file and rank, so move menus are always listed the same way. Castling sorts as a king move
+    /// to the king's destination.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn legal_sans_sorted(&self) -> Result<Vec<String>, ChessError>
+    where
+        Self: Clone,
+        Self::Move: Copy + ChessMove<Square = Self::Square>,
+    {
+        let legal_moves: Vec<Self::Move> = self.all_legal_moves()?.into_iter().collect();
+        let mut sans = legal_moves
+            .iter()
+            .map(|&chess_move| {
+                let mut board = self.clone();
+                board.move_piece(chess_move)?;
+                let san = ambiguous_move(self, &legal_moves, &chess_move, &board)?.as_pgn_str();
+                let kind = self.get_piece(chess_move.src())?.kind();
+                let dest = chess_move.dest();
+                Ok(((Reverse(kind.value_ord_key()), dest.file(), dest.rank()), san))
+            })
+            .collect::<Result<Vec<_>, ChessError>>()?;
+        sans.sort();
+        Ok(sans.into_iter().map(|(_, san)| san).collect())
+    }
+
+    /// Return every legal move in coordinate form, such as `e2e4` or `e7e8q`
+    ///
+    /// Cheaper than SAN and never ambiguous, so handy for diffing against engine logs.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state, for
+    ///   example if there are no pieces of the colour of the current turn or there is not one king
+    ///   of each colour on the board.
+    fn legal_moves_coord_strings(&self) -> Result<Vec<String>, ChessError>
+    where
+        Self::Move: ChessMove,
+    {
+        Ok(self
+            .all_legal_moves()?
+            .into_iter()
+            .map(|chess_move| chess_move.to_uci_str())
+            .collect())
+    }
+
     /// Disambiguate AmbiguousMove type
     ///
     /// # Errors
@@ -292,4 +624,130 @@ pub trait LegalMoveGenerator: PLegalMoveGenerator {
             Err(ChessError::InvalidPGN(pgn.to_string()))
         }
     }
+
+    /// Return true if `san` is a legal move in the current position
+    ///
+    /// Input that can't be parsed, has trailing text or matches no legal move or several is not
+    /// legal, so untrusted input can be checked without handling errors.
+    fn is_legal_san(&self, san: &str) -> bool {
+        match chess_move_parser(san) {
+            Ok(("", chess_move)) => self
+                .disambiguate_move(chess_move)
+                .and_then(|chess_move| self.is_move_legal(chess_move))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+/// Add the number of nodes at each depth under `board` to the matching element of `counts`
+fn count_branches<B>(board: &B, counts: &mut [u64]) -> Result<(), ChessError>
+where
+    B: LegalMoveGenerator + Clone,
+{
+    let Some((count, deeper)) = counts.split_first_mut() else {
+        return Ok(());
+    };
+    for chess_move in board.all_legal_moves()? {
+        *count += 1;
+        let mut after = board.clone();
+        after.move_piece(chess_move)?;
+        count_branches(&after, deeper)?;
+    }
+    Ok(())
+}
+
+/// Sum of the hashes of the lines `depth` plies deep under `board`, reached by the line hashed as
+/// `line`
+fn hash_lines<B>(board: &B, depth: u32, line: u64) -> Result<u64, ChessError>
+where
+    B: LegalMoveGenerator + Clone,
+{
+    let Some(depth) = depth.checked_sub(1) else {
+        return Ok(line);
+    };
+    let mut hash: u64 = 0;
+    for chess_move in board.all_legal_moves()? {
+        let key = [chess_move.src(), chess_move.dest()].iter().fold(
+            chess_move.promote_to().map_or(0, |piece| u64::from(char::from(piece))),
+            |key, square| (key << 6) | u64::from(square.rank() * 8 + square.file()),
+        );
+        let mut after = board.clone();
+        after.move_piece(chess_move)?;
+        hash = hash.wrapping_add(hash_lines(&after, depth, mix(line ^ key))?);
+    }
+    Ok(hash)
+}
+
+/// SplitMix64 finaliser, spreading every input bit across the output
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Shortest [`AmbiguousMove`] for `chess_move` among `legal_moves`, given the board `after` it
+fn ambiguous_move<B>(
+    board: &B,
+    legal_moves: &[B::Move],
+    chess_move: &B::Move,
+    after: &B,
+) -> Result<AmbiguousMove, ChessError>
+where
+    B: LegalMoveGenerator,
+    B::Move: Copy + ChessMove<Square = B::Square>,
+{
+    let (src, dest) = (chess_move.src(), chess_move.dest());
+    let piece = board.get_piece(chess_move.src())?;
+    let piece_kind = piece.kind();
+    // Not taken from the board state, which reports a check into a draw as the draw
+    let action = if !board.gives_check(*chess_move)? {
+        None
+    } else if after.all_legal_moves()?.into_iter().next().is_none() {
+        Some(MoveAction::Checkmate)
+    } else {
+        Some(MoveAction::Check)
+    };
+    // Chess960 castling is written as the king taking its own rook
+    let takes_own_rook = board
+        .get_piece(chess_move.dest())
+        .is_ok_and(|other| other.kind() == PieceKind::Rook && other.colour() == piece.colour());
+    if piece_kind == PieceKind::King && (src.file().abs_diff(dest.file()) == 2 || takes_own_rook) {
+        let side = if dest.file() > src.file() {
+            CastlingSide::KingSide
+        } else {
+            CastlingSide::QueenSide
+        };
+        return Ok(AmbiguousMove::Castle { side, action });
+    }
+    let takes =
+        board.get_piece(chess_move.dest()).is_ok() || (piece_kind == PieceKind::Pawn && src.file() != dest.file());
+    let mut rivals = vec![];
+    for other in legal_moves {
+        let (other_src, other_dest) = (other.src(), other.dest());
+        if (other_dest.file(), other_dest.rank()) == (dest.file(), dest.rank())
+            && (other_src.file(), other_src.rank()) != (src.file(), src.rank())
+            && board.get_piece(other.src())?.kind() == piece_kind
+        {
+            rivals.push((other_src.file(), other_src.rank()));
+        }
+    }
+    let (src_file, src_rank) = match piece_kind {
+        PieceKind::Pawn if takes => (Some(src.file()), None),
+        PieceKind::Pawn => (None, None),
+        _ if rivals.is_empty() => (None, None),
+        _ if rivals.iter().all(|&(file, _)| file != src.file()) => (Some(src.file()), None),
+        _ if rivals.iter().all(|&(_, rank)| rank != src.rank()) => (None, Some(src.rank())),
+        _ => (Some(src.file()), Some(src.rank())),
+    };
+    Ok(AmbiguousMove::Normal {
+        piece_kind,
+        src_file,
+        src_rank,
+        takes,
+        dest: SimpleSquare::new(dest.file(), dest.rank()),
+        promote_to: chess_move.promote_to(),
+        action,
+    })
 }
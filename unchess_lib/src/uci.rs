@@ -0,0 +1,356 @@
+//! Driving external engines over UCI
+//!
+//! An [`Engine`] runs an engine binary such as Stockfish as a child process, speaking the
+//! Universal Chess Interface over its standard input and output. Moves are passed to and from the
+//! engine as [`SimpleMove`]s, which convert to SAN with
+//! [`crate::traits::LegalMoveGenerator::ambiguous_move`].
+
+use std::ffi::OsStr;
+use std::io::{BufRead as _, BufReader, Lines, Write as _};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+use crate::comment::Evaluation;
+use crate::enums::PieceColour;
+use crate::error::ChessError;
+use crate::parser::uci::{best_move, info};
+use crate::simple_types::SimpleMove;
+use crate::traits::{ChessBoard, ChessMove as _};
+
+/// When an engine should stop searching
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SearchLimit {
+    /// Search this many plies deep, `go depth`
+    Depth(u32),
+    /// Search for this long, `go movetime`
+    MoveTime(Duration),
+    /// Search this many nodes, `go nodes`
+    Nodes(u64),
+}
+
+impl SearchLimit {
+    /// The `go` command searching to this limit
+    pub fn as_go_str(&self) -> String {
+        match self {
+            SearchLimit::Depth(depth) => format!("go depth {depth}"),
+            SearchLimit::MoveTime(time) => format!("go movetime {}", time.as_millis()),
+            SearchLimit::Nodes(nodes) => format!("go nodes {nodes}"),
+        }
+    }
+}
+
+/// Search progress reported by an engine in an `info` line
+///
+/// Fields the engine didn't include are none, and fields this crate doesn't use are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Info {
+    /// Depth searched in plies
+    pub depth: Option<u32>,
+    /// Which of several principal variations this is, counting from 1
+    pub multipv: Option<u32>,
+    /// Score from the point of view of the side to move, as engines report it
+    pub score: Option<Evaluation>,
+    /// Nodes searched
+    pub nodes: Option<u64>,
+    /// Time spent searching
+    pub time: Option<Duration>,
+    /// Principal variation, the moves the engine expects to be played
+    pub pv: Vec<SimpleMove>,
+    /// Free text the engine wants displayed
+    pub string: Option<String>,
+}
+
+/// Outcome of a search
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Move the engine would play, none if it has no legal move
+    pub best_move: Option<SimpleMove>,
+    /// Reply the engine expects to the best move
+    pub ponder: Option<SimpleMove>,
+    /// Every `info` line sent during the search, in order
+    pub info: Vec<Info>,
+}
+
+impl SearchResult {
+    /// Last score reported for the main line, from the point of view of the side to move
+    pub fn score(&self) -> Option<Evaluation> {
+        self.info
+            .iter()
+            .rev()
+            .filter(|info| info.multipv.is_none_or(|multipv| multipv == 1))
+            .find_map(|info| info.score)
+    }
+
+    /// Last score reported for the main line from white's point of view, with `turn` to move
+    ///
+    /// As stored in `[%eval]` comments.
+    pub fn evaluation(&self, turn: PieceColour) -> Option<Evaluation> {
+        self.score().map(|score| match (turn, score) {
+            (PieceColour::White, _) => score,
+            (PieceColour::Black, Evaluation::Centipawns(centipawns)) => Evaluation::Centipawns(-centipawns),
+            (PieceColour::Black, Evaluation::Mate(moves)) => Evaluation::Mate(-moves),
+        })
+    }
+}
+
+/// Engine running as a child process
+///
+/// The engine is sent `quit` and waited for when dropped.
+#[derive(Debug)]
+pub struct Engine {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    name: Option<String>,
+}
+
+impl Engine {
+    /// Start the engine at `program` and wait for it to be ready
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be started or talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before finishing the
+    ///   handshake
+    pub fn spawn(program: impl AsRef<OsStr>) -> Result<Self, ChessError> {
+        Self::from_command(Command::new(program))
+    }
+
+    /// Start the engine run by `command`, for passing arguments or a working directory, and wait
+    /// for it to be ready
+    ///
+    /// Standard input and output are replaced with pipes to talk to the engine.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be started or talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before finishing the
+    ///   handshake
+    pub fn from_command(mut command: Command) -> Result<Self, ChessError> {
+        let mut process = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            return Err(ChessError::EngineExited("uciok".to_string()));
+        };
+        let mut engine = Self {
+            process,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            name: None,
+        };
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line("uciok")?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            } else {
+                // Options and authors aren't needed to drive the engine
+            }
+        }
+        Ok(engine)
+    }
+
+    /// Name the engine gave itself, if any
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Set engine option `name` to `value`, e.g. `Threads` to `4`
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), ChessError> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    /// Tell the engine the next position is from a different game, and wait for it to be ready
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before it was ready
+    pub fn new_game(&mut self) -> Result<(), ChessError> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    /// Wait until the engine has finished handling every command sent so far
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before it was ready
+    pub fn wait_ready(&mut self) -> Result<(), ChessError> {
+        self.send("isready")?;
+        while self.read_line("readyok")?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Set the position to search, as `moves` played from `fen`, or the starting position if none
+    ///
+    /// Sending the moves of a game rather than just its final position lets the engine see
+    /// repetitions.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    pub fn set_position(&mut self, fen: Option<&str>, moves: &[SimpleMove]) -> Result<(), ChessError> {
+        let mut command = match fen {
+            Some(fen) => format!("position fen {fen}"),
+            None => "position startpos".to_string(),
+        };
+        if !moves.is_empty() {
+            command.push_str(" moves");
+            for chess_move in moves {
+                command.push(' ');
+                command.push_str(&chess_move.to_uci_str());
+            }
+        }
+        self.send(&command)
+    }
+
+    /// Search the current position until `limit` is reached
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before sending its best
+    ///   move
+    /// - [`crate::error::ChessError::InvalidUCI`] if the engine's best move isn't valid UCI
+    pub fn go(&mut self, limit: SearchLimit) -> Result<SearchResult, ChessError> {
+        self.send(&limit.as_go_str())?;
+        let mut result = SearchResult::default();
+        loop {
+            let line = self.read_line("bestmove")?;
+            let line = line.trim();
+            if let Ok((_, line)) = info(line) {
+                result.info.push(line);
+            } else if let Ok((_, (best, ponder))) = best_move(line) {
+                result.best_move = best;
+                result.ponder = ponder;
+                return Ok(result);
+            } else if line.starts_with("bestmove") {
+                return Err(ChessError::InvalidUCI(line.to_string()));
+            } else {
+                // Anything else an engine sends while searching can be ignored
+            }
+        }
+    }
+
+    /// Search `board` until `limit` is reached
+    ///
+    /// Only the position is sent, not the moves leading to it, so the engine can't see
+    /// repetitions.
+    ///
+    /// # Errors
+    /// - [`crate::error::ChessError::InvalidBoard`] if the board is in an invalid state
+    /// - [`crate::error::ChessError::Io`] if the engine couldn't be talked to
+    /// - [`crate::error::ChessError::EngineExited`] if the engine exited before sending its best
+    ///   move
+    /// - [`crate::error::ChessError::InvalidUCI`] if the engine's best move isn't valid UCI
+    pub fn analyse<B: ChessBoard>(&mut self, board: &B, limit: SearchLimit) -> Result<SearchResult, ChessError> {
+        self.set_position(Some(&board.to_fen()?), &[])?;
+        self.go(limit)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), ChessError> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Next line from the engine, failing if it exits while `expected` is awaited
+    fn read_line(&mut self, expected: &str) -> Result<String, ChessError> {
+        match self.stdout.next() {
+            Some(line) => Ok(line?),
+            None => Err(ChessError::EngineExited(expected.to_string())),
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // Errors can't be reported from drop, and only mean the engine has already exited
+        if self.send("quit").is_ok() {
+            drop(self.process.wait());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::piece_list::PieceListBoard;
+
+    #[test]
+    fn go_commands() {
+        assert_eq!(SearchLimit::Depth(20).as_go_str(), "go depth 20");
+        assert_eq!(
+            SearchLimit::MoveTime(Duration::from_secs(2)).as_go_str(),
+            "go movetime 2000"
+        );
+        assert_eq!(SearchLimit::Nodes(100_000).as_go_str(), "go nodes 100000");
+    }
+
+    #[test]
+    fn evaluation_from_whites_view() {
+        let result = SearchResult {
+            info: vec![
+                Info {
+                    score: Some(Evaluation::Centipawns(40)),
+                    ..Info::default()
+                },
+                Info {
+                    multipv: Some(2),
+                    score: Some(Evaluation::Centipawns(-10)),
+                    ..Info::default()
+                },
+            ],
+            ..SearchResult::default()
+        };
+        assert_eq!(result.score(), Some(Evaluation::Centipawns(40)));
+        assert_eq!(result.evaluation(PieceColour::Black), Some(Evaluation::Centipawns(-40)));
+        assert_eq!(SearchResult::default().evaluation(PieceColour::White), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scripted_engine() {
+        use crate::traits::ChessBoard as _;
+
+        let script = r#"
+            while read -r line; do
+                case "$line" in
+                    uci) echo "id name Scripted 1.0"; echo "option name Hash type spin"; echo uciok ;;
+                    isready) echo readyok ;;
+                    "position fen "*) echo "info string $line" ;;
+                    go*) echo "info depth 1 score cp 25 pv e7e5"
+                         echo "info depth 2 score mate 3 pv e7e5 g1f3"
+                         echo "bestmove e7e5 ponder g1f3" ;;
+                    quit) exit 0 ;;
+                esac
+            done
+        "#;
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        let mut engine = Engine::from_command(command).unwrap();
+        assert_eq!(engine.name(), Some("Scripted 1.0"));
+        engine.set_option("Hash", "16").unwrap();
+        engine.new_game().unwrap();
+        let board = PieceListBoard::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let result = engine.analyse(&board, SearchLimit::Depth(2)).unwrap();
+        assert_eq!(
+            result.info[0].string.as_deref(),
+            Some("position fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+        );
+        assert_eq!(result.best_move, Some(SimpleMove::from_uci_str("e7e5").unwrap()));
+        assert_eq!(result.ponder, Some(SimpleMove::from_uci_str("g1f3").unwrap()));
+        assert_eq!(result.evaluation(PieceColour::Black), Some(Evaluation::Mate(-3)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engine_exits_early() {
+        let mut command = Command::new("true");
+        command.arg("uci");
+        assert!(matches!(
+            Engine::from_command(command),
+            Err(ChessError::EngineExited(_) | ChessError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+//! Zobrist hashing keys
+//!
+//! Keys are generated at compile time from a fixed seed so hashes are stable between runs and
+//! platforms, making them usable as storage keys as well as for transposition tables.
+
+use crate::enums::{CastlingSide, PieceColour, PieceKind};
+use crate::traits::{ChessPiece, ChessSquare};
+
+const PIECE_KEYS: usize = 12 * 64;
+const TURN_KEY: usize = PIECE_KEYS;
+const CASTLING_KEYS: usize = TURN_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const NUM_KEYS: usize = EN_PASSANT_KEYS + 8;
+
+/// Fill the key table with SplitMix64 output
+const fn generate_keys() -> [u64; NUM_KEYS] {
+    let mut keys = [0; NUM_KEYS];
+    let mut state: u64 = 0x756E_6368_6573_7321;
+    let mut i = 0;
+    while i < NUM_KEYS {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: [u64; NUM_KEYS] = generate_keys();
+
+/// Key for `piece` standing on `square`
+pub fn piece_key(piece: &impl ChessPiece, square: &impl ChessSquare) -> u64 {
+    let kind = match piece.kind() {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    };
+    let colour = match piece.colour() {
+        PieceColour::White => 0,
+        PieceColour::Black => 6,
+    };
+    KEYS[(colour + kind) * 64 + square.rank() as usize * 8 + square.file() as usize]
+}
+
+/// Key included when it is white's turn
+pub fn turn_key() -> u64 {
+    KEYS[TURN_KEY]
+}
+
+/// Key for `colour` having the right to castle on `side`
+pub fn castling_key(colour: PieceColour, side: CastlingSide) -> u64 {
+    let colour = match colour {
+        PieceColour::White => 0,
+        PieceColour::Black => 2,
+    };
+    let side = match side {
+        CastlingSide::KingSide => 0,
+        CastlingSide::QueenSide => 1,
+    };
+    KEYS[CASTLING_KEYS + colour + side]
+}
+
+/// Key for an en passant square on `file`
+pub fn en_passant_key(file: u8) -> u64 {
+    KEYS[EN_PASSANT_KEYS + file as usize]
+}
@@ -0,0 +1,29 @@
+//! Integration tests comparing move generation against a UCI engine
+//!
+//! Run with `cargo test --features testing -- --ignored`, optionally setting `UNCHESS_UCI_ENGINE`
+//! to the engine's path.
+#![cfg(feature = "testing")]
+#![allow(clippy::tests_outside_test_module)]
+use unchess_lib::board::piece_list::PieceListBoard;
+use unchess_lib::testing::{compare_perft, find_engine};
+use unchess_lib::traits::ChessBoard as _;
+
+const POSITIONS: [&str; 5] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "8/8/8/8/8/8/8/KN5k w - - 0 1",
+];
+
+#[test]
+#[ignore = "requires a UCI engine"]
+fn perft_matches_engine() {
+    let Some(engine) = find_engine() else {
+        return;
+    };
+    for fen in POSITIONS {
+        let board = PieceListBoard::from_fen(fen).unwrap();
+        assert_eq!(compare_perft(&engine, &board, 3).unwrap(), vec![], "{fen}");
+    }
+}